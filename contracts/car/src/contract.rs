@@ -1,22 +1,27 @@
 // car_nft/src/contract.rs
 
 use cosmwasm_std::{
-    entry_point, to_json_binary, Binary, Coin, Deps, DepsMut, Env, MessageInfo, Response,
-    StdResult, Uint128, WasmMsg,
+    entry_point, to_json_binary, Binary, Coin, Deps, DepsMut, Env, MessageInfo, Order, Response,
+    StdError, StdResult, Uint128, WasmMsg,
 };
 use cw2::set_contract_version;
 use cw721_base::{Cw721Contract, ExecuteMsg as Cw721ExecuteMsg, InstantiateMsg as Cw721InstantiateMsg, MintMsg};
 
 use crate::error::CarError;
 use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::state::{CAR_ID_COUNTER, CONFIG, PENDING_OWNER};
+use crate::state::{CAR_ID_COUNTER, CAR_INFO, CONFIG, PENDING_OWNER, Q_TABLE, RACE_RECORDS};
 use racing::types::CarMetadata;
-use racing::car::Config;
+use racing::car::{Config, GetCarInfoResponse};
 use racing::traits_engine::{default_rarity_table, generate_traits_with_rarity, traits_to_attributes};
 
 const CONTRACT_NAME: &str = "car_nft";
 const CONTRACT_VERSION: &str = "0.1.0";
 
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 100;
+
+const MAX_ROYALTY_BPS: u16 = 10_000;
+
 // Plug our extension into cw721-base
 pub type CarCw721<'a> = Cw721Contract<'a, Option<CarMetadata>, cosmwasm_std::Empty, cosmwasm_std::Empty, cosmwasm_std::Empty>;
 
@@ -35,7 +40,7 @@ pub fn instantiate(
     // Save owner and payment options
     let owner = info.sender.clone();
     let payment_options = msg.payment_options.unwrap_or_default();
-    CONFIG.save(deps.storage, &Config { owner: owner.clone(), payment_options })?;
+    CONFIG.save(deps.storage, &Config { owner: owner.clone(), payment_options, race_engine: None })?;
 
     // Set minter to this contract address so only self-calls can mint
     let cw_msg = Cw721InstantiateMsg {
@@ -68,8 +73,10 @@ pub fn execute(
                 .map_err(CarError::from)
         }
         ExecuteMsg::MintCar { owner, token_uri, extension } => execute_mint_car(deps, env, info, owner, token_uri, extension),
-        ExecuteMsg::UpdateConfig { payment_options, new_owner } => execute_update_config(deps, info, payment_options, new_owner),
+        ExecuteMsg::UpdateConfig { payment_options, new_owner, race_engine } => execute_update_config(deps, info, payment_options, new_owner, race_engine),
         ExecuteMsg::UpdateCustomDecal { token_id, svg } => execute_update_custom_decal(deps, info, token_id, svg),
+        ExecuteMsg::Burn { car_id } => execute_burn(deps, info, car_id),
+        ExecuteMsg::RecordRaceOutcome { car_id, won } => execute_record_race_outcome(deps, info, car_id, won),
     }
 }
 
@@ -78,6 +85,7 @@ fn execute_update_config(
     info: MessageInfo,
     payment_options: Option<Vec<Coin>>,
     new_owner: Option<String>,
+    race_engine: Option<String>,
 ) -> Result<Response, CarError> {
     let mut config = CONFIG.load(deps.storage)?;
     let current_owner = config.owner.clone();
@@ -107,6 +115,9 @@ fn execute_update_config(
     if let Some(payment_options) = payment_options {
         config.payment_options = payment_options;
     }
+    if let Some(race_engine) = race_engine {
+        config.race_engine = Some(deps.api.addr_validate(&race_engine)?);
+    }
     CONFIG.save(deps.storage, &config)?;
 
     Ok(Response::new().add_attribute("action", "update_config"))
@@ -141,15 +152,23 @@ fn execute_mint_car(
     let token_id = next_id.to_string();
     CAR_ID_COUNTER.save(deps.storage, &(next_id + Uint128::one()))?;
 
-    // Populate car_id in metadata
+    // Populate car_id and creator in metadata
     if let Some(meta) = &mut extension {
+        if let Some(royalty_bps) = meta.royalty_bps {
+            if royalty_bps > MAX_ROYALTY_BPS {
+                return Err(CarError::InvalidRoyalty { royalty_bps });
+            }
+        }
         meta.car_id = Some(token_id.clone());
+        meta.creator = info.sender.to_string();
     } else {
         extension = Some(CarMetadata {
             name: String::new(),
             image_data: None,
             attributes: None,
             car_id: Some(token_id.clone()),
+            creator: info.sender.to_string(),
+            royalty_bps: None,
         });
     }
 
@@ -225,6 +244,8 @@ fn execute_update_custom_decal(
         image_data: None,
         attributes: None,
         car_id: Some(token_id.clone()),
+        creator: String::new(),
+        royalty_bps: None,
     });
 
     // Ensure the car has a custom slot either set or empty
@@ -262,6 +283,77 @@ fn execute_update_custom_decal(
         .add_attribute("token_id", token_id))
 }
 
+fn execute_burn(
+    deps: DepsMut,
+    info: MessageInfo,
+    car_id: String,
+) -> Result<Response, CarError> {
+    let id = car_id
+        .parse::<u128>()
+        .map_err(|_| CarError::InvalidCarId { car_id: car_id.clone() })?;
+
+    // Only token owner may burn
+    let contract: CarCw721 = Cw721Contract::default();
+    let token = contract.tokens.load(deps.storage, &car_id)
+        .map_err(|_| CarError::CarNotFound { car_id: id })?;
+    if token.owner != info.sender {
+        return Err(CarError::Unauthorized {});
+    }
+
+    contract.tokens.remove(deps.storage, &car_id)?;
+    contract.decrement_tokens(deps.storage)?;
+    CAR_INFO.remove(deps.storage, id);
+    RACE_RECORDS.remove(deps.storage, id);
+
+    // Clear learned Q-values for the retired car
+    let state_keys = Q_TABLE
+        .prefix(id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+    for key in &state_keys {
+        Q_TABLE.remove(deps.storage, (id, key.as_str()));
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "burn")
+        .add_attribute("token_id", car_id)
+        .add_attribute("q_entries_cleared", state_keys.len().to_string()))
+}
+
+fn execute_record_race_outcome(
+    deps: DepsMut,
+    info: MessageInfo,
+    car_id: String,
+    won: bool,
+) -> Result<Response, CarError> {
+    // Only the configured race engine may report outcomes
+    let config = CONFIG.load(deps.storage)?;
+    if config.race_engine.as_ref() != Some(&info.sender) {
+        return Err(CarError::Unauthorized {});
+    }
+
+    let id = car_id
+        .parse::<u128>()
+        .map_err(|_| CarError::InvalidCarId { car_id: car_id.clone() })?;
+    let contract: CarCw721 = Cw721Contract::default();
+    if contract.tokens.may_load(deps.storage, &car_id)?.is_none() {
+        return Err(CarError::CarNotFound { car_id: id });
+    }
+
+    let mut record = RACE_RECORDS.may_load(deps.storage, id)?.unwrap_or_default();
+    record.total_races += 1;
+    if won {
+        record.wins += 1;
+    }
+    RACE_RECORDS.save(deps.storage, id, &record)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "record_race_outcome")
+        .add_attribute("token_id", car_id)
+        .add_attribute("total_races", record.total_races.to_string())
+        .add_attribute("wins", record.wins.to_string()))
+}
+
 #[entry_point]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
@@ -269,6 +361,59 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             let contract: CarCw721 = Cw721Contract::default();
             contract.query(deps, env, q)
         }
+        QueryMsg::TokensByOwner { owner, start_after, limit } => {
+            to_json_binary(&query_tokens_by_owner(deps, owner, start_after, limit)?)
+        }
+        QueryMsg::GetCarInfo { car_id } => to_json_binary(&query_car_info(deps, car_id)?),
     }
 }
 
+fn query_car_info(deps: Deps, car_id: String) -> StdResult<GetCarInfoResponse> {
+    let contract: CarCw721 = Cw721Contract::default();
+    let token = contract.tokens.load(deps.storage, &car_id)?;
+    let record = match car_id.parse::<u128>() {
+        Ok(id) => RACE_RECORDS.may_load(deps.storage, id)?.unwrap_or_default(),
+        Err(_) => Default::default(),
+    };
+
+    Ok(GetCarInfoResponse {
+        car_id,
+        owner: token.owner.to_string(),
+        total_races: record.total_races,
+        wins: record.wins,
+    })
+}
+
+fn query_tokens_by_owner(
+    deps: Deps,
+    owner: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<String>> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let start_after = start_after
+        .map(|id| id.parse::<u128>())
+        .transpose()
+        .map_err(|_| StdError::generic_err("invalid start_after car id"))?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    // Token ids are stored as strings, so order numerically before paginating
+    let contract: CarCw721 = Cw721Contract::default();
+    let mut car_ids = vec![];
+    for item in contract.tokens.range(deps.storage, None, None, Order::Ascending) {
+        let (token_id, token) = item?;
+        if token.owner != owner {
+            continue;
+        }
+        if let Ok(car_id) = token_id.parse::<u128>() {
+            if start_after.map_or(true, |start| car_id > start) {
+                car_ids.push(car_id);
+            }
+        }
+    }
+    car_ids.sort_unstable();
+    car_ids.truncate(limit);
+
+    Ok(car_ids.into_iter().map(|id| id.to_string()).collect())
+}
+
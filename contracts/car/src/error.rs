@@ -24,6 +24,9 @@ pub enum CarError {
     #[error("Q-table not found for car: {car_id} and state: {state_hash}")]
     QTableNotFound { car_id: u128, state_hash: String },
 
+    #[error("Invalid royalty: {royalty_bps} bps exceeds 10000")]
+    InvalidRoyalty { royalty_bps: u16 },
+
     #[error("Decal is not custom and cannot be edited")]
     NotCustomDecal {},
 }
@@ -0,0 +1,286 @@
+#[cfg(test)]
+mod simple_tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier};
+    use cosmwasm_std::{from_json, MemoryStorage, OwnedDeps};
+
+    use crate::contract::{execute, instantiate, query};
+    use crate::error::CarError;
+    use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+    use crate::state::Q_TABLE;
+    use cw721_base::{ExecuteMsg as Cw721ExecuteMsg, MintMsg};
+
+    use racing::types::CarMetadata;
+
+    fn setup(deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>) {
+        let msg = InstantiateMsg {
+            name: "Membrane Cars".to_string(),
+            symbol: "CAR".to_string(),
+            payment_options: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+    }
+
+    /// Mint a car for `owner`, running the cw721 self-call as the contract
+    fn mint_car(deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>, owner: &str) -> String {
+        mint_car_with(deps, owner, None)
+    }
+
+    fn mint_car_with(
+        deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>,
+        owner: &str,
+        extension: Option<CarMetadata>,
+    ) -> String {
+        let env = mock_env();
+        let exec = ExecuteMsg::MintCar {
+            owner: owner.to_string(),
+            token_uri: None,
+            extension,
+        };
+        let resp = execute(deps.as_mut(), env.clone(), mock_info("minter", &[]), exec).unwrap();
+        let cosmwasm_std::CosmosMsg::Wasm(cosmwasm_std::WasmMsg::Execute { msg, .. }) = &resp.messages[0].msg else { panic!("expected Wasm Execute") };
+        let cw: Cw721ExecuteMsg<Option<CarMetadata>, cosmwasm_std::Empty> = from_json(msg).unwrap();
+        let token_id = match &cw { Cw721ExecuteMsg::Mint(MintMsg { token_id, .. }) => token_id.clone(), _ => panic!("expected cw721 Mint") };
+
+        let info_minter = mock_info(env.contract.address.as_str(), &[]);
+        execute(deps.as_mut(), env, info_minter, ExecuteMsg::Base(cw)).unwrap();
+        token_id
+    }
+
+    fn tokens_by_owner(
+        deps: &OwnedDeps<MemoryStorage, MockApi, MockQuerier>,
+        owner: &str,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Vec<String> {
+        let q = QueryMsg::TokensByOwner { owner: owner.to_string(), start_after, limit };
+        from_json(&query(deps.as_ref(), mock_env(), q).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn tokens_by_owner_filters_and_paginates() {
+        let mut deps = mock_dependencies();
+        setup(&mut deps);
+
+        let first = mint_car(&mut deps, "alice");
+        let second = mint_car(&mut deps, "bob");
+        let third = mint_car(&mut deps, "alice");
+
+        assert_eq!(tokens_by_owner(&deps, "alice", None, None), vec![first.clone(), third.clone()]);
+        assert_eq!(tokens_by_owner(&deps, "bob", None, None), vec![second]);
+        assert!(tokens_by_owner(&deps, "carol", None, None).is_empty());
+
+        // Pages continue after the given car id
+        assert_eq!(tokens_by_owner(&deps, "alice", None, Some(1)), vec![first.clone()]);
+        assert_eq!(tokens_by_owner(&deps, "alice", Some(first), Some(1)), vec![third]);
+    }
+
+    #[test]
+    fn burn_removes_car_and_q_values() {
+        let mut deps = mock_dependencies();
+        setup(&mut deps);
+
+        let kept = mint_car(&mut deps, "alice");
+        let burned = mint_car(&mut deps, "alice");
+        let burned_id: u128 = burned.parse().unwrap();
+        Q_TABLE.save(deps.as_mut().storage, (burned_id, "state-a"), &[1, 2, 3, 4]).unwrap();
+        Q_TABLE.save(deps.as_mut().storage, (burned_id, "state-b"), &[4, 3, 2, 1]).unwrap();
+
+        // Only the owner may burn
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bob", &[]),
+            ExecuteMsg::Burn { car_id: burned.clone() },
+        )
+        .unwrap_err();
+        assert_eq!(err, CarError::Unauthorized {});
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Burn { car_id: burned.clone() },
+        )
+        .unwrap();
+
+        let owner_of = QueryMsg::Base(cw721_base::QueryMsg::OwnerOf { token_id: burned.clone(), include_expired: None });
+        assert!(query(deps.as_ref(), mock_env(), owner_of).is_err());
+        let nft_info = QueryMsg::Base(cw721_base::QueryMsg::NftInfo { token_id: burned.clone() });
+        assert!(query(deps.as_ref(), mock_env(), nft_info).is_err());
+
+        let remaining = Q_TABLE
+            .prefix(burned_id)
+            .keys(deps.as_ref().storage, None, None, cosmwasm_std::Order::Ascending)
+            .count();
+        assert_eq!(remaining, 0);
+
+        let all = QueryMsg::Base(cw721_base::QueryMsg::AllTokens { start_after: None, limit: None });
+        let tokens: cw721::TokensResponse = from_json(&query(deps.as_ref(), mock_env(), all).unwrap()).unwrap();
+        assert_eq!(tokens.tokens, vec![kept]);
+    }
+
+    #[test]
+    fn mint_records_creator_and_royalty() {
+        let mut deps = mock_dependencies();
+        setup(&mut deps);
+
+        let metadata = |royalty_bps| CarMetadata {
+            name: "Speedster".to_string(),
+            image_data: None,
+            attributes: None,
+            car_id: None,
+            creator: String::new(),
+            royalty_bps: Some(royalty_bps),
+        };
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("minter", &[]),
+            ExecuteMsg::MintCar { owner: "alice".to_string(), token_uri: None, extension: Some(metadata(10_001)) },
+        )
+        .unwrap_err();
+        assert_eq!(err, CarError::InvalidRoyalty { royalty_bps: 10_001 });
+
+        let token_id = mint_car_with(&mut deps, "alice", Some(metadata(250)));
+
+        use cw721::NftInfoResponse;
+        let q = QueryMsg::Base(cw721_base::QueryMsg::NftInfo { token_id });
+        let nft_info: NftInfoResponse<Option<CarMetadata>> = from_json(&query(deps.as_ref(), mock_env(), q).unwrap()).unwrap();
+        let meta = nft_info.extension.unwrap();
+        assert_eq!(meta.creator, "minter");
+        assert_eq!(meta.royalty_bps, Some(250));
+
+        // Metadata stored before these fields existed still deserializes
+        let legacy: CarMetadata = from_json(br#"{"name":"Old","image_data":null,"attributes":null,"car_id":"7"}"#).unwrap();
+        assert_eq!(legacy.creator, "");
+        assert_eq!(legacy.royalty_bps, None);
+    }
+
+    fn base_exec(
+        deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>,
+        sender: &str,
+        msg: Cw721ExecuteMsg<Option<CarMetadata>, cosmwasm_std::Empty>,
+    ) -> Result<cosmwasm_std::Response, CarError> {
+        execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), ExecuteMsg::Base(msg))
+    }
+
+    fn owner_of(deps: &OwnedDeps<MemoryStorage, MockApi, MockQuerier>, token_id: &str) -> String {
+        let q = QueryMsg::Base(cw721_base::QueryMsg::OwnerOf { token_id: token_id.to_string(), include_expired: None });
+        let res: cw721::OwnerOfResponse = from_json(&query(deps.as_ref(), mock_env(), q).unwrap()).unwrap();
+        res.owner
+    }
+
+    #[test]
+    fn approved_spender_can_transfer() {
+        let mut deps = mock_dependencies();
+        setup(&mut deps);
+        let token_id = mint_car(&mut deps, "alice");
+
+        base_exec(&mut deps, "alice", Cw721ExecuteMsg::Approve { spender: "escrow".to_string(), token_id: token_id.clone(), expires: None }).unwrap();
+
+        let q = QueryMsg::Base(cw721_base::QueryMsg::Approvals { token_id: token_id.clone(), include_expired: None });
+        let approvals: cw721::ApprovalsResponse = from_json(&query(deps.as_ref(), mock_env(), q).unwrap()).unwrap();
+        assert_eq!(approvals.approvals.len(), 1);
+        assert_eq!(approvals.approvals[0].spender, "escrow");
+
+        base_exec(&mut deps, "escrow", Cw721ExecuteMsg::TransferNft { recipient: "bob".to_string(), token_id: token_id.clone() }).unwrap();
+        assert_eq!(owner_of(&deps, &token_id), "bob");
+    }
+
+    #[test]
+    fn revoked_spender_cannot_transfer() {
+        let mut deps = mock_dependencies();
+        setup(&mut deps);
+        let token_id = mint_car(&mut deps, "alice");
+
+        base_exec(&mut deps, "alice", Cw721ExecuteMsg::Approve { spender: "escrow".to_string(), token_id: token_id.clone(), expires: None }).unwrap();
+        base_exec(&mut deps, "alice", Cw721ExecuteMsg::Revoke { spender: "escrow".to_string(), token_id: token_id.clone() }).unwrap();
+
+        let res = base_exec(&mut deps, "escrow", Cw721ExecuteMsg::TransferNft { recipient: "bob".to_string(), token_id: token_id.clone() });
+        assert!(res.is_err());
+        assert_eq!(owner_of(&deps, &token_id), "alice");
+    }
+
+    #[test]
+    fn operator_can_transfer_all_cars() {
+        let mut deps = mock_dependencies();
+        setup(&mut deps);
+        let first = mint_car(&mut deps, "alice");
+        let second = mint_car(&mut deps, "alice");
+
+        base_exec(&mut deps, "alice", Cw721ExecuteMsg::ApproveAll { operator: "escrow".to_string(), expires: None }).unwrap();
+
+        for token_id in [&first, &second] {
+            base_exec(&mut deps, "escrow", Cw721ExecuteMsg::TransferNft { recipient: "bob".to_string(), token_id: token_id.clone() }).unwrap();
+            assert_eq!(owner_of(&deps, token_id), "bob");
+        }
+    }
+
+    mod integration {
+        use cosmwasm_std::Addr;
+        use cw_multi_test::{App, Contract, ContractWrapper, Executor};
+
+        use crate::contract::{execute, instantiate, query};
+        use crate::error::CarError;
+        use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+        use cw721_base::{ExecuteMsg as Cw721ExecuteMsg, MintMsg};
+        use racing::car::GetCarInfoResponse;
+
+        fn car_contract() -> Box<dyn Contract<cosmwasm_std::Empty>> {
+            Box::new(ContractWrapper::new(execute, instantiate, query))
+        }
+
+        #[test]
+        fn engine_reported_outcomes_show_in_car_info() {
+            let mut app = App::default();
+            let code_id = app.store_code(car_contract());
+            let owner = Addr::unchecked("creator");
+            let engine = Addr::unchecked("engine");
+            let car = app
+                .instantiate_contract(
+                    code_id,
+                    owner.clone(),
+                    &InstantiateMsg { name: "Membrane Cars".to_string(), symbol: "CAR".to_string(), payment_options: None },
+                    &[],
+                    "car",
+                    None,
+                )
+                .unwrap();
+
+            // Mint directly as the contract, which is the cw721 minter
+            let mint = Cw721ExecuteMsg::Mint(MintMsg {
+                token_id: "0".to_string(),
+                owner: "alice".to_string(),
+                token_uri: None,
+                extension: None,
+            });
+            app.execute_contract(car.clone(), car.clone(), &ExecuteMsg::Base(mint), &[]).unwrap();
+
+            app.execute_contract(
+                owner,
+                car.clone(),
+                &ExecuteMsg::UpdateConfig { payment_options: None, new_owner: None, race_engine: Some(engine.to_string()) },
+                &[],
+            )
+            .unwrap();
+
+            // Only the engine may report
+            let err = app
+                .execute_contract(Addr::unchecked("alice"), car.clone(), &ExecuteMsg::RecordRaceOutcome { car_id: "0".to_string(), won: true }, &[])
+                .unwrap_err();
+            assert_eq!(err.downcast::<CarError>().unwrap(), CarError::Unauthorized {});
+
+            app.execute_contract(engine.clone(), car.clone(), &ExecuteMsg::RecordRaceOutcome { car_id: "0".to_string(), won: true }, &[]).unwrap();
+            app.execute_contract(engine, car.clone(), &ExecuteMsg::RecordRaceOutcome { car_id: "0".to_string(), won: false }, &[]).unwrap();
+
+            let info: GetCarInfoResponse = app
+                .wrap()
+                .query_wasm_smart(car, &QueryMsg::GetCarInfo { car_id: "0".to_string() })
+                .unwrap();
+            assert_eq!(info.owner, "alice");
+            assert_eq!(info.total_races, 2);
+            assert_eq!(info.wins, 1);
+        }
+    }
+}
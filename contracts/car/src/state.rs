@@ -3,7 +3,7 @@ use cw_storage_plus::{Item, Map};
 use serde::{Deserialize, Serialize};
 
 use racing::types::{CarMetadata, QTableEntry};
-use racing::car::Config;
+use racing::car::{Config, RaceRecord};
 
 
 // Pending owner transfer
@@ -14,6 +14,9 @@ pub const CONFIG: Item<Config> = Item::new("config");
 // Car information: car_id -> CarInfo
 pub const CAR_INFO: Map<u128, CarInfo> = Map::new("car_info");
 
+// Race record cache: car_id -> RaceRecord, written by the race engine
+pub const RACE_RECORDS: Map<u128, RaceRecord> = Map::new("race_records");
+
 // Car ID counter
 pub const CAR_ID_COUNTER: Item<Uint128> = Item::new("car_id_counter");
 
@@ -1,24 +1,29 @@
 // race_engine/src/contract.rs
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use cosmwasm_std::{
-    entry_point, to_json_binary, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, QuerierWrapper, Response, StdResult, Storage, Uint128, from_json
+    entry_point, to_json_binary, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, QuerierWrapper, Reply, Response, StdResult, Storage, SubMsg, Uint128, WasmMsg, from_json
 };
 use cw_storage_plus::Bound;
 
 use crate::error::ContractError;
-use crate::state::{CAR_TRACK_TRAINING_STATS, add_recent_race, get_config, get_q_values, get_recent_races, set_config, set_q_values, CONFIG, MAX_TICKS, Q_TABLE, update_solo_training_stats, update_pvp_training_stats, get_track_training_stats};
-use racing::types::{ActionSelectionStrategy, QTableEntry, RewardNumbers, Track, TrackTile};
-use racing::race_engine::{CarState, Config, ConfigResponse, ExecuteMsg, GetQResponse, GetTrackTrainingStatsResponse, InstantiateMsg, QueryMsg, RaceResult, RaceResultResponse, RaceState, RecentRacesResponse, TrainingConfig, DEFAULT_BOOST_SPEED, DEFAULT_SPEED};
+use crate::state::{BEST_PROGRESS, CAR_TRACK_TRAINING_STATS, GHOSTS, RECENT_RACE_RESULTS, LAST_RACE_SUMMARY, TOTAL_CARS, TOTAL_Q_ENTRIES, TOTAL_RACES, TOTAL_TICKS, RaceReplayInputs, release_q_entries, add_recent_race, next_race_key, get_config, get_q_values, get_recent_races, set_config, set_q_values, state_hash_from_key, state_key, CONFIG, LAST_REWARD_BREAKDOWN, TRACK_CACHE, MAX_TICKS, MAX_TICKS_LIMIT, Q_TABLE, TRACK_Q_TABLE, update_solo_training_stats, update_pvp_training_stats, get_failed_races, get_track_training_stats};
+use racing::types::{ActionSelectionStrategy, QTableEntry, RewardBreakdown, RewardNumbers, Track, TrackTile};
+use racing::race_engine::{ActionOutcome, ActionSelectionResponse, BestActionResponse, QInitStrategy, CarStatsSummaryResponse, RewardBreakdownResponse, LastRaceSummary, NonFinishReason, PolicyEntry, ResponseDetail, StateEncoding, SimulateRaceResponse, Standing, CarState, CarWin, CarWinsResponse, Config, ConfigResponse, EngineStatsResponse, ExecuteMsg, GetQResponse, GetTrackTrainingStatsResponse, InstantiateMsg, QTableSizeResponse, QueryMsg, RaceResult, RaceResultResponse, RaceState, RecentRacesResponse, TimeoutValueResponse, TrainingConfig, VerifyReplayResponse, QTableExport, Ghost, GhostRaceResponse, RaceInputs, RankingMode, DEFAULT_BOOST_SPEED, Q_TABLE_EXPORT_VERSION, DEFAULT_SPEED};
 use racing::car::{ExecuteMsg as Car_ExecuteMsg, QueryMsg as Car_QueryMsg};
 // Race simulation constants
 const MAX_CARS: usize = 8;
 // const MAX_TRACK_SIZE: usize = 50;
 const MIN_CARS: usize = 1;
+// Competitive races that don't train need someone to race against
+const MIN_COMPETITIVE_CARS: usize = 2;
 
 const MAX_LIMIT: u32 = 32;
 
+// Reply ids
+const RECORD_RACE_OUTCOME_REPLY_ID: u64 = 1;
+
 // Action constants (4 possible actions: 0-3)
 const ACTION_UP: usize = 0;
 const ACTION_DOWN: usize = 1;
@@ -41,21 +46,73 @@ const ALPHA: f32 = 0.1; // Learning rate
 const GAMMA: f32 = 0.9; // Discount factor
 const MAX_Q_VALUE: i32 = 100;
 const MIN_Q_VALUE: i32 = -100;
+const MIN_ELIGIBILITY_TRACE: f32 = 0.01; // Traces below this are dropped
+const Q_VALUES_BYTES: u64 = 16; // 4 i32 action values per Q-table entry
+const DEFAULT_Q_UPDATE_BATCH_SIZE: u32 = 32; // Updates per chunk in apply_batched_q_updates
+const DISTANCE_PRIMER_BONUS: i32 = 10; // Head start for the action toward the finish with DistanceBiased init
+const SEED_ROUND: u32 = 0x9e37_79b9; // Mixed into every car seed, changing it changes every race
 
 // Reward constants
 const STUCK_PENALTY: i32 = -5;
 const WALL_PENALTY: i32 = -8;
 const NO_MOVE_PENALTY: i32 = 0;
 const EXPLORATION_BONUS: i32 = 6;
+const MAX_SPEED_REWARD: i32 = 100; // Default cap on the finish speed reward
+const MAX_MARGIN_REWARD: i32 = 100; // Cap on the winning margin reward
 const RANK_REWARDS: [i32; 3] = [100, 50, 25]; // 1st, 2nd, 3rd place
 
+// Non-finish heuristics
+const OSCILLATION_WINDOW: usize = 6; // Trailing positions that have to alternate between two tiles
+const BOXED_IN_SPAN: u32 = 2; // Widest and tallest the path of a boxed-in car gets
+
 /// Deterministic but simple RNG for on-chain use (fallback if no external crate)
-fn pseudo_random(seed: u32, modulus: u32) -> u32 {
+pub(crate) fn pseudo_random(seed: u32, modulus: u32) -> u32 {
     let a: u32 = 1103515245;
     let c: u32 = 12345;
     (a.wrapping_mul(seed).wrapping_add(c)) % modulus
 }
 
+/// Seed for a car's random draws on a tick
+/// - Blake2b over the tick, car_id and SEED_ROUND, so car 0 explores too
+///   and cars whose ids share factors don't draw in lockstep
+pub(crate) fn car_seed(tick_index: u32, car_id: u128) -> u32 {
+    let mut hasher = Blake2bVar::new(32).unwrap();
+    hasher.update(&tick_index.to_le_bytes());
+    hasher.update(&car_id.to_le_bytes());
+    hasher.update(&SEED_ROUND.to_le_bytes());
+    let mut out = [0u8; 32];
+    hasher.finalize_variable(&mut out).unwrap();
+    u32::from_le_bytes([out[0], out[1], out[2], out[3]])
+}
+
+/// Order cars are processed in on a tick, as indices into race_state.cars
+/// - Unshuffled it's the cars' own order, and contested cells go to the lower car_id every tick
+/// - Shuffled it's a Fisher-Yates shuffle drawn from the tick index, the same for every replay of the race
+pub(crate) fn processing_order(cars: &[CarState], tick_index: u32, shuffle: bool) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..cars.len()).collect();
+    if !shuffle {
+        return order;
+    }
+    for k in (1..order.len()).rev() {
+        let mut hasher = Blake2bVar::new(32).unwrap();
+        hasher.update(b"order");
+        hasher.update(&tick_index.to_le_bytes());
+        hasher.update(&(k as u32).to_le_bytes());
+        hasher.update(&SEED_ROUND.to_le_bytes());
+        let mut out = [0u8; 32];
+        hasher.finalize_variable(&mut out).unwrap();
+        let j = u32::from_le_bytes([out[0], out[1], out[2], out[3]]) as usize % (k + 1);
+        order.swap(k, j);
+    }
+    order
+}
+
+/// Epsilon for a car that has run the track `failed_races` times without winning, up to 1
+pub(crate) fn exploration_epsilon(training_config: &TrainingConfig, failed_races: u32) -> f32 {
+    let boost = training_config.failure_epsilon_step.unwrap_or(0.0) * failed_races as f32;
+    (training_config.epsilon + boost).min(1.0)
+}
+
 /// Create action strategy based on training configuration
 /// 
 /// For epsilon decay strategy (when enable_epsilon_decay is true):
@@ -74,9 +131,12 @@ fn make_action_strategy(
     current_tick: u32,
     total_ticks: u32,
     enable_epsilon_decay: bool,
+    deterministic_softmax: bool,
 ) -> ActionSelectionStrategy {
     if !training_mode {
         ActionSelectionStrategy::Best
+    } else if temperature > 0.0 && deterministic_softmax {
+        ActionSelectionStrategy::DeterministicSoftmax(((temperature * 1000.0) as u32).max(1))
     } else if temperature > 0.0 {
         ActionSelectionStrategy::Softmax(temperature)
     } else if epsilon > 0.0 {
@@ -140,90 +200,176 @@ fn make_action_strategy(
 // }
 
 /// Parse through Vec to update Q-values in storage
-fn batch_update_car_q_values(storage: &mut dyn Storage, car_id: u128, state_updates: &Vec<QTableEntry>, msgs: &mut Vec<CosmosMsg>, config: &Config) -> Result<(), ContractError> {
+fn batch_update_car_q_values(storage: &mut dyn Storage, car_id: u128, q_track: Option<u128>, state_updates: &Vec<QTableEntry>, msgs: &mut Vec<CosmosMsg>, config: &Config) -> Result<(), ContractError> {
    //For each QTableEntry, update the Q-values in storage
    for update in state_updates {
-        set_q_values(storage, car_id, &update.state_hash, update.action_values)?;
+        set_q_values(storage, car_id, q_track, &update.state_hash, &config.state_encoding, update.action_values)?;
    }
    
     Ok(())
 }
 
+/// A single Q-learning update: (state_hash, action, reward, next_state_hash)
+pub(crate) type QUpdateTuple = ([u8; 32], u8, i32, Option<[u8; 32]>);
+
 /// Apply batched Q-learning updates to car contract
 /// 
 /// This function applies multiple Q-learning updates in a single call to the car contract,
 /// which is more efficient than individual updates.
-fn apply_batched_q_updates(
+///
+/// Updates arrive in chunks, in episode order. Each chunk is written to storage before the
+/// next one is loaded so only one chunk's states are held in memory; the result is the same
+/// as applying every update in a single chunk.
+///
+/// With `lambda > 0` the updates use TD(λ) eligibility traces: each TD error is also applied
+/// to recently visited (state, action) pairs, weighted by a trace that decays by γλ per step.
+/// `lambda == 0` is the plain 1-step update.
+///
+/// `q_track` picks the car's per-track Q-table, None writes its shared one.
+pub(crate) fn apply_batched_q_updates<I>(
     storage: &mut dyn Storage,
     car: &CarState,
-    updates: Vec<( [u8; 32], u8, i32, Option< [u8; 32]>)>, // (state_hash, action, reward, next_state_hash)
+    q_track: Option<u128>,
+    chunks: I,
     config: Config,
     querier: QuerierWrapper,
-) -> Result<(), ContractError> {
+    lambda: f32,
+) -> Result<(), ContractError>
+where
+    I: IntoIterator<Item = Vec<QUpdateTuple>>,
+{
     // In a real implementation, this would:
     // 1. Use pre-loaded Q-values from car state (no need to re-query)
     // 2. Apply Q-learning updates for each (state, action, reward, next_state)
     // 3. Send all updated Q-values back to the car contract in a single transaction
     
     let mut msgs = vec![];
-    
-    // Collect all unique state hashes that need to be updated
-    let mut state_updates: HashMap< [u8; 32], QTableEntry> = HashMap::new();
-    
-    // First pass: collect all current Q-values from pre-loaded Q-tables for states that need updates
-    for (state_hash, _, _, _) in &updates {
-        if !state_updates.contains_key(state_hash) {
-            if let Some(cached_values) = car.q_table.iter().find(|q| q.state_hash == *state_hash) {
-                state_updates.insert(state_hash.clone(), cached_values.clone());
-            } else {
-                // Initialize with default Q-values if not found in cache
-                state_updates.insert(state_hash.clone(), QTableEntry {
-                    state_hash: state_hash.clone(),
-                    action_values: [0, 0, 0, 0],
-                });
+
+    // States written by earlier chunks, their latest values are in storage
+    let mut written: HashSet<[u8; 32]> = HashSet::new();
+    // TD(λ) traces carry over between chunks: (state_hash, action, eligibility)
+    let mut traces: Vec<([u8; 32], usize, f32)> = vec![];
+
+    for updates in chunks {
+        // Collect all unique state hashes that need to be updated
+        let mut state_updates: HashMap< [u8; 32], QTableEntry> = HashMap::new();
+        
+        // First pass: collect current Q-values for every state this chunk reads or writes
+        let needed = updates.iter()
+            .flat_map(|(state_hash, _, _, next_state_hash)| std::iter::once(*state_hash).chain(*next_state_hash))
+            .chain(traces.iter().map(|trace| trace.0))
+            .collect::<Vec<_>>();
+        for state_hash in needed {
+            if !state_updates.contains_key(&state_hash) {
+                let entry = initial_q_entry(storage, car, q_track, &config, &written, &state_hash)?;
+                state_updates.insert(state_hash, entry);
             }
         }
-    }
-    
-    // Second pass: apply Q-learning updates to collected Q-values
-    for (state_hash, action, reward, next_state_hash) in updates {
-        // Validate action index (4 possible actions: 0-3)
-        if action >= 4 {
-            return Err(ContractError::InvalidAction { action: action as usize });
-        }
-
-        // Get current Q-values for this state
-        let q_values = state_updates.get_mut(&state_hash).unwrap();
         
-        // Get max Q-value for next state (for Q-learning update)
-        let max_next_q = if let Some(next_hash) = &next_state_hash {
-            let next_q_values = if let Some(cached_values) = car.q_table.iter().find(|q| q.state_hash == *next_hash) {
-                cached_values.action_values
-            } else {
-                // Fallback to query if not in pre-loaded Q-tables
-                 [0, 0, 0, 0]
-            };
-            next_q_values.iter().max().cloned().unwrap_or(0)
+        // Second pass (TD(λ)): apply Q-learning updates through eligibility traces
+        if lambda > 0.0 {
+            for (state_hash, action, reward, next_state_hash) in updates {
+                if action >= 4 {
+                    return Err(ContractError::InvalidAction { action: action as usize });
+                }
+
+                // Bootstrap from the values learned so far in this batch
+                let max_next_q = match &next_state_hash {
+                    Some(next_hash) => state_updates.get(next_hash)
+                        .map(|q| q.action_values.iter().max().cloned().unwrap_or(0))
+                        .unwrap_or(0),
+                    None => 0,
+                };
+
+                // TD error: δ = r + γ max Q(s',a') - Q(s,a)
+                let current_q = state_updates.get(&state_hash).unwrap().action_values[action as usize];
+                let td_error = (reward as f32) + (GAMMA * (max_next_q as f32)) - (current_q as f32);
+
+                // Accumulating trace for the visited pair
+                if let Some(trace) = traces.iter_mut().find(|t| t.0 == state_hash && t.1 == action as usize) {
+                    trace.2 += 1.0;
+                } else {
+                    traces.push((state_hash, action as usize, 1.0));
+                }
+
+                // Q(s,a) += α δ e(s,a) for every traced pair, then decay the traces
+                for trace in traces.iter_mut() {
+                    let q_values = state_updates.get_mut(&trace.0).unwrap();
+                    let new_value = ((q_values.action_values[trace.1] as f32) + ALPHA * td_error * trace.2).round() as i32;
+                    q_values.action_values[trace.1] = new_value.clamp(MIN_Q_VALUE, MAX_Q_VALUE);
+                    trace.2 *= GAMMA * lambda;
+                }
+
+                // Forget pairs whose trace has decayed to nothing
+                traces.retain(|t| t.2 >= MIN_ELIGIBILITY_TRACE);
+            }
         } else {
-            0 // No next state, so no future reward
-        };
-        
-        // Q-learning update formula: Q(s,a) = Q(s,a) + α[r + γ max Q(s',a') - Q(s,a)]
-        let old_value = q_values.action_values[action as usize];
-        let new_value = ((1.0 - ALPHA) * (old_value as f32) + 
-                        ALPHA * ((reward as f32) + (GAMMA * (max_next_q as f32)))).round() as i32;
+            // Second pass: apply Q-learning updates to collected Q-values
+            for (state_hash, action, reward, next_state_hash) in updates {
+                // Validate action index (4 possible actions: 0-3)
+                if action >= 4 {
+                    return Err(ContractError::InvalidAction { action: action as usize });
+                }
+
+                // Get current Q-values for this state
+                let q_values = state_updates.get_mut(&state_hash).unwrap();
+                
+                // Get max Q-value for next state (for Q-learning update)
+                let max_next_q = if let Some(next_hash) = &next_state_hash {
+                    let next_q_values = if let Some(cached_values) = car.q_table.iter().find(|q| q.state_hash == *next_hash) {
+                        cached_values.action_values
+                    } else {
+                        // Fallback to query if not in pre-loaded Q-tables
+                         [0, 0, 0, 0]
+                    };
+                    next_q_values.iter().max().cloned().unwrap_or(0)
+                } else {
+                    0 // No next state, so no future reward
+                };
+                
+                // Q-learning update formula: Q(s,a) = Q(s,a) + α[r + γ max Q(s',a') - Q(s,a)]
+                let old_value = q_values.action_values[action as usize];
+                let new_value = ((1.0 - ALPHA) * (old_value as f32) + 
+                                ALPHA * ((reward as f32) + (GAMMA * (max_next_q as f32)))).round() as i32;
+                
+                // Clamp the value to prevent explosion
+                q_values.action_values[action as usize] = new_value.clamp(MIN_Q_VALUE, MAX_Q_VALUE);
+            }
+        }
         
-        // Clamp the value to prevent explosion
-        q_values.action_values[action as usize] = new_value.clamp(MIN_Q_VALUE, MAX_Q_VALUE);
+        // Third pass: send this chunk's updated Q-values to car contract in a single batch
+        written.extend(state_updates.keys().copied());
+        let state_updates_vec: Vec<QTableEntry> = state_updates.into_values().collect();
+        batch_update_car_q_values(storage, car.car_id, q_track, &state_updates_vec, &mut msgs, &config)?;
     }
     
-    // Third pass: send all updated Q-values to car contract in a single batch
-    let state_updates_vec: Vec<QTableEntry> = state_updates.into_values().collect();
-    batch_update_car_q_values(storage, car.car_id, &state_updates_vec, &mut msgs, &config)?;
-    
     Ok(())
 }
 
+// Q-values a chunk starts from: what an earlier chunk wrote, else the pre-loaded Q-table, else zeros
+fn initial_q_entry(
+    storage: &dyn Storage,
+    car: &CarState,
+    q_track: Option<u128>,
+    config: &Config,
+    written: &HashSet<[u8; 32]>,
+    state_hash: &[u8; 32],
+) -> Result<QTableEntry, ContractError> {
+    if written.contains(state_hash) {
+        return Ok(QTableEntry {
+            state_hash: *state_hash,
+            action_values: get_q_values(storage, car.car_id, q_track, state_hash, &config.state_encoding)?,
+        });
+    }
+    Ok(car.q_table.iter()
+        .find(|q| q.state_hash == *state_hash)
+        .cloned()
+        .unwrap_or(QTableEntry {
+            state_hash: *state_hash,
+            action_values: [0, 0, 0, 0],
+        }))
+}
+
 #[entry_point]
 pub fn instantiate(
     deps: DepsMut,
@@ -241,6 +387,11 @@ pub fn instantiate(
         car_contract: car_contract.to_string(),
         max_ticks: MAX_TICKS,
         max_recent_races: 10,
+        authorized_callers: vec![],
+        state_encoding: msg.state_encoding.unwrap_or_default(),
+        q_update_batch_size: msg.q_update_batch_size,
+        per_track_q: msg.per_track_q.unwrap_or(false),
+        distinguish_edges: msg.distinguish_edges.unwrap_or(false),
     };
     
     set_config(deps.storage, config)?;
@@ -256,34 +407,211 @@ pub fn instantiate(
 pub fn execute(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    let config = get_config(deps.storage)?;
     match msg {
-        ExecuteMsg::SimulateRace { track_id, car_ids, train, training_config, reward_config } => {
-            execute_simulate_race(deps, _env, track_id, car_ids, train, training_config, reward_config)
+        ExecuteMsg::SimulateRace { track_id, car_ids, train, training_config, reward_config, max_ticks, response_detail, teams, end_on_first_finish, record_replay, ranking_mode, report_records, competitive, shuffle_order } => {
+            assert_authorized_caller(&config, &info)?;
+            execute_simulate_race(deps, _env, track_id, car_ids, train, training_config, reward_config, max_ticks, response_detail, teams, end_on_first_finish.unwrap_or(false), record_replay.unwrap_or(false), ranking_mode.unwrap_or_default(), report_records.unwrap_or(false), competitive.unwrap_or(false), shuffle_order.unwrap_or(false))
         },
         ExecuteMsg::ResetQ { car_id } => {
+            assert_admin(&config, &info)?;
             execute_reset_q(deps.storage, car_id.into())
         },
+        ExecuteMsg::ResetTrainingStats { car_id, track_id } => {
+            assert_admin(&config, &info)?;
+            execute_reset_training_stats(deps.storage, car_id.into(), track_id)
+        },
+        ExecuteMsg::ImportQTable { car_id, track_id, export } => {
+            assert_admin(&config, &info)?;
+            execute_import_q_table(deps.storage, &config, car_id.into(), track_id, export)
+        },
+        ExecuteMsg::UpdateAuthorizedCallers { authorized_callers } => {
+            assert_admin(&config, &info)?;
+            execute_update_authorized_callers(deps, config, authorized_callers)
+        },
+        ExecuteMsg::CompactQTable { car_id, track_id, threshold } => {
+            assert_admin(&config, &info)?;
+            execute_compact_q_table(deps.storage, car_id.into(), track_id, threshold)
+        },
+        ExecuteMsg::SimulateEliminationRace { track_id, car_ids, elimination_interval } => {
+            assert_authorized_caller(&config, &info)?;
+            execute_simulate_elimination_race(deps, _env, track_id, car_ids, elimination_interval)
+        },
+        ExecuteMsg::SimulateGhostRace { track_id, car_id } => {
+            assert_authorized_caller(&config, &info)?;
+            execute_simulate_ghost_race(deps, _env, track_id, car_id)
+        },
+        ExecuteMsg::InvalidateTrackCache { track_id } => {
+            assert_admin(&config, &info)?;
+            TRACK_CACHE.remove(deps.storage, track_id.u128());
+            Ok(Response::new()
+                .add_attribute("method", "invalidate_track_cache")
+                .add_attribute("track_id", track_id.to_string()))
+        },
+    }
+}
+
+/// Only the config admin may call admin functions
+fn assert_admin(config: &Config, info: &MessageInfo) -> Result<(), ContractError> {
+    if info.sender.as_str() != config.admin {
+        return Err(ContractError::Unauthorized {});
     }
+    Ok(())
+}
+
+/// The admin and any address in the authorized_callers list may run races
+fn assert_authorized_caller(config: &Config, info: &MessageInfo) -> Result<(), ContractError> {
+    let sender = info.sender.as_str();
+    if sender != config.admin && !config.authorized_callers.iter().any(|caller| caller == sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+/// Replace the SimulateRace allowlist
+fn execute_update_authorized_callers(
+    deps: DepsMut,
+    mut config: Config,
+    authorized_callers: Vec<String>,
+) -> Result<Response, ContractError> {
+    let mut validated = vec![];
+    for caller in authorized_callers {
+        validated.push(deps.api.addr_validate(&caller)?.to_string());
+    }
+    config.authorized_callers = validated;
+    set_config(deps.storage, config.clone())?;
+
+    Ok(Response::new()
+        .add_attribute("method", "update_authorized_callers")
+        .add_attribute("authorized_callers", config.authorized_callers.join(",")))
 }
 
 /// Reset the Q-table for a car
 fn execute_reset_q(storage: &mut dyn Storage, car_id: u128) -> Result<Response, ContractError> {
     let prefix = Q_TABLE.prefix(car_id);
     let range = prefix.range(storage, None, None, cosmwasm_std::Order::Ascending);
-    let keys: Vec<[u8; 32]> = range.map(|item| {
+    let keys: Vec<Vec<u8>> = range.map(|item| {
         let (key, _) = item.unwrap();
         key
     }).collect();
     
+    release_q_entries(storage, keys.len() as u64)?;
     for key in keys {
-        Q_TABLE.remove(storage, (car_id, &key));
+        Q_TABLE.remove(storage, (car_id, key.as_slice()));
+    }
+
+    // Along with every per-track table
+    let track_keys: Vec<(u128, Vec<u8>)> = TRACK_Q_TABLE.sub_prefix(car_id)
+        .keys(storage, None, None, cosmwasm_std::Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    release_q_entries(storage, track_keys.len() as u64)?;
+    for (track_id, key) in track_keys {
+        TRACK_Q_TABLE.remove(storage, (car_id, track_id, key.as_slice()));
     }
     Ok(Response::new())
 }
 
+fn execute_import_q_table(storage: &mut dyn Storage, config: &Config, car_id: u128, track_id: Option<u128>, export: QTableExport) -> Result<Response, ContractError> {
+    // State hashes only line up with the encoding they were generated with
+    if export.version != Q_TABLE_EXPORT_VERSION || export.state_encoding != config.state_encoding {
+        return Err(ContractError::UnsupportedQTableExport {
+            version: export.version,
+            state_encoding: format!("{:?}", export.state_encoding),
+            expected_version: Q_TABLE_EXPORT_VERSION,
+            expected_encoding: format!("{:?}", config.state_encoding),
+        });
+    }
+
+    for entry in &export.entries {
+        set_q_values(storage, car_id, track_id, &entry.state_hash, &config.state_encoding, entry.action_values)?;
+    }
+    Ok(Response::new()
+        .add_attribute("method", "import_q_table")
+        .add_attribute("car_id", car_id.to_string())
+        .add_attribute("entries", export.entries.len().to_string()))
+}
+
+fn execute_reset_training_stats(storage: &mut dyn Storage, car_id: u128, track_id: Option<u128>) -> Result<Response, ContractError> {
+    let track_ids: Vec<u128> = match track_id {
+        Some(track_id) => vec![track_id],
+        None => CAR_TRACK_TRAINING_STATS.prefix(car_id)
+            .keys(storage, None, None, cosmwasm_std::Order::Ascending)
+            .collect::<StdResult<_>>()?,
+    };
+
+    for track_id in &track_ids {
+        CAR_TRACK_TRAINING_STATS.remove(storage, (car_id, *track_id));
+    }
+    Ok(Response::new()
+        .add_attribute("method", "reset_training_stats")
+        .add_attribute("car_id", car_id.to_string())
+        .add_attribute("tracks", track_ids.len().to_string()))
+}
+
+/// Remove low-information Q-table entries for a car
+fn execute_compact_q_table(storage: &mut dyn Storage, car_id: u128, track_id: Option<u128>, threshold: u32) -> Result<Response, ContractError> {
+    let prefix = match track_id {
+        Some(track_id) => TRACK_Q_TABLE.prefix((car_id, track_id)),
+        None => Q_TABLE.prefix(car_id),
+    };
+    let range = prefix.range(storage, None, None, cosmwasm_std::Order::Ascending);
+    let mut keys: Vec<Vec<u8>> = vec![];
+    for item in range {
+        let (key, action_values) = item?;
+        let strongest = action_values.iter().map(|value| value.unsigned_abs()).max().unwrap_or(0);
+        if strongest <= threshold {
+            keys.push(key);
+        }
+    }
+
+    release_q_entries(storage, keys.len() as u64)?;
+    for key in &keys {
+        match track_id {
+            Some(track_id) => TRACK_Q_TABLE.remove(storage, (car_id, track_id, key.as_slice())),
+            None => Q_TABLE.remove(storage, (car_id, key.as_slice())),
+        }
+    }
+    Ok(Response::new()
+        .add_attribute("method", "compact_q_table")
+        .add_attribute("car_id", car_id.to_string())
+        .add_attribute("pruned", keys.len().to_string()))
+}
+
+// Reject layouts the simulation would index out of bounds on or could never finish
+pub(crate) fn validate_track(track: &Track) -> Result<(), ContractError> {
+    let invalid = |reason: &str| Err(ContractError::InvalidTrack { reason: reason.to_string() });
+
+    let width = match track.layout.first() {
+        Some(row) if !row.is_empty() => row.len(),
+        _ => return invalid("layout is empty"),
+    };
+    if track.layout.iter().any(|row| row.len() != width) {
+        return invalid("rows have different lengths");
+    }
+    if find_start_indices(&track.layout).is_empty() {
+        return invalid("no start tile");
+    }
+    if find_finish_tiles(&track.layout).is_empty() {
+        return invalid("no finish tile");
+    }
+    Ok(())
+}
+
+fn find_finish_tiles(track_layout: &[Vec<racing::types::TrackTile>]) -> Vec<(i32, i32)> {
+    let mut finish_tiles = vec![];
+    for (y, row) in track_layout.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            if tile.properties.is_finish {
+                finish_tiles.push((x as i32, y as i32));
+            }
+        }
+    }
+    finish_tiles
+}
+
 fn find_start_indices(track_layout: &[Vec<racing::types::TrackTile>]) -> Vec<(usize, usize)> {
     let mut start_indices = vec![];
     for (y, row) in track_layout.iter().enumerate() {
@@ -299,13 +627,22 @@ fn find_start_indices(track_layout: &[Vec<racing::types::TrackTile>]) -> Vec<(us
 
 
 pub fn execute_simulate_race(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     track_id: Uint128,
     car_ids: Vec<u128>,
     train: bool,
     training_config: Option<TrainingConfig>,
     reward_config: Option<RewardNumbers>,
+    max_ticks: Option<u32>,
+    response_detail: Option<ResponseDetail>,
+    teams: Option<Vec<Vec<u128>>>,
+    end_on_first_finish: bool,
+    record_replay: bool,
+    ranking_mode: RankingMode,
+    report_records: bool,
+    competitive: bool,
+    shuffle_order: bool,
 ) -> Result<Response, ContractError> {
     let config = get_config(deps.storage)?;
     // Validate input
@@ -315,6 +652,30 @@ pub fn execute_simulate_race(
             actual: car_ids.len() as u32
         });
     }
+    if competitive && !train && car_ids.len() < MIN_COMPETITIVE_CARS {
+        return Err(ContractError::InvalidCarCount {
+            expected: MIN_COMPETITIVE_CARS as u32,
+            actual: car_ids.len() as u32
+        });
+    }
+    assert_distinct_cars(&car_ids)?;
+
+    // Per-race tick limit, also used as the "didn't finish" completion time
+    let max_ticks = max_ticks.unwrap_or(config.max_ticks);
+    if max_ticks == 0 || max_ticks > MAX_TICKS_LIMIT {
+        return Err(ContractError::InvalidMaxTicks { max_ticks, limit: MAX_TICKS_LIMIT });
+    }
+
+    // Every car has to be on exactly one team
+    if let Some(teams) = &teams {
+        let mut members: Vec<u128> = teams.iter().flatten().copied().collect();
+        members.sort();
+        let mut expected = car_ids.clone();
+        expected.sort();
+        if teams.iter().any(|team| team.is_empty()) || members != expected {
+            return Err(ContractError::InvalidRaceConfig);
+        }
+    }
 
     //If training_config is None, use default values
     let training_config = match training_config {
@@ -324,8 +685,31 @@ pub fn execute_simulate_race(
             epsilon: EPSILON,
             temperature: TEMPERATURE,
             enable_epsilon_decay: true,
+            lambda: None,
+            init_strategy: None,
+            enable_action_masking: false,
+            deterministic_softmax: false,
+            online_batch_ticks: None,
+            reward_time_decay: None,
+            failure_epsilon_step: None,
         },
     };
+
+    // TD(λ) trace decay, 0 keeps the 1-step update
+    let lambda = training_config.lambda.unwrap_or(0.0);
+    if !(0.0..=1.0).contains(&lambda) {
+        return Err(ContractError::InvalidRaceConfig);
+    }
+    if training_config.reward_time_decay.is_some_and(|decay| !(decay > 0.0 && decay <= 1.0)) {
+        return Err(ContractError::InvalidRaceConfig);
+    }
+    if training_config.failure_epsilon_step.is_some_and(|step| !(0.0..).contains(&step)) {
+        return Err(ContractError::InvalidRaceConfig);
+    }
+    // Replays re-run without the rewards, so they can't reproduce online updates
+    if training_config.online_batch_ticks == Some(0) || (training_config.online_batch_ticks.is_some() && record_replay) {
+        return Err(ContractError::InvalidRaceConfig);
+    }
     let reward_config = match reward_config {
         Some(config) => config,
         None => RewardNumbers {
@@ -335,69 +719,78 @@ pub fn execute_simulate_race(
             no_move: NO_MOVE_PENALTY,
             explore: EXPLORATION_BONUS,
             rank: racing::types::RankReward {
-                first: RANK_REWARDS[0],
-                second: RANK_REWARDS[1],
-                third: RANK_REWARDS[2],
-                other: 0, // Default value instead of array access
+                ranks: RANK_REWARDS.to_vec(),
+                other: 0, // Default value instead of array access,
+                margin_bonus: None,
             },
+            max_speed_reward: None,
+            draft_bonus: None,
+            checkpoint_bonus: None,
+            backward_penalty_multiplier: None,
+            collision_penalty: None,
+            reward_min: None,
+            reward_max: None,
+            comeback_bonus: None,
+            path_efficiency_bonus: None,
+            target_ticks: None,
+            overtime_penalty_per_tick: None,
+            evasion_bonus: None,
+            tile_variety_bonus: None,
+            frustration_penalty: None,
+            new_best_bonus: None,
         },
     };
+    if let (Some(reward_min), Some(reward_max)) = (reward_config.reward_min, reward_config.reward_max) {
+        if reward_min > reward_max {
+            return Err(ContractError::InvalidRaceConfig);
+        }
+    }
 
     // Load track from track manager contract
-    let track = load_track_from_manager(deps.as_ref(), config.clone(), track_id.clone())?;
+    let track = load_track_from_manager(deps.branch(), config.clone(), track_id.clone())?;
+    validate_track(&track)?;
     let track_layout = track.layout;
     let fastest_track_tick_time = track.fastest_tick_time;
 
-    //Find the indices of any starting tiles
-    let start_indices = find_start_indices(&track_layout);
-
-    // Initialize car states
-    let mut cars = vec![];
-    for (i, car_id) in car_ids.iter().enumerate() {
-        //if there are multiple starting tiles, choose car ID mod start_indices.len()
-        let start_index = if start_indices.len() > 1 {
-            (i % start_indices.len()) as usize
-        } else {
-            0
-        };
-        
-        // **NEW**: Query all Q-tables for this car upfront
-        // let q_tables_res = query_full_q_tables(config.clone(), deps.querier, car_id)?;
-        // let q_tables = get_q_tables(q_tables_res)?;
-
-        cars.push(CarState {
-            car_id: car_id.clone(),
-            tile: track_layout[start_indices[start_index].1][start_indices[start_index].0].clone(),
-            x: start_indices[start_index].0 as i32,
-            y: start_indices[start_index].1 as i32,
-            stuck: false,
-            finished: false,
-            steps_taken: 0,
-            last_action: ACTION_UP, // Default to UP
-            // **NEW**: Initialize action history
-            action_history: vec![],
-            // **NEW**: Initialize hit_wall
-            hit_wall: false,
-            // **NEW**: Initialize speed modifiers
-            current_speed: DEFAULT_SPEED as u32, // Default normal speed
-            // **NEW**: Initialize Q-tables with pre-queried values
-            q_table: vec![],
-        });
+    let mut race_state = init_race_state(track_layout, &car_ids, config.state_encoding.clone());
+    race_state.q_track = config.per_track_q.then_some(track_id.u128());
+    race_state.distinguish_edges = config.distinguish_edges;
+    race_state.shuffle_order = shuffle_order;
+    if train {
+        for car in race_state.cars.iter_mut() {
+            car.failed_races = get_failed_races(deps.storage, car.car_id, track_id.u128())?;
+            car.best_progress = BEST_PROGRESS.may_load(deps.storage, (car.car_id, track_id.u128()))?;
+        }
     }
 
-    // Initialize race state
-    let mut race_state = RaceState {
-        cars,
-        track_layout,
-        tick: 0,
-        play_by_play: std::collections::HashMap::new(),
-    };
-
     // Simulate race
-    let race_result = simulate_race(deps.storage, &mut race_state, training_config)?;
+    let online = match train {
+        true => Some(OnlineUpdates {
+            reward_config: &reward_config,
+            config: &config,
+            querier: deps.querier,
+            fastest_track_tick_time,
+        }),
+        false => None,
+    };
+    let mut race_result = simulate_race(deps.storage, &mut race_state, training_config.clone(), max_ticks, None, end_on_first_finish, &ranking_mode, online)?;
+    if let Some(teams) = teams {
+        race_result.team_results = Some(calculate_team_results(&teams, &race_result.rankings));
+        race_result.teams = Some(teams);
+    }
 
     // Generate race ID
-    let race_id = format!("race_{}_{}", track_id, env.block.time.seconds());
+    let race_key = next_race_key(deps.storage)?;
+    let race_id = format_race_id(track_id, &env, race_key);
+
+    record_ghosts(deps.storage, &race_id, track_id.u128(), &race_state)?;
+
+    let replay = record_replay.then(|| RaceReplayInputs {
+        track_layout: race_state.track_layout.clone(),
+        state_encoding: config.state_encoding.clone(),
+        distinguish_edges: config.distinguish_edges,
+        q_tables: race_state.cars.iter().map(|car| (car.car_id, q_values_read(car))).collect(),
+    });
 
     // Create race result
     let race_result_struct = racing::race_engine::RaceResult {
@@ -408,13 +801,39 @@ pub fn execute_simulate_race(
         rankings: race_result.rankings.clone(),
         play_by_play: race_result.play_by_play.clone(),
         steps_taken: race_result.steps_taken.clone(),
+        eliminations: None,
+        teams: race_result.teams.clone(),
+        team_results: race_result.team_results.clone(),
     };
 
     // Save race result
-    add_recent_race(deps.storage, race_result_struct.clone(), None, Some(track_id.into()))?;
-    for car_id in car_ids.clone() {
-        add_recent_race(deps.storage, race_result_struct.clone(), Some(car_id), None)?;
+    add_recent_race(deps.storage, race_key, &race_result_struct, Some(RaceInputs {
+        track_id,
+        car_ids: car_ids.clone(),
+        train,
+        training_config: training_config.clone(),
+        reward_config: reward_config.clone(),
+        max_ticks,
+        teams: race_result.teams.clone(),
+        end_on_first_finish,
+        ranking_mode: ranking_mode.clone(),
+        shuffle_order,
+        failed_races: race_state.cars.iter().map(|car| (car.car_id, car.failed_races)).collect(),
+    }), replay)?;
+    for rank in &race_result_struct.rankings {
+        let step = race_result_struct.steps_taken.iter().find(|step| step.car_id == rank.car_id);
+        LAST_RACE_SUMMARY.save(deps.storage, rank.car_id, &LastRaceSummary {
+            race_id: race_id.clone(),
+            track_id: track_id.u128(),
+            rank: rank.rank,
+            steps: step.map_or(0, |step| step.steps_taken),
+            finished: step.is_some_and(|step| step.finished),
+        })?;
     }
+    let total_races = TOTAL_RACES.may_load(deps.storage)?.unwrap_or_default();
+    TOTAL_RACES.save(deps.storage, &(total_races + 1))?;
+    let total_ticks = TOTAL_TICKS.may_load(deps.storage)?.unwrap_or_default();
+    TOTAL_TICKS.save(deps.storage, &(total_ticks + race_state.tick as u64))?;
 
     // **NEW**: Apply Q-learning updates directly to car model in storage
     if train {
@@ -425,53 +844,395 @@ pub fn execute_simulate_race(
             reward_config.clone(), 
             config.clone(), 
             deps.querier,
-            fastest_track_tick_time
+            fastest_track_tick_time,
+            lambda,
+            training_config.reward_time_decay,
         )?;
         
         // **NEW**: Update training stats for each car
         let is_solo = car_ids.len() == 1;
         for car in &race_state.cars {
             let won = race_result.winner_ids.contains(&car.car_id);
-            let completion_time = if car.finished { car.steps_taken } else { MAX_TICKS };
-            
+
             // Update training stats
             if is_solo {
-                update_solo_training_stats(deps.storage, car.car_id, track_id.into(), won, completion_time)?;
+                update_solo_training_stats(deps.storage, car.car_id, track_id.into(), won, car.finished, car.steps_taken)?;
             } else {
-                update_pvp_training_stats(deps.storage, car.car_id, track_id.into(), won, completion_time)?;
+                update_pvp_training_stats(deps.storage, car.car_id, track_id.into(), won, car.finished, car.steps_taken)?;
+            }
+
+            let race_best = car.action_history.iter()
+                .map(|(_, _, tile)| tile.progress_towards_finish)
+                .chain([car.tile.progress_towards_finish])
+                .max()
+                .unwrap_or(0);
+            if !car.best_progress.is_some_and(|best| race_best <= best) {
+                BEST_PROGRESS.save(deps.storage, (car.car_id, track_id.u128()), &race_best)?;
             }
         }
     }
 
-    let mut response = Response::new()
+    // Report a new track record back to the track manager
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let fastest_finish = race_state.cars.iter()
+        .filter(|car| car.finished)
+        .map(|car| car.steps_taken as u64)
+        .min();
+    if let Some(fastest_finish) = fastest_finish.filter(|steps| report_records && *steps < fastest_track_tick_time) {
+        messages.push(CosmosMsg::Wasm(cosmwasm_std::WasmMsg::Execute {
+            contract_addr: config.track_contract.clone(),
+            msg: to_json_binary(&racing::track_manager::ExecuteMsg::UpdateFastestTime {
+                track_id,
+                fastest_tick_time: fastest_finish,
+            })?,
+            funds: vec![],
+        }));
+        // Keep the cached track in step so the next race is timed against the new record
+        if let Some(mut track) = TRACK_CACHE.may_load(deps.storage, track_id.u128())? {
+            track.fastest_tick_time = fastest_finish;
+            TRACK_CACHE.save(deps.storage, track_id.u128(), &track)?;
+        }
+    }
+
+    // Keep every car's race record in the car contract in step, a car it won't record doesn't fail the race (see `reply`)
+    let outcomes = race_state.cars.iter()
+        .map(|car| -> StdResult<SubMsg> {
+            let msg = WasmMsg::Execute {
+                contract_addr: config.car_contract.clone(),
+                msg: to_json_binary(&Car_ExecuteMsg::RecordRaceOutcome {
+                    car_id: car.car_id.to_string(),
+                    won: race_result.winner_ids.contains(&car.car_id),
+                })?,
+                funds: vec![],
+            };
+            Ok(SubMsg::reply_on_error(msg, RECORD_RACE_OUTCOME_REPLY_ID))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    // Only ship the play-by-play when asked for, it's the bulk of the result
+    let full = response_detail.unwrap_or(ResponseDetail::Summary) == ResponseDetail::Full;
+    let data = SimulateRaceResponse {
+        race_id: race_id.clone(),
+        winner_ids: race_result.winner_ids.clone(),
+        rankings: race_result.rankings.clone(),
+        play_by_play: full.then(|| race_result.play_by_play.clone()),
+        steps_taken: full.then(|| race_result.steps_taken.clone()),
+    };
+
+    let response = Response::new()
+        .add_messages(messages)
+        .add_submessages(outcomes)
         .add_attribute("method", "simulate_race")
         .add_attribute("race_id", race_id)
         .add_attribute("car_count", car_ids.len().to_string())
         .add_attribute("ticks", race_state.tick.to_string())
-        .add_attribute("winners", race_result.winner_ids.len().to_string());
-    
+        .add_attribute("winners", race_result.winner_ids.len().to_string())
+        .set_data(to_json_binary(&data)?);
 
     Ok(response)
 }
 
+/// The Q-values a car read during the race, first read of each state
+fn q_values_read(car: &CarState) -> Vec<QTableEntry> {
+    let mut entries: Vec<QTableEntry> = vec![];
+    for entry in &car.q_table {
+        if !entries.iter().any(|seen| seen.state_hash == entry.state_hash) {
+            entries.push(entry.clone());
+        }
+    }
+    entries
+}
+
+/// Blake2b-256 of a play-by-play, serialized in car id order so the hash doesn't depend on map order
+pub fn play_by_play_hash(play_by_play: &HashMap<u128, racing::race_engine::PlayByPlay>) -> Result<[u8; 32], ContractError> {
+    let mut entries: Vec<_> = play_by_play.iter().collect();
+    entries.sort_by_key(|(car_id, _)| **car_id);
+    let bytes = cosmwasm_std::to_json_vec(&entries)?;
+
+    let mut out = [0u8; 32];
+    let mut hasher = Blake2bVar::new(32).unwrap();
+    hasher.update(&bytes);
+    hasher.finalize_variable(&mut out).map_err(|e| ContractError::SimulationError { message: e.to_string() })?;
+    Ok(out)
+}
+
 /// Load track from track manager contract
-fn load_track_from_manager(deps: Deps, config: Config, track_id: Uint128) -> Result<Track, ContractError> {
-    // For testing purposes, return a simple test track
-    // In a real implementation, this would query the track manager contract
+fn load_track_from_manager(deps: DepsMut, config: Config, track_id: Uint128) -> Result<Track, ContractError> {
+    // Serve repeat races from the cache
+    if let Some(track) = TRACK_CACHE.may_load(deps.storage, track_id.u128())? {
+        return Ok(track);
+    }
+
     let track: Track = deps.querier.query_wasm_smart::<Track>(
         config.track_contract, &racing::track_manager::QueryMsg::GetTrack {
         track_id: track_id,
     })?;
+    TRACK_CACHE.save(deps.storage, track_id.u128(), &track)?;
     
     Ok(track)
 }
 
 /// Simulate the complete race
-fn simulate_race(storage: &mut dyn Storage, race_state: &mut RaceState, training_config: TrainingConfig) -> Result<RaceResult, ContractError> {
+pub fn execute_simulate_elimination_race(
+    mut deps: DepsMut,
+    env: Env,
+    track_id: Uint128,
+    car_ids: Vec<u128>,
+    elimination_interval: u32,
+) -> Result<Response, ContractError> {
+    let config = get_config(deps.storage)?;
+    // Needs someone to eliminate
+    if car_ids.len() < 2 || car_ids.len() > MAX_CARS {
+        return Err(ContractError::InvalidCarCount {
+            expected: 2,
+            actual: car_ids.len() as u32
+        });
+    }
+    assert_distinct_cars(&car_ids)?;
+    if elimination_interval == 0 {
+        return Err(ContractError::InvalidRaceConfig);
+    }
+
+    let track = load_track_from_manager(deps.branch(), config.clone(), track_id.clone())?;
+    validate_track(&track)?;
+    let mut race_state = init_race_state(track.layout, &car_ids, config.state_encoding.clone());
+    race_state.q_track = config.per_track_q.then_some(track_id.u128());
+    race_state.distinguish_edges = config.distinguish_edges;
+
+    // Cars race on what they've learned, nothing is trained
+    let training_config = TrainingConfig {
+        training_mode: false,
+        epsilon: 0.0,
+        temperature: 0.0,
+        enable_epsilon_decay: false,
+        lambda: None,
+        init_strategy: None,
+        enable_action_masking: false,
+        deterministic_softmax: false,
+        online_batch_ticks: None,
+        reward_time_decay: None,
+        failure_epsilon_step: None,
+    };
+    let mut race_result = simulate_race(deps.storage, &mut race_state, training_config, config.max_ticks, Some(elimination_interval), false, &RankingMode::default(), None)?;
+
+    let race_key = next_race_key(deps.storage)?;
+    let race_id = format_race_id(track_id, &env, race_key);
+    race_result.race_id = race_id.clone();
+    race_result.track_id = track_id;
+    race_result.car_ids = car_ids.clone();
+
+    add_recent_race(deps.storage, race_key, &race_result, None, None)?;
+
+    let data = SimulateRaceResponse {
+        race_id: race_id.clone(),
+        winner_ids: race_result.winner_ids.clone(),
+        rankings: race_result.rankings.clone(),
+        play_by_play: None,
+        steps_taken: None,
+    };
+
+    Ok(Response::new()
+        .add_attribute("method", "simulate_elimination_race")
+        .add_attribute("race_id", race_id)
+        .add_attribute("car_count", car_ids.len().to_string())
+        .add_attribute("ticks", race_state.tick.to_string())
+        .add_attribute("eliminations", race_result.eliminations.map(|e| e.len()).unwrap_or(0).to_string())
+        .set_data(to_json_binary(&data)?))
+}
+
+/// Race a car against its stored ghost on a track
+pub fn execute_simulate_ghost_race(
+    mut deps: DepsMut,
+    env: Env,
+    track_id: Uint128,
+    car_id: u128,
+) -> Result<Response, ContractError> {
+    let config = get_config(deps.storage)?;
+    let ghost = GHOSTS.may_load(deps.storage, (car_id, track_id.u128()))?
+        .ok_or(ContractError::GhostNotFound { car_id, track_id: track_id.u128() })?;
+
+    let track = load_track_from_manager(deps.branch(), config.clone(), track_id.clone())?;
+    validate_track(&track)?;
+    let mut race_state = init_race_state(track.layout, &[car_id], config.state_encoding.clone());
+    race_state.q_track = config.per_track_q.then_some(track_id.u128());
+    race_state.distinguish_edges = config.distinguish_edges;
+
+    // Time trials run on what the car has learned, nothing is trained
+    let training_config = TrainingConfig {
+        training_mode: false,
+        epsilon: 0.0,
+        temperature: 0.0,
+        enable_epsilon_decay: false,
+        lambda: None,
+        init_strategy: None,
+        enable_action_masking: false,
+        deterministic_softmax: false,
+        online_batch_ticks: None,
+        reward_time_decay: None,
+        failure_epsilon_step: None,
+    };
+    let race_result = simulate_race(deps.storage, &mut race_state, training_config, config.max_ticks, None, false, &RankingMode::default(), None)?;
+
+    let race_id = format_race_id(track_id, &env, next_race_key(deps.storage)?);
+    let car = &race_state.cars[0];
+    let steps = car.finished.then_some(car.steps_taken);
+    let beat_ghost = steps.map_or(false, |steps| steps < ghost.steps);
+    record_ghosts(deps.storage, &race_id, track_id.u128(), &race_state)?;
+
+    let data = GhostRaceResponse {
+        race_id: race_id.clone(),
+        car_id,
+        steps,
+        ghost_steps: ghost.steps,
+        beat_ghost,
+        play_by_play: race_result.play_by_play.get(&car_id).cloned().unwrap_or(racing::race_engine::PlayByPlay {
+            starting_position: racing::race_engine::Position { car_id, x: car.x as u32, y: car.y as u32 },
+            actions: vec![],
+        }),
+        ghost: ghost.play_by_play,
+    };
+
+    Ok(Response::new()
+        .add_attribute("method", "simulate_ghost_race")
+        .add_attribute("race_id", race_id)
+        .add_attribute("car_id", car_id.to_string())
+        .add_attribute("ghost_steps", ghost.steps.to_string())
+        .add_attribute("beat_ghost", beat_ghost.to_string())
+        .set_data(to_json_binary(&data)?))
+}
+
+/// Races on a track in the same block share the block time, the race key tells them apart
+fn format_race_id(track_id: Uint128, env: &Env, race_key: u64) -> String {
+    format!("race_{}_{}_{}", track_id, env.block.time.seconds(), race_key)
+}
+
+/// Keep each finished car's run as its ghost on the track if it's the fastest yet
+fn record_ghosts(storage: &mut dyn Storage, race_id: &str, track_id: u128, race_state: &RaceState) -> Result<(), ContractError> {
+    for car in race_state.cars.iter().filter(|car| car.finished) {
+        let faster = GHOSTS.may_load(storage, (car.car_id, track_id))?
+            .map_or(true, |ghost| car.steps_taken < ghost.steps);
+        if !faster {
+            continue;
+        }
+        if let Some(play_by_play) = race_state.play_by_play.get(&car.car_id) {
+            GHOSTS.save(storage, (car.car_id, track_id), &Ghost {
+                race_id: race_id.to_string(),
+                steps: car.steps_taken,
+                play_by_play: play_by_play.clone(),
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Each car can only race once per race, a repeat would share its Q-table and stats with itself
+fn assert_distinct_cars(car_ids: &[u128]) -> Result<(), ContractError> {
+    let mut seen = HashSet::new();
+    match car_ids.iter().find(|car_id| !seen.insert(**car_id)) {
+        Some(car_id) => Err(ContractError::DuplicateCar { car_id: *car_id }),
+        None => Ok(()),
+    }
+}
+
+/// Place the cars on the start tiles of a fresh race
+fn init_race_state(track_layout: Vec<Vec<racing::types::TrackTile>>, car_ids: &[u128], state_encoding: StateEncoding) -> RaceState {
+    //Find the indices of any starting tiles
+    let start_indices = find_start_indices(&track_layout);
+
+    // Initialize car states
+    // - Everything on CarState (position, wall hits, cached Q-values) is scoped to this race,
+    //   only the Q-table and training stats carry over to the next one
+    let mut cars = vec![];
+    for (i, car_id) in car_ids.iter().enumerate() {
+        //if there are multiple starting tiles, choose car ID mod start_indices.len()
+        let start_index = if start_indices.len() > 1 {
+            (i % start_indices.len()) as usize
+        } else {
+            0
+        };
+        
+        // **NEW**: Query all Q-tables for this car upfront
+        // let q_tables_res = query_full_q_tables(config.clone(), deps.querier, car_id)?;
+        // let q_tables = get_q_tables(q_tables_res)?;
+
+        cars.push(CarState {
+            car_id: car_id.clone(),
+            tile: track_layout[start_indices[start_index].1][start_indices[start_index].0].clone(),
+            x: start_indices[start_index].0 as i32,
+            y: start_indices[start_index].1 as i32,
+            stuck: false,
+            stuck_remaining: 0,
+            move_cooldown: 0,
+            midpoint_rank: None,
+            start_progress: 0,
+            cells_traveled: 0,
+            q_updates_applied: 0,
+            tile_kinds: vec![],
+            failed_races: 0,
+            best_progress: None,
+            eliminated: false,
+            finished: false,
+            steps_taken: 0,
+            last_action: ACTION_UP, // Default to UP
+            // **NEW**: Initialize action history
+            action_history: vec![],
+            // **NEW**: Initialize hit_wall
+            hit_wall: false,
+            wall_hits: 0,
+            drafting: vec![],
+            first_to_checkpoint: vec![],
+            evaded: vec![],
+            new_best: vec![],
+            outcomes: vec![],
+            // **NEW**: Initialize speed modifiers
+            current_speed: DEFAULT_SPEED as u32, // Default normal speed
+            // **NEW**: Initialize Q-tables with pre-queried values
+            q_table: vec![],
+        });
+    }
+
+    // Initialize race state
+    let finish_tiles = find_finish_tiles(&track_layout);
+    RaceState {
+        cars,
+        track_layout,
+        tick: 0,
+        play_by_play: std::collections::HashMap::new(),
+        claimed_checkpoints: vec![],
+        state_encoding,
+        finish_tiles,
+        q_track: None,
+        distinguish_edges: false,
+        shuffle_order: false,
+    }
+}
+
+/// What simulate_race needs to apply Q-updates mid-race, see TrainingConfig::online_batch_ticks
+pub(crate) struct OnlineUpdates<'a> {
+    pub reward_config: &'a RewardNumbers,
+    pub config: &'a Config,
+    pub querier: QuerierWrapper<'a>,
+    pub fastest_track_tick_time: u64,
+}
+
+pub(crate) fn simulate_race(
+    storage: &mut dyn Storage,
+    race_state: &mut RaceState,
+    training_config: TrainingConfig,
+    max_ticks: u32,
+    elimination_interval: Option<u32>,
+    end_on_first_finish: bool,
+    ranking_mode: &RankingMode,
+    online: Option<OnlineUpdates>,
+) -> Result<RaceResult, ContractError> {
     let mut tick = 0;
     
     // Initialize play_by_play for each car
-    for car in &race_state.cars {
+    for car in race_state.cars.iter_mut() {
+        car.start_progress = car.tile.progress_towards_finish;
+        car.cells_traveled = 0;
+        car.q_updates_applied = 0;
+        car.tile_kinds = vec![];
         race_state.play_by_play.insert(car.car_id.clone(), racing::race_engine::PlayByPlay {
             starting_position: racing::race_engine::Position {
                 car_id: car.car_id.clone(),
@@ -482,16 +1243,48 @@ fn simulate_race(storage: &mut dyn Storage, race_state: &mut RaceState, training
         });
     }
     
-    while tick < MAX_TICKS && !all_cars_finished(&race_state.cars) {
+    let mut eliminations = vec![];
+    while tick < max_ticks && !all_cars_finished(&race_state.cars) {
         // Simulate one tick
-        simulate_tick(storage, race_state, training_config.clone(), tick)?;
+        simulate_tick(storage, race_state, training_config.clone(), tick, max_ticks)?;
         
         tick += 1;
         race_state.tick = tick;
+
+        if let (Some(online), Some(batch_ticks)) = (&online, training_config.online_batch_ticks) {
+            if tick % batch_ticks == 0 {
+                apply_online_q_updates(storage, race_state, online, training_config.lambda.unwrap_or(0.0), training_config.reward_time_decay)?;
+            }
+        }
+
+        // Sprint races are over as soon as anyone crosses the line
+        if end_on_first_finish && race_state.cars.iter().any(|car| car.finished) {
+            break;
+        }
+
+        if let Some(interval) = elimination_interval {
+            if tick % interval == 0 {
+                if let Some(car_id) = eliminate_trailing_car(&mut race_state.cars, &eliminations) {
+                    eliminations.push(car_id);
+                }
+            }
+            // Last car standing, or nobody left who could be eliminated
+            let racing = race_state.cars.iter()
+                .filter(|car| !car.finished && !eliminations.contains(&car.car_id))
+                .count();
+            if eliminations.len() + 1 >= race_state.cars.len() || racing == 0 {
+                break;
+            }
+        }
     }
 
+    record_midpoint_ranks(race_state);
+
     // Determine winners and rankings
-    let (winner_ids, rankings, steps_taken) = calculate_results(&race_state.cars, &race_state.track_layout);
+    let (mut winner_ids, mut rankings, steps_taken) = calculate_results(&race_state.cars, &race_state.track_layout, ranking_mode);
+    if elimination_interval.is_some() {
+        rank_eliminations(&race_state.cars, &eliminations, &mut winner_ids, &mut rankings);
+    }
 
     Ok(RaceResult {
         ///Filled by calling function
@@ -503,18 +1296,151 @@ fn simulate_race(storage: &mut dyn Storage, race_state: &mut RaceState, training
         rankings,
         play_by_play: race_state.play_by_play.clone(),
         steps_taken,
+        eliminations: elimination_interval.map(|_| eliminations),
+        teams: None,
+        team_results: None,
     })
 }
 
+/// Apply the Q-updates cars still racing have buffered since the last flush
+/// - A car's latest action waits for the next flush, its next state isn't known yet
+/// - Finished cars keep theirs for the end-of-race update, which knows their rank
+fn apply_online_q_updates(
+    storage: &mut dyn Storage,
+    race_state: &mut RaceState,
+    online: &OnlineUpdates,
+    lambda: f32,
+    reward_time_decay: Option<f32>,
+) -> Result<(), ContractError> {
+    // Nobody has a rank yet
+    let race_result = RaceResult {
+        race_id: String::new(),
+        track_id: Uint128::zero(),
+        car_ids: vec![],
+        winner_ids: vec![],
+        rankings: vec![],
+        play_by_play: HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+    let batch_size = online.config.q_update_batch_size.unwrap_or(DEFAULT_Q_UPDATE_BATCH_SIZE).max(1) as usize;
+
+    for car in race_state.cars.iter_mut().filter(|car| !car.finished) {
+        let ready = car.action_history.len().saturating_sub(1);
+        let mut updates: Vec<QUpdateTuple> = vec![];
+        for i in car.q_updates_applied..ready {
+            let (state_hash, action, tile) = &car.action_history[i];
+            let breakdown = calculate_reward_breakdown(
+                car,
+                &race_result,
+                *action,
+                match i {
+                    0 => car.tile.clone(),
+                    _ => car.action_history[i - 1].2.clone(),
+                },
+                tile.clone(),
+                i,
+                car.action_history.len(),
+                online.reward_config.clone(),
+                online.fastest_track_tick_time,
+                None,
+            )?;
+            let reward = decay_reward(clamp_reward(breakdown.total(), online.reward_config), reward_time_decay, i);
+            updates.push((*state_hash, *action as u8, reward, Some(car.action_history[i + 1].0)));
+        }
+        if updates.is_empty() {
+            continue;
+        }
+
+        let chunks = updates.chunks(batch_size).map(|chunk| chunk.to_vec());
+        apply_batched_q_updates(storage, car, race_state.q_track, chunks, online.config.clone(), online.querier, lambda)?;
+        car.q_updates_applied = ready;
+
+        // Later ticks and flushes start from what was just written
+        for entry in car.q_table.iter_mut() {
+            if let Ok(action_values) = get_q_values(storage, car.car_id, race_state.q_track, &entry.state_hash, &online.config.state_encoding) {
+                entry.action_values = action_values;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rank each car where it stood at the race's halfway tick, replayed from the play-by-play
+/// - Cars that had already finished lead, the rest are ordered by progress_towards_finish, ties to the lower car_id
+fn record_midpoint_ranks(race_state: &mut RaceState) {
+    let midpoint = (race_state.tick / 2) as usize;
+    let mut standings: Vec<(bool, u16, u128)> = race_state.cars.iter()
+        .map(|car| {
+            let position = race_state.play_by_play.get(&car.car_id).map(|play_by_play| match midpoint {
+                0 => Some(&play_by_play.starting_position),
+                _ => play_by_play.actions.get(midpoint - 1).map(|action| &action.resulting_position),
+            });
+            match position {
+                Some(Some(position)) => {
+                    let tile = &race_state.track_layout[position.y as usize][position.x as usize];
+                    (tile.properties.is_finish, tile.progress_towards_finish, car.car_id)
+                },
+                // Finished cars stop recording actions, so a short play-by-play finished before the midpoint
+                Some(None) => (true, car.tile.progress_towards_finish, car.car_id),
+                None => (car.finished, car.tile.progress_towards_finish, car.car_id),
+            }
+        })
+        .collect();
+    standings.sort_by_key(|(finished, progress, car_id)| (std::cmp::Reverse(*finished), std::cmp::Reverse(*progress), *car_id));
+
+    for car in race_state.cars.iter_mut() {
+        car.midpoint_rank = standings.iter()
+            .position(|(_, _, car_id)| *car_id == car.car_id)
+            .map(|rank| rank as u32);
+    }
+}
+
+/// Knock out the car furthest from the finish that's still racing
+/// - Ties go against the higher car_id, matching collision priority
+fn eliminate_trailing_car(cars: &mut [CarState], eliminations: &[u128]) -> Option<u128> {
+    let remaining = cars.iter().filter(|car| !eliminations.contains(&car.car_id)).count();
+    if remaining <= 1 {
+        return None;
+    }
+    let trailing = cars.iter_mut()
+        .filter(|car| !car.finished && !eliminations.contains(&car.car_id))
+        .min_by_key(|car| (car.tile.progress_towards_finish, std::cmp::Reverse(car.car_id)))?;
+    // Stuck cars with no stun countdown sit out every remaining tick
+    trailing.eliminated = true;
+    trailing.stuck = true;
+    trailing.stuck_remaining = 0;
+    Some(trailing.car_id)
+}
+
+/// Eliminated cars rank last, the most recently eliminated highest, and the sole survivor wins
+fn rank_eliminations(cars: &[CarState], eliminations: &[u128], winner_ids: &mut Vec<u128>, rankings: &mut Vec<racing::race_engine::Rank>) {
+    let mut order: Vec<u128> = rankings.iter()
+        .map(|rank| rank.car_id)
+        .filter(|car_id| !eliminations.contains(car_id))
+        .collect();
+    order.extend(eliminations.iter().rev());
+    *rankings = order.into_iter()
+        .enumerate()
+        .map(|(rank, car_id)| racing::race_engine::Rank { car_id, rank: rank as u32 })
+        .collect();
+
+    if eliminations.len() + 1 == cars.len() {
+        *winner_ids = rankings.iter().take(1).map(|rank| rank.car_id).collect();
+    }
+}
+
 /// Simulate one tick of the race
-fn simulate_tick(storage: &mut dyn Storage, race_state: &mut RaceState, training_config: TrainingConfig, tick_index: u32) -> Result<(), ContractError> {
+pub(crate) fn simulate_tick(storage: &mut dyn Storage, race_state: &mut RaceState, training_config: TrainingConfig, tick_index: u32, max_ticks: u32) -> Result<(), ContractError> {
     // **NEW**: Reset car states for this tick
     for car in &mut race_state.cars {
         reset_car_state_for_tick(car);
     }
-    
-    let mut new_positions = vec![];
-    let mut wall_collisions = vec![];
+
+    // Both passes below follow this order, and collision ties too when shuffled, results stay indexed by car
+    let order = processing_order(&race_state.cars, tick_index, race_state.shuffle_order);
     
     // **NEW**: Collect all car positions before the loop to avoid borrow checker issues
     let all_car_positions: Vec<(i32, i32)> = race_state.cars.iter()
@@ -525,47 +1451,59 @@ fn simulate_tick(storage: &mut dyn Storage, race_state: &mut RaceState, training
     let car_finished_status: Vec<bool> = race_state.cars.iter()
         .map(|car| car.finished)
         .collect();
+    // Finished and eliminated cars are off the track as far as the others can tell
+    let off_track: Vec<bool> = race_state.cars.iter()
+        .map(|car| car.finished || car.eliminated)
+        .collect();
+
+    let start_progress: Vec<u16> = race_state.cars.iter()
+        .map(|car| car.tile.progress_towards_finish)
+        .collect();
     
     // Calculate intended moves for all cars
-    let mut car_actions = vec![];
+    // - Default action for cars that don't act this tick, it won't be used
+    let mut car_actions = vec![ACTION_UP; race_state.cars.len()];
+    let init_strategy = training_config.init_strategy.clone().unwrap_or_default();
     
     // First pass: collect all car data and calculate actions
-    for i in 0..race_state.cars.len() {
+    for &i in &order {
         // Get car data without borrowing
         let car_x = race_state.cars[i].x;
         let car_y = race_state.cars[i].y;
         let car_speed = race_state.cars[i].current_speed;
         let car_finished = race_state.cars[i].finished;
         let car_stuck = race_state.cars[i].stuck;
+        let car_cooling_down = race_state.cars[i].move_cooldown > 0;
         
-        if car_finished || car_stuck {
-            new_positions.push((car_x, car_y));
-            wall_collisions.push(false);
-            car_actions.push(ACTION_UP); // Default action, won't be used
+        if car_finished || car_stuck || car_cooling_down {
             continue;
         }
         
         //Get action strategy
-        let strategy = make_action_strategy(training_config.training_mode, training_config.epsilon, training_config.temperature, tick_index, MAX_TICKS, training_config.enable_epsilon_decay); // ε-greedy with 10% explore        
+        let epsilon = exploration_epsilon(&training_config, race_state.cars[i].failed_races);
+        let strategy = make_action_strategy(training_config.training_mode, epsilon, training_config.temperature, tick_index, max_ticks, training_config.enable_epsilon_decay, training_config.deterministic_softmax); // ε-greedy with 10% explore        
         // Get car action based on Q-table or heuristic
         // Get other cars' current positions (excluding this car)
         let other_cars_positions: Vec<(i32, i32)> = all_car_positions.iter()
             .enumerate()
-            .filter(|(j, _)| *j != i && !car_finished_status[*j])
+            .filter(|(j, _)| *j != i && !off_track[*j])
             .map(|(_, pos)| *pos)
             .collect();
         
         // Calculate action and update Q-table cache
-        let action = calculate_car_action(&mut race_state.cars[i], storage, &race_state.track_layout, car_x, car_y, car_speed, &other_cars_positions, strategy, tick_index)?;
-        car_actions.push(action);
+        let action = calculate_car_action(&mut race_state.cars[i], storage, &race_state.track_layout, &race_state.finish_tiles, &race_state.state_encoding, race_state.distinguish_edges, race_state.q_track, &init_strategy, car_x, car_y, car_speed, &other_cars_positions, strategy, tick_index, training_config.enable_action_masking)?;
+        car_actions[i] = action;
         // println!("Car action: {}, position: ({}, {})", action, car_x, car_y);
     }
     
     // Second pass: calculate new positions based on actions
-    for i in 0..race_state.cars.len() {
+    // - Cars that don't act keep their cell
+    let mut new_positions = all_car_positions.clone();
+    let mut wall_collisions = vec![false; race_state.cars.len()];
+    for &i in &order {
         let car = &race_state.cars[i];
-        if car.finished || car.stuck {
-            continue; // Already handled in first pass
+        if car.finished || car.stuck || car.move_cooldown > 0 {
+            continue;
         }
         
         let action = car_actions[i];
@@ -576,20 +1514,29 @@ fn simulate_tick(storage: &mut dyn Storage, race_state: &mut RaceState, training
         // Calculate new position
         let (new_x, new_y, hit_wall) = calculate_new_position(car.x, car.y, action, tile_speed, &race_state.track_layout)?;
         
-        new_positions.push((new_x, new_y));
-        wall_collisions.push(hit_wall);
+        new_positions[i] = (new_x, new_y);
+        wall_collisions[i] = hit_wall;
     }
     
     // Check for collisions
-    let mut final_positions = vec![];
-    for (i, (new_x, new_y)) in new_positions.iter().enumerate() {
-        if check_collision(*new_x, *new_y, &new_positions, i) {
-            // Collision detected, stay in place
-            final_positions.push((race_state.cars[i].x, race_state.cars[i].y));
-        } else {
-            final_positions.push((*new_x, *new_y));
-        }
-    }
+    let final_positions = resolve_collisions(&race_state.cars, &new_positions, race_state.shuffle_order.then_some(order.as_slice()));
+
+    // Near misses: carrying on with its last action would have taken the car into a cell another car
+    // was moving into, and it turned off that heading and still moved
+    let evasions: Vec<bool> = race_state.cars.iter().enumerate()
+        .map(|(i, car)| {
+            let acted = !car.finished && !car.stuck && car.move_cooldown == 0;
+            if !acted || car_actions[i] == car.last_action || wall_collisions[i] || final_positions[i] == (car.x, car.y) {
+                return false;
+            }
+            let heading = match calculate_new_position(car.x, car.y, car.last_action, car.current_speed, &race_state.track_layout) {
+                Ok((x, y, false)) => (x, y),
+                _ => return false,
+            };
+            new_positions.iter().enumerate()
+                .any(|(j, target)| j != i && *target == heading && *target != all_car_positions[j])
+        })
+        .collect();
     
     // Update car positions and apply tile effects
     for (i, car) in race_state.cars.iter_mut().enumerate() {
@@ -604,12 +1551,17 @@ fn simulate_tick(storage: &mut dyn Storage, race_state: &mut RaceState, training
         // Get other cars' current positions (excluding this car)
         let other_cars_positions: Vec<(i32, i32)> = all_car_positions.iter()
             .enumerate()
-            .filter(|(j, _)| *j != i && !car_finished_status[*j])
+            .filter(|(j, _)| *j != i && !off_track[*j])
             .map(|(_, pos)| *pos)
             .collect();
         
-        let state_hash = generate_state_hash(&race_state.track_layout, car.x, car.y, car.current_speed, &other_cars_positions);
-        let action = if car.x != new_x || car.y != new_y { 
+        let state_hash = generate_state_hash(&race_state.track_layout, car.x, car.y, car.current_speed, &other_cars_positions, &race_state.finish_tiles, &race_state.state_encoding, race_state.distinguish_edges);
+        // Collision resolution held the car back from the cell it was heading for
+        let blocked_by_car = !car.stuck && new_positions[i] != (new_x, new_y);
+        let action = if blocked_by_car {
+            // Credit the move it tried, so the collision penalty lands on it
+            car_actions[i]
+        } else if car.x != new_x || car.y != new_y { 
             // Determine action based on movement
             if car.x < new_x { ACTION_RIGHT }
             else if car.x > new_x { ACTION_LEFT }
@@ -622,12 +1574,48 @@ fn simulate_tick(storage: &mut dyn Storage, race_state: &mut RaceState, training
         
         // Record action in history
         car.action_history.push((state_hash, action, car.tile.clone()));
+        car.outcomes.push(if car.stuck {
+            ActionOutcome::Stuck
+        } else if hit_wall {
+            ActionOutcome::HitWall
+        } else if blocked_by_car {
+            ActionOutcome::BlockedByCar
+        } else if (new_x, new_y) == (car.x, car.y) {
+            ActionOutcome::NoMove
+        } else {
+            ActionOutcome::Moved
+        });
+        car.evaded.push(evasions[i]);
+
+        // Stunned cars count down the turns they sat out
+        if car.stuck && car.stuck_remaining > 0 {
+            car.stuck_remaining -= 1;
+            if car.stuck_remaining == 0 {
+                car.stuck = false;
+            }
+        }
+        // Cars on slow tiles count down the ticks they wait between moves
+        if car.move_cooldown > 0 {
+            car.move_cooldown -= 1;
+        }
         
         // **NEW**: Track wall collision
         car.hit_wall = hit_wall;
+        if hit_wall {
+            car.wall_hits += 1;
+        }
         
         // **NEW**: Apply tile effects using properties directly
+        let previous_position = (car.x, car.y);
         apply_tile_effects_to_car(car, new_x, new_y, &race_state.track_layout)?;
+        // Count the cells driven, not where a teleporter dropped the car
+        if (car.x, car.y) != previous_position {
+            car.cells_traveled += ((new_x - previous_position.0).abs() + (new_y - previous_position.1).abs()) as u32;
+            let kind = race_state.track_layout[new_y as usize][new_x as usize].properties.kind();
+            if !car.tile_kinds.contains(&kind) {
+                car.tile_kinds.push(kind);
+            }
+        }
         
         car.last_action = action;
         
@@ -637,51 +1625,136 @@ fn simulate_tick(storage: &mut dyn Storage, race_state: &mut RaceState, training
                 action: action.to_string(),
                 resulting_position: racing::race_engine::Position {
                     car_id: car.car_id.clone(),
-                    x: new_x as u32,
-                    y: new_y as u32,
+                    x: car.x as u32,
+                    y: car.y as u32,
                 },
             });
         }
     }
+
+    // Drafting: moved forward and ended the tick next to an opponent that's further ahead
+    let end_positions: Vec<(i32, i32, u16)> = race_state.cars.iter()
+        .map(|car| (car.x, car.y, car.tile.progress_towards_finish))
+        .collect();
+    for (i, car) in race_state.cars.iter_mut().enumerate() {
+        if car_finished_status[i] {
+            continue;
+        }
+
+        let progress = car.tile.progress_towards_finish;
+        let drafting = progress > start_progress[i]
+            && end_positions.iter().enumerate().any(|(j, (x, y, other_progress))| {
+                j != i
+                    && !off_track[j]
+                    && (x - car.x).abs() + (y - car.y).abs() == 1
+                    && *other_progress > progress
+            });
+        car.drafting.push(drafting);
+    }
+
+    // Checkpoints: only the first car onto each checkpoint tile claims it (PvP only)
+    let pvp = race_state.cars.len() > 1;
+    for (i, car) in race_state.cars.iter_mut().enumerate() {
+        if car_finished_status[i] {
+            continue;
+        }
+
+        let arrived = (car.x, car.y) != all_car_positions[i] && car.tile.properties.is_checkpoint;
+        let first = pvp && arrived && !race_state.claimed_checkpoints.contains(&(car.x, car.y));
+        if first {
+            race_state.claimed_checkpoints.push((car.x, car.y));
+        }
+        car.first_to_checkpoint.push(first);
+    }
+
+    // New best: the first action this race that gets further than the car's earlier training races
+    for (i, car) in race_state.cars.iter_mut().enumerate() {
+        if car_finished_status[i] {
+            continue;
+        }
+
+        let new_best = car.best_progress.is_some_and(|best| car.tile.progress_towards_finish > best)
+            && !car.new_best.contains(&true);
+        car.new_best.push(new_best);
+    }
     
     Ok(())
 }
 
-/// Calculate car action using pre-loaded Q-tables
+/// Calculate car action using pre-loaded Q-tables
+/// Q-values for a state the car hasn't seen before
+fn initial_q_values(track_layout: &[Vec<racing::types::TrackTile>], x: i32, y: i32, seed: u32, init_strategy: &QInitStrategy) -> [i32; 4] {
+    if *init_strategy == QInitStrategy::Zero {
+        return [0; 4];
+    }
+
+    // For new states, use small random initial Q-values instead of zeros
+    // This provides better exploration and prevents all cars from learning the same way
+    let mut q_values = [
+        pseudo_random(seed, 5) as i32,
+        pseudo_random(seed.wrapping_add(1), 5) as i32,
+        pseudo_random(seed.wrapping_add(2), 5) as i32,
+        pseudo_random(seed.wrapping_add(3), 5) as i32,
+    ];
+
+    if *init_strategy == QInitStrategy::DistanceBiased {
+        // Progress of the tile each action moves onto, None for walls and off-track
+        let progress: Vec<Option<u16>> = DIRS.iter()
+            .map(|(dx, dy)| {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 {
+                    return None;
+                }
+                track_layout.get(ny as usize)?
+                    .get(nx as usize)
+                    .filter(|tile| !tile.properties.blocks_movement)
+                    .map(|tile| tile.progress_towards_finish)
+            })
+            .collect();
+
+        if let Some(closest) = progress.iter().flatten().max().copied() {
+            for (action, tile_progress) in progress.iter().enumerate() {
+                if *tile_progress == Some(closest) {
+                    q_values[action] += DISTANCE_PRIMER_BONUS;
+                }
+            }
+        }
+    }
+    q_values
+}
+
 fn calculate_car_action(
     car: &mut CarState,
     storage: &mut dyn Storage,
     track_layout: &[Vec<racing::types::TrackTile>],
+    finish_tiles: &[(i32, i32)],
+    state_encoding: &StateEncoding,
+    distinguish_edges: bool,
+    q_track: Option<u128>,
+    init_strategy: &QInitStrategy,
     x: i32,
     y: i32,
     car_speed: u32,
     other_cars: &[(i32, i32)],
     strategy: ActionSelectionStrategy,
     seed: u32, // required for deterministic randomness
+    action_masking: bool,
 ) -> Result<usize, ContractError> {
     //Set seed.
     // - Allows for deterministic randomness for each car to be different
-    let seed = seed * car.car_id as u32;
+    let seed = car_seed(seed, car.car_id);
     // Generate state hash for current position
-    let state_hash = generate_state_hash(track_layout, x, y, car_speed, other_cars);
+    let state_hash = generate_state_hash(track_layout, x, y, car_speed, other_cars, finish_tiles, state_encoding, distinguish_edges);
     
     // Get Q-values from storage
-    let q_values = if let Ok(stored_values) = Q_TABLE.load(storage, (car.car_id, &state_hash)) {
+    let q_values = if let Ok(stored_values) = get_q_values(storage, car.car_id, q_track, &state_hash, state_encoding) {
         stored_values
     } 
     //If Q-table is not stored, check if it exists in car state
     else if let Some(cached_values) = car.q_table.iter().find(|q| q.state_hash == state_hash) {
         cached_values.action_values.clone()
     } else {
-        // For new states, use small random initial Q-values instead of zeros
-        // This provides better exploration and prevents all cars from learning the same way
-        let random_q_values = [
-            pseudo_random(seed, 5) as i32,
-            pseudo_random(seed + 1, 5) as i32,
-            pseudo_random(seed + 2, 5) as i32,
-            pseudo_random(seed + 3, 5) as i32,
-        ];
-        random_q_values
+        initial_q_values(track_layout, x, y, seed, init_strategy)
     };
     //Store Q-values in car state
     car.q_table.push(QTableEntry {
@@ -689,29 +1762,50 @@ fn calculate_car_action(
         action_values: q_values,
     });
     
-    let action_count = q_values.len() as u32;
+    // Moves into a wall only bounce the car, leave them out if there's anywhere else to go
+    let mut candidates: Vec<usize> = (0..q_values.len()).collect();
+    if action_masking {
+        let open: Vec<usize> = candidates.iter()
+            .copied()
+            .filter(|&action| !matches!(calculate_new_position(x, y, action, car_speed, track_layout), Ok((_, _, true))))
+            .collect();
+        if !open.is_empty() {
+            candidates = open;
+        }
+    }
+
+    Ok(select_action(&q_values, &candidates, strategy, seed))
+}
+
+/// Pick one of the candidate actions from the car's Q-values under the given strategy
+/// - `seed` is the car's seed for the tick, see car_seed
+pub(crate) fn select_action(
+    q_values: &[i32; 4],
+    candidates: &[usize],
+    strategy: ActionSelectionStrategy,
+    seed: u32,
+) -> usize {
+    let action_count = candidates.len() as u32;
+    let best_action = || candidates.iter()
+        .copied()
+        .max_by_key(|&action| q_values[action])
+        .unwrap_or(0);
 
     match strategy {
         ActionSelectionStrategy::Best => {
-            Ok(q_values.iter().enumerate()
-                .max_by_key(|(_, &val)| val)
-                .map(|(idx, _)| idx)
-                .unwrap_or(0))
+            best_action()
         }
 
         ActionSelectionStrategy::Random => {
-            Ok((pseudo_random(seed, action_count)) as usize)
+            candidates[pseudo_random(seed, action_count) as usize]
         }
 
         ActionSelectionStrategy::EpsilonGreedy(epsilon) => {
             let threshold = (epsilon * 100.0) as u32;
             if pseudo_random(seed, 100) < threshold {
-                Ok((pseudo_random(seed + 1, action_count)) as usize)
+                candidates[pseudo_random(seed.wrapping_add(1), action_count) as usize]
             } else {
-                Ok(q_values.iter().enumerate()
-                    .max_by_key(|(_, &val)| val)
-                    .map(|(idx, _)| idx)
-                    .unwrap_or(0))
+                best_action()
             }
         }
 
@@ -723,18 +1817,15 @@ fn calculate_car_action(
             
             let threshold = (current_epsilon * 100.0) as u32;
             if pseudo_random(seed, 100) < threshold {
-                Ok((pseudo_random(seed + 1, action_count)) as usize)
+                candidates[pseudo_random(seed.wrapping_add(1), action_count) as usize]
             } else {
-                Ok(q_values.iter().enumerate()
-                    .max_by_key(|(_, &val)| val)
-                    .map(|(idx, _)| idx)
-                    .unwrap_or(0))
+                best_action()
             }
         }
 
         ActionSelectionStrategy::Softmax(temp) => {
-            let exp_vals: Vec<f32> = q_values.iter()
-                .map(|&q| ((q as f32) / temp).exp())
+            let exp_vals: Vec<f32> = candidates.iter()
+                .map(|&action| ((q_values[action] as f32) / temp).exp())
                 .collect();
 
             let sum: f32 = exp_vals.iter().sum();
@@ -746,15 +1837,61 @@ fn calculate_car_action(
             for (i, &p) in probs.iter().enumerate() {
                 acc += p;
                 if sample < acc {
-                    return Ok(i);
+                    return candidates[i];
+                }
+            }
+
+            candidates[candidates.len() - 1] // fallback
+        }
+
+        ActionSelectionStrategy::DeterministicSoftmax(milli_temperature) => {
+            let weights = integer_softmax_weights(q_values, candidates, milli_temperature);
+            let total: u64 = weights.iter().sum();
+            let sample = pseudo_random(seed, 10000) as u64;
+
+            // sample / 10000 < acc / total, kept in integers
+            let mut acc = 0;
+            for (i, &weight) in weights.iter().enumerate() {
+                acc += weight;
+                if sample * total < acc * 10000 {
+                    return candidates[i];
                 }
             }
 
-            Ok(action_count as usize - 1) // fallback
+            candidates[candidates.len() - 1] // fallback
         }
     }
 }
 
+/// 2^(-r/16) for r in 0..16, scaled by 2^32
+const POW2_NEG_SIXTEENTHS: [u64; 16] = [
+    4294967296, 4112874773, 3938502376, 3771522796, 3611622603, 3458501653, 3311872529, 3171459999,
+    3037000500, 2908241642, 2784941738, 2666869345, 2553802834, 2445529972, 2341847524, 2242560872,
+];
+/// log2(e) scaled by 2^16
+const LOG2_E_Q16: u64 = 94548;
+
+/// Unnormalised softmax weights for the candidate actions, bit-identical on every target
+/// - exp((q - q_max) / T) is taken as 2^(-(q_max - q) * log2(e) / T), rounded down to a sixteenth of a power of two
+/// - Q-values are clamped to MIN_Q_VALUE..=MAX_Q_VALUE so the gap stays small, and the best action always weighs 2^32
+fn integer_softmax_weights(q_values: &[i32; 4], candidates: &[usize], milli_temperature: u32) -> Vec<u64> {
+    let q_max = candidates.iter().map(|&action| q_values[action]).max().unwrap_or(0);
+    let milli_temperature = milli_temperature.max(1) as u64;
+    candidates.iter()
+        .map(|&action| {
+            let gap = q_max.abs_diff(q_values[action]) as u64;
+            // Exponent in sixteenths of a power of two
+            let sixteenths = gap * 1000 * LOG2_E_Q16 * 16 / (milli_temperature << 16);
+            let shift = sixteenths / 16;
+            if shift >= 32 {
+                0
+            } else {
+                POW2_NEG_SIXTEENTHS[(sixteenths % 16) as usize] >> shift
+            }
+        })
+        .collect()
+}
+
 // /// Query Q-table from car contract
 // fn query_car_q_table(
 //     car_id: u128,
@@ -784,6 +1921,9 @@ enum TileFlag { Wall=0, Sticky=1, Boost=2, Finish=3, Normal=4 }
 #[repr(u8)]
 enum Dir3 { None=0, Up=1, Down=2, Left=3, Right=4 }
 
+#[repr(u8)]
+enum SpeedBucket { Slow=0, Normal=1, Boost=2 }
+
 const DIRS: [(i32, i32); 4] = [(0,-1), (0,1), (-1,0), (1,0)]; // U D L R
 
 pub fn generate_state_hash(
@@ -791,10 +1931,13 @@ pub fn generate_state_hash(
     x: i32, y: i32,
     speed: u32,
     other_cars: &[(i32,i32)],
+    finish_tiles: &[(i32,i32)],
+    encoding: &StateEncoding,
+    distinguish_edges: bool,
 ) -> [u8; 32] {
 
-    // ---------- 1. build 22-bit key ----------
-    let mut key: u32 = 0;           // we’ll only use lowest 22 bits
+    // ---------- 1. build 24-bit key ----------
+    let mut key: u32 = 0;           // we’ll only use lowest 24 bits, 28 with distinguish_edges
     for (i, &(dx,dy)) in DIRS.iter().enumerate() {
         let tx = x + dx * speed as i32;
         let ty = y + dy * speed as i32;
@@ -831,38 +1974,80 @@ pub fn generate_state_hash(
     }
 
     // ---------- 2. closest-car direction ----------
-    let mut dir3 = Dir3::None as u8;
-    if !other_cars.is_empty() {
-        let (mut best_d2, mut best_dir) = (i32::MAX, Dir3::None as u8);
-        for &(cx,cy) in other_cars {
-            let dx = cx - x;
-            let dy = cy - y;
-            let d2 = dx*dx + dy*dy;
-            if d2 < best_d2 {
-                best_d2 = d2;
-                best_dir = if dx.abs() > dy.abs() {
-                    if dx > 0 { Dir3::Right } else { Dir3::Left }
-                } else {
-                    if dy > 0 { Dir3::Down }  else { Dir3::Up }
-                } as u8;
-            }
-        }
-        dir3 = best_dir;
-    }
+    let dir3 = nearest_direction(x, y, other_cars);
     key |= (dir3 as u32) << 16;   // bits 16-18
 
-    // ---------- 3. hash ----------
-    let mut hasher = Blake2bVar::new(32).unwrap(); // 256-bit
-    let key_bytes = key.to_le_bytes();            // 4 bytes, lowest 3 used
-    hasher.update(&key_bytes[..3]);               // feed 3 tight bytes
+    // ---------- 3. closest-finish direction ----------
+    // Changing the key layout invalidates Q-tables learned before it
+    let finish_dir3 = nearest_direction(x, y, finish_tiles);
+    key |= (finish_dir3 as u32) << 19;   // bits 19-21
+
+    // ---------- 4. speed bucket ----------
+    // Also a key layout change, Q-tables from before it no longer match
+    // - Slow tiles hold a car back with move_cooldown rather than its speed, a car on one is in the slow bucket
+    let on_slow_tile = track.get(y as usize)
+        .and_then(|row| row.get(x as usize))
+        .is_some_and(|tile| tile.properties.slow_ticks > 0);
+    let speed_bucket = if speed < DEFAULT_SPEED as u32 || on_slow_tile {
+        SpeedBucket::Slow
+    } else if speed == DEFAULT_SPEED as u32 {
+        SpeedBucket::Normal
+    } else {
+        SpeedBucket::Boost
+    } as u8;
+    key |= (speed_bucket as u32) << 22;   // bits 22-23
+
+    // ---------- 5. edge distance ----------
+    // Out-of-bounds probes read as walls, this tells a map edge apart from an interior one
+    // - Cells to the nearest side edge and to the nearest top/bottom edge, capped at 3
+    if distinguish_edges {
+        let width = track.first().map_or(0, |row| row.len()) as i32;
+        let height = track.len() as i32;
+        let edge_x = x.min(width - 1 - x).clamp(0, 3) as u32;
+        let edge_y = y.min(height - 1 - y).clamp(0, 3) as u32;
+        key |= edge_x << 24;   // bits 24-25
+        key |= edge_y << 26;   // bits 26-27
+    }
+
+    // ---------- 6. encode ----------
+    let key_bytes = key.to_le_bytes();            // 4 bytes, lowest 3 used unless distinguish_edges
     let mut out = [0u8; 32];
-    hasher.finalize_variable(&mut out);
+    match encoding {
+        StateEncoding::Hashed => {
+            // Without the edge field, hash the same 3 bytes as before it existed
+            let key_len = if distinguish_edges { 4 } else { 3 };
+            let mut hasher = Blake2bVar::new(32).unwrap(); // 256-bit
+            hasher.update(&key_bytes[..key_len]);
+            hasher.finalize_variable(&mut out);
+        }
+        // The key is already unique per state, keep it as is (zero-padded)
+        StateEncoding::Packed => out[..4].copy_from_slice(&key_bytes),
+    }
 
     out
 }
 
+/// Direction of the closest point, None if there are none
+fn nearest_direction(x: i32, y: i32, points: &[(i32, i32)]) -> u8 {
+    let (mut best_d2, mut best_dir) = (i32::MAX, Dir3::None as u8);
+    for &(px,py) in points {
+        let dx = px - x;
+        let dy = py - y;
+        let d2 = dx*dx + dy*dy;
+        if d2 < best_d2 {
+            best_d2 = d2;
+            best_dir = if dx.abs() > dy.abs() {
+                if dx > 0 { Dir3::Right } else { Dir3::Left }
+            } else {
+                if dy > 0 { Dir3::Down }  else { Dir3::Up }
+            } as u8;
+        }
+    }
+    best_dir
+}
+
 /// Calculate new position based on action
-fn calculate_new_position(
+pub(crate) fn calculate_new_position(
     x: i32,
     y: i32,
     action: usize,
@@ -877,6 +2062,13 @@ fn calculate_new_position(
         _ => return Err(ContractError::InvalidAction { action }),
     };
 
+    // One-way tiles bounce moves in a direction they don't allow, the car stays put
+    // - Action indices line up with the DIRECTION_* bits
+    let allowed_directions = track_layout[y as usize][x as usize].properties.allowed_directions;
+    if allowed_directions != 0 && allowed_directions & (1 << action) == 0 {
+        return Ok((x, y, true));
+    }
+
     let mut new_x = x + dx;
     let mut new_y = y + dy;
     let mut hit_wall = false;
@@ -927,6 +2119,7 @@ fn apply_tile_effects_to_car(
 ) -> Result<(), ContractError> {
     //Increment steps taken
     car.steps_taken += 1;
+    let previous_position = (car.x, car.y);
 
     // Check bounds before accessing tile
     let out_of_bounds = new_x < 0 || new_y < 0 || 
@@ -957,19 +2150,45 @@ fn apply_tile_effects_to_car(
         car.tile = tile.clone();
     } else if tile.properties.blocks_movement {
         // Wall - stay in place
-    } else if tile.properties.skip_next_turn {
-        // Sticky tile - move but skip next turn
+    } else if tile.properties.skip_next_turn || tile.properties.stun_ticks > 0 {
+        // Sticky tile - move but sit out the next stun_ticks turns
+        // - Only entering the tile stuns, a car idling on it isn't stunned again
+        let entering = (car.x, car.y) != (new_x, new_y);
         car.x = new_x;
         car.y = new_y;
         car.tile = tile.clone();
-        car.stuck = true; // Will be reset next turn
+        if entering {
+            car.stuck = true;
+            car.stuck_remaining = tile.properties.stun_ticks.max(1);
+        }
     } else {
         // Normal movement
+        // - Entering a slow tile starts the wait before the next move
+        if tile.properties.slow_ticks > 0 && (car.x, car.y) != (new_x, new_y) {
+            car.move_cooldown = tile.properties.slow_ticks;
+        }
         car.x = new_x;
         car.y = new_y;
         car.tile = tile.clone();
     }
     
+    // Teleporters relocate the car within the same tick, so the next state hash is taken at the partner tile
+    // - Only arriving teleports, targets don't chain, and a wall or off-track target leaves the car where it landed
+    if let Some((target_x, target_y)) = tile.properties.teleport_target {
+        let landed = previous_position != (new_x, new_y) && (car.x, car.y) == (new_x, new_y);
+        let target = track_layout
+            .get(target_y as usize)
+            .and_then(|row| row.get(target_x as usize))
+            .filter(|target| !target.properties.blocks_movement);
+        if let (true, false, Some(target)) = (landed, car.finished, target) {
+            car.x = target_x as i32;
+            car.y = target_y as i32;
+            car.tile = target.clone();
+            car.current_speed = target.properties.speed_modifier;
+            car.finished = target.properties.is_finish;
+        }
+    }
+
     // Apply damage/healing
     if tile.properties.damage != 0 {
         // TODO: Implement damage system if needed
@@ -986,22 +2205,98 @@ fn reset_car_state_for_tick(car: &mut CarState) {
 }
 
 /// Check for collision between cars
-fn check_collision(x: i32, y: i32, positions: &[(i32, i32)], current_car: usize) -> bool {
+fn check_collision(x: i32, y: i32, cars: &[CarState], positions: &[(i32, i32)], current_car: usize) -> bool {
     for (i, (other_x, other_y)) in positions.iter().enumerate() {
-        if i != current_car && *other_x == x && *other_y == y {
+        if i != current_car && !cars[i].eliminated && *other_x == x && *other_y == y {
             return true;
         }
     }
     false
 }
 
+/// Resolve cars contending for the same cell
+/// - A car that isn't moving keeps its cell, anyone moving into it stays put
+/// - Among moving cars the highest priority keeps its move (see `collision_priority`), the rest stay put
+/// - Two cars swapping cells head-on are both blocked
+/// - Eliminated cars don't hold their cell, anyone can drive into it
+/// - Repeats until settled, since a car sent back to its cell can block another
+/// - `shuffled_order` is the tick's shuffled processing_order, which settles the last ties instead of car_id
+pub(crate) fn resolve_collisions(cars: &[CarState], intended_positions: &[(i32, i32)], shuffled_order: Option<&[usize]>) -> Vec<(i32, i32)> {
+    let tie_breaks: Vec<u128> = match shuffled_order {
+        Some(order) => {
+            let mut turns = vec![0; cars.len()];
+            for (turn, &i) in order.iter().enumerate() {
+                turns[i] = turn as u128;
+            }
+            turns
+        }
+        None => cars.iter().map(|car| car.car_id).collect(),
+    };
+    let mut final_positions = intended_positions.to_vec();
+    loop {
+        let mut blocked = vec![];
+        for (i, car) in cars.iter().enumerate() {
+            let (x, y) = final_positions[i];
+            let moving = (x, y) != (car.x, car.y);
+            if !moving {
+                continue;
+            }
+            if check_swap(cars, &final_positions, i) {
+                blocked.push(i);
+                continue;
+            }
+            if !check_collision(x, y, cars, &final_positions, i) {
+                continue;
+            }
+
+            let outranked = final_positions.iter().enumerate().any(|(j, position)| {
+                j != i && !cars[j].eliminated && *position == (x, y) && (
+                    *position == (cars[j].x, cars[j].y)
+                    || collision_priority(&cars[j], tie_breaks[j]) > collision_priority(car, tie_breaks[i])
+                )
+            });
+            if outranked {
+                blocked.push(i);
+            }
+        }
+
+        if blocked.is_empty() {
+            return final_positions;
+        }
+        for i in blocked {
+            final_positions[i] = (cars[i].x, cars[i].y);
+        }
+    }
+}
+
+/// Check if a car is moving into the cell of a car moving into its own cell
+fn check_swap(cars: &[CarState], positions: &[(i32, i32)], current_car: usize) -> bool {
+    let current = &cars[current_car];
+    cars.iter().enumerate().any(|(i, other)| {
+        i != current_car
+            && !other.eliminated
+            && positions[current_car] == (other.x, other.y)
+            && positions[i] == (current.x, current.y)
+    })
+}
+
+/// Priority for contested cells: higher progress_towards_finish, then fewer steps_taken, then the lower tie_break
+/// - tie_break is the car_id, or the car's turn in a shuffled tick
+fn collision_priority(car: &CarState, tie_break: u128) -> (u16, std::cmp::Reverse<u32>, std::cmp::Reverse<u128>) {
+    (
+        car.tile.progress_towards_finish,
+        std::cmp::Reverse(car.steps_taken),
+        std::cmp::Reverse(tie_break),
+    )
+}
+
 /// Check if all cars have finished
 fn all_cars_finished(cars: &[CarState]) -> bool {
     cars.iter().all(|car| car.finished)
 }
 
 /// Calculate race results using progress_towards_finish from tile properties
-fn calculate_results(cars: &[CarState], track_layout: &[Vec<racing::types::TrackTile>]) -> (Vec<u128>, Vec<racing::race_engine::Rank>, Vec<racing::race_engine::Step>) {
+pub(crate) fn calculate_results(cars: &[CarState], track_layout: &[Vec<racing::types::TrackTile>], ranking_mode: &RankingMode) -> (Vec<u128>, Vec<racing::race_engine::Rank>, Vec<racing::race_engine::Step>) {
     let mut finished_cars: Vec<_> = cars.iter()
         .filter(|car| car.finished)
         .collect();
@@ -1011,7 +2306,8 @@ fn calculate_results(cars: &[CarState], track_layout: &[Vec<racing::types::Track
         .collect();
     
     // Sort finished cars by steps taken (lower is better)
-    finished_cars.sort_by_key(|car| car.steps_taken);
+    // Ties on the same tick go to fewer wall hits, then the lower car_id
+    finished_cars.sort_by_key(|car| (car.steps_taken, car.wall_hits, car.car_id));
     
     // Sort unfinished cars by progress_towards_finish (higher progress = closer to finish)
     unfinished_cars.sort_by_key(|car| {
@@ -1025,32 +2321,68 @@ fn calculate_results(cars: &[CarState], track_layout: &[Vec<racing::types::Track
         .map(|car| car.car_id.clone())
         .collect();
     
-    // Rankings: finished cars first (by steps), then unfinished cars (by progress)
-    let mut rankings = vec![];
-    for (rank, car) in finished_cars.iter().enumerate() {
-        rankings.push(racing::race_engine::Rank {
+    let ranked_cars: Vec<&CarState> = match ranking_mode {
+        // Finished cars first (by steps), then unfinished cars (by progress)
+        RankingMode::FinishedFirst => finished_cars.into_iter().chain(unfinished_cars).collect(),
+        // Finishers score the best progress short of the finish line plus the ticks they had to spare
+        RankingMode::ProgressUnified => {
+            let best_progress = track_layout.iter()
+                .flatten()
+                .filter(|tile| !tile.properties.is_finish)
+                .map(|tile| tile.progress_towards_finish as u32)
+                .max()
+                .unwrap_or(0);
+            let race_ticks = cars.iter().map(|car| car.steps_taken).max().unwrap_or(0);
+            let mut ranked_cars: Vec<_> = cars.iter().collect();
+            ranked_cars.sort_by_key(|car| {
+                let score = if car.finished {
+                    best_progress + (race_ticks - car.steps_taken)
+                } else {
+                    car.tile.progress_towards_finish as u32
+                };
+                (std::cmp::Reverse(score), car.steps_taken, car.wall_hits, car.car_id)
+            });
+            ranked_cars
+        },
+    };
+    let rankings = ranked_cars.iter()
+        .enumerate()
+        .map(|(rank, car)| racing::race_engine::Rank {
             car_id: car.car_id.clone(),
             rank: rank as u32,
-        });
-    }
-    for (rank, car) in unfinished_cars.iter().enumerate() {
-        rankings.push(racing::race_engine::Rank {
-            car_id: car.car_id.clone(),
-            rank: (finished_cars.len() + rank) as u32,
-        });
-    }
+        })
+        .collect();
     
     // Steps taken for each car
     let steps_taken = cars.iter()
         .map(|car| racing::race_engine::Step {
             car_id: car.car_id.clone(),
             steps_taken: car.steps_taken,
+            finished: car.finished,
         })
         .collect();
     
     (winner_ids, rankings, steps_taken)
 }
 
+/// Rank teams by their best finisher
+/// - Returns (team_id, best individual rank), best team first, ties to the lower team_id
+pub(crate) fn calculate_team_results(teams: &[Vec<u128>], rankings: &[racing::race_engine::Rank]) -> Vec<(u32, u32)> {
+    let mut team_results: Vec<(u32, u32)> = teams.iter()
+        .enumerate()
+        .map(|(team_id, team)| {
+            let best = rankings.iter()
+                .filter(|rank| team.contains(&rank.car_id))
+                .map(|rank| rank.rank)
+                .min()
+                .unwrap_or(u32::MAX);
+            (team_id as u32, best)
+        })
+        .collect();
+    team_results.sort_by_key(|(team_id, score)| (*score, *team_id));
+    team_results
+}
+
 /// Create a test track for development
 fn create_test_track() -> Vec<Vec<racing::types::TrackTile>> {
     let width = 10;
@@ -1077,7 +2409,7 @@ fn create_test_track() -> Vec<Vec<racing::types::TrackTile>> {
     for x in 0..width {
         track[height-1][x] = racing::types::TrackTile {
             properties: racing::types::TileProperties::start(),
-            progress_towards_finish: height as u16 - 1,
+            progress_towards_finish: 0,
             x: x as u8,
             y: (height-1) as u8,
         };
@@ -1127,11 +2459,11 @@ fn create_test_track() -> Vec<Vec<racing::types::TrackTile>> {
         y: 6,
     };
     
-    // Set proper coordinates and distances
+    // Set proper coordinates and distances, progress is higher closer to the finish
     for y in 0..height {
         for x in 0..width {
             if x < track[y].len() {
-                track[y][x].progress_towards_finish = y as u16;
+                track[y][x].progress_towards_finish = (height - 1 - y) as u16;
                 track[y][x].x = x as u8;
                 track[y][x].y = y as u8;
             }
@@ -1142,15 +2474,144 @@ fn create_test_track() -> Vec<Vec<racing::types::TrackTile>> {
 }
 
 
+/// Car race records are a cache, a car contract that won't record an outcome (car not minted there,
+/// engine not allowed to report) leaves that car's record behind rather than failing the race
+#[entry_point]
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        RECORD_RACE_OUTCOME_REPLY_ID => Ok(Response::new()
+            .add_attribute("method", "record_race_outcome_skipped")
+            .add_attribute("reason", msg.result.into_result().err().unwrap_or_default())),
+        id => Err(ContractError::SimulationError { message: format!("Unknown reply id: {}", id) }),
+    }
+}
+
 #[entry_point]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetRaceResult { race_id, track_id } => to_json_binary(&query_race_result(deps, track_id, race_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetRaceResultById { race_id } => to_json_binary(&query_race_result_by_id(deps, race_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetRaceStandings { race_id, track_id } => to_json_binary(&query_race_standings(deps, track_id, race_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
         QueryMsg::ListRecentRaces { car_id, track_id, start_after, limit } => to_json_binary(&query_recent_races(deps, car_id, track_id, start_after, limit).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
         QueryMsg::GetConfig {  } => to_json_binary(&CONFIG.load(deps.storage).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
-        QueryMsg::GetQ { car_id, state_hash } => to_json_binary(&query_q_values(deps, car_id, state_hash).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetQ { car_id, track_id, state_hash, start_after, limit } => to_json_binary(&query_q_values(deps, car_id, track_id, state_hash, start_after, limit).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
         QueryMsg::GetTrackTrainingStats { car_id, track_id, start_after, limit } => to_json_binary(&query_track_training_stats(deps, car_id, track_id, start_after, limit).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetMultiCarStats { car_ids, track_id } => to_json_binary(&query_multi_car_stats(deps, car_ids, track_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetTimeoutValue { track_id } => to_json_binary(&query_timeout_value(deps, track_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetQTableSize { car_id, track_id } => to_json_binary(&query_q_table_size(deps, car_id, track_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetCarWins { car_id, track_id, limit } => to_json_binary(&query_car_wins(deps, car_id, track_id, limit).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetBestAction { car_id, track_id, state_hash } => to_json_binary(&query_best_action(deps, car_id, track_id, state_hash).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::SimulateActionSelection { car_id, track_id, state_hash, strategy, seed } => to_json_binary(&query_simulate_action_selection(deps, car_id, track_id, state_hash, strategy, seed).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetRewardBreakdown { car_id } => to_json_binary(&query_reward_breakdown(deps, car_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetCarStatsSummary { car_id } => to_json_binary(&query_car_stats_summary(deps, car_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetCarTracks { car_id } => to_json_binary(&query_car_tracks(deps, car_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::ExportQTable { car_id, track_id } => to_json_binary(&query_export_q_table(deps, car_id, track_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::ExportPolicy { car_id, track_id, start_after, limit } => to_json_binary(&query_export_policy(deps, car_id, track_id, start_after, limit).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::VerifyReplay { race_id, expected_hash } => to_json_binary(&query_verify_replay(deps, race_id, expected_hash).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetLastRaceSummary { car_id } => to_json_binary(&query_last_race_summary(deps, car_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetEngineStats {} => to_json_binary(&query_engine_stats(deps).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetNonFinishReason { race_id, car_id } => to_json_binary(&query_non_finish_reason(deps, race_id, car_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetRaceInputs { race_id } => to_json_binary(&query_race_inputs(deps, race_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+    }
+}
+
+pub fn query_best_action(deps: Deps, car_id: u128, track_id: Option<u128>, state_hash: [u8; 32]) -> Result<BestActionResponse, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let stored = get_q_values(deps.storage, car_id, track_id, &state_hash, &config.state_encoding).ok();
+
+    let best_action = stored.as_ref().map(greedy_action);
+
+    Ok(BestActionResponse {
+        car_id,
+        state_hash,
+        best_action,
+        action_values: stored.unwrap_or([0; 4]),
+    })
+}
+
+/// Greedy action for a state's Q-values, same tie-break as ActionSelectionStrategy::Best
+fn greedy_action(q_values: &[i32; 4]) -> usize {
+    q_values.iter().enumerate()
+        .max_by_key(|(_, &val)| val)
+        .map(|(idx, _)| idx)
+        .unwrap_or(0)
+}
+
+pub fn query_simulate_action_selection(
+    deps: Deps,
+    car_id: u128,
+    track_id: Option<u128>,
+    state_hash: [u8; 32],
+    strategy: ActionSelectionStrategy,
+    seed: u32,
+) -> Result<ActionSelectionResponse, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let action_values = get_q_values(deps.storage, car_id, track_id, &state_hash, &config.state_encoding).unwrap_or([0; 4]);
+
+    // No position to mask from, so every action is a candidate
+    let candidates: Vec<usize> = (0..action_values.len()).collect();
+    let action = select_action(&action_values, &candidates, strategy, car_seed(seed, car_id));
+
+    Ok(ActionSelectionResponse {
+        car_id,
+        state_hash,
+        action,
+        action_values,
+    })
+}
+
+pub fn query_reward_breakdown(deps: Deps, car_id: u128) -> Result<RewardBreakdownResponse, ContractError> {
+    Ok(RewardBreakdownResponse {
+        car_id,
+        breakdown: LAST_REWARD_BREAKDOWN.may_load(deps.storage, car_id)?,
+    })
+}
+
+pub fn query_last_race_summary(deps: Deps, car_id: u128) -> Result<Option<LastRaceSummary>, ContractError> {
+    Ok(LAST_RACE_SUMMARY.may_load(deps.storage, car_id)?)
+}
+
+pub fn query_q_table_size(deps: Deps, car_id: u128, track_id: Option<u128>) -> Result<QTableSizeResponse, ContractError> {
+    let prefix = match track_id {
+        Some(track_id) => TRACK_Q_TABLE.prefix((car_id, track_id)),
+        None => Q_TABLE.prefix(car_id),
+    };
+    let mut entries = 0u64;
+    let mut estimated_bytes = 0u64;
+    for key in prefix.keys_raw(deps.storage, None, None, cosmwasm_std::Order::Ascending) {
+        entries += 1;
+        estimated_bytes += key.len() as u64 + Q_VALUES_BYTES;
     }
+
+    Ok(QTableSizeResponse {
+        car_id,
+        entries,
+        estimated_bytes,
+    })
+}
+
+pub fn query_engine_stats(deps: Deps) -> Result<EngineStatsResponse, ContractError> {
+    let total_races = TOTAL_RACES.may_load(deps.storage)?.unwrap_or_default();
+    let total_ticks = TOTAL_TICKS.may_load(deps.storage)?.unwrap_or_default();
+    let total_cars = TOTAL_CARS.may_load(deps.storage)?.unwrap_or_default();
+    let total_q_entries = TOTAL_Q_ENTRIES.may_load(deps.storage)?.unwrap_or_default();
+
+    Ok(EngineStatsResponse {
+        total_races,
+        total_cars,
+        total_q_entries,
+        total_ticks,
+        average_ticks: total_ticks.checked_div(total_races).unwrap_or(0),
+    })
+}
+
+pub fn query_timeout_value(deps: Deps, track_id: u128) -> Result<TimeoutValueResponse, ContractError> {
+    // No per-track limits yet, every track uses the config default
+    let config = CONFIG.load(deps.storage)?;
+    Ok(TimeoutValueResponse {
+        track_id,
+        max_ticks: config.max_ticks,
+    })
 }
 
 
@@ -1159,28 +2620,41 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
 pub fn query_q_values(
     deps: Deps,
     car_id: u128,
+    track_id: Option<u128>,
     state_hash: Option<[u8; 32]>,
+    start_after: Option<[u8; 32]>,
+    limit: Option<u32>,
 ) -> Result<GetQResponse, ContractError> {
     // Check if car exists
     // get_car_info(deps.storage, &car_id)?;
     
+    let config = CONFIG.load(deps.storage)?;
     let q_values = match state_hash {
         Some(hash) => {
             // Return single Q-table entry
-            let action_values = get_q_values(deps.storage, car_id, &hash).unwrap_or([0; 4]);
+            let action_values = get_q_values(deps.storage, car_id, track_id, &hash, &config.state_encoding).unwrap_or([0; 4]);
             vec![QTableEntry {
                 state_hash: hash,
                 action_values,
             }]
         }
         None => {
-            // Return all Q-table entries for this car
+            // Return a page of Q-table entries for this car
+            let limit = limit.unwrap_or(MAX_LIMIT).min(MAX_LIMIT) as usize;
+            let start_after = start_after.as_ref()
+                .map(|hash| Bound::exclusive(state_key(hash, &config.state_encoding)));
             let mut entries = vec![];
-            let range = Q_TABLE.prefix(car_id).range(deps.storage, None, None, cosmwasm_std::Order::Ascending);
+            let prefix = match track_id {
+                Some(track_id) => TRACK_Q_TABLE.prefix((car_id, track_id)),
+                None => Q_TABLE.prefix(car_id),
+            };
+            let range = prefix
+                .range(deps.storage, start_after, None, cosmwasm_std::Order::Ascending)
+                .take(limit);
             for item in range {
-                let (state_hash, action_values) = item.map_err(|e| ContractError::Std(e))?;
+                let (key, action_values) = item.map_err(|e| ContractError::Std(e))?;
                 entries.push(QTableEntry {
-                    state_hash,
+                    state_hash: state_hash_from_key(&key),
                     action_values,
                 });
             }
@@ -1213,12 +2687,115 @@ pub fn query_race_result(
                 rankings: r.rankings,
                 play_by_play: r.play_by_play.into_iter().map(|(k, v)| (k, v)).collect(),
                 steps_taken: r.steps_taken,
+                eliminations: r.eliminations,
+                teams: r.teams,
+                team_results: r.team_results,
             }
         }),
         None => Err(ContractError::RaceNotFound { race_id }),
     }
 }
 
+pub fn query_race_standings(
+    deps: Deps,
+    track_id: u128,
+    race_id: String,
+) -> Result<Vec<Standing>, ContractError> {
+    let result = query_race_result(deps, track_id, race_id)?.result;
+
+    let mut standings: Vec<Standing> = result.rankings.iter()
+        .map(|rank| {
+            let step = result.steps_taken.iter().find(|step| step.car_id == rank.car_id);
+            Standing {
+                car_id: rank.car_id,
+                rank: rank.rank,
+                steps: step.map_or(0, |step| step.steps_taken),
+                finished: step.is_some_and(|step| step.finished),
+            }
+        })
+        .collect();
+    standings.sort_by_key(|standing| standing.rank);
+    Ok(standings)
+}
+
+/// Classify a car's non-finish from the positions in its play-by-play
+pub fn query_non_finish_reason(
+    deps: Deps,
+    race_id: String,
+    car_id: u128,
+) -> Result<Option<NonFinishReason>, ContractError> {
+    let result = query_race_result_by_id(deps, race_id)?.result;
+    let play_by_play = result.play_by_play.get(&car_id)
+        .ok_or_else(|| ContractError::CarNotFound { car_id: car_id.to_string() })?;
+
+    if result.steps_taken.iter().any(|step| step.car_id == car_id && step.finished) {
+        return Ok(None);
+    }
+    if result.eliminations.as_ref().is_some_and(|eliminations| eliminations.contains(&car_id)) {
+        return Ok(Some(NonFinishReason::Eliminated));
+    }
+
+    let positions: Vec<(u32, u32)> = std::iter::once(&play_by_play.starting_position)
+        .chain(play_by_play.actions.iter().map(|action| &action.resulting_position))
+        .map(|position| (position.x, position.y))
+        .collect();
+
+    // Every position in the window matches the one two back, and never the one before
+    let tail = &positions[positions.len().saturating_sub(OSCILLATION_WINDOW)..];
+    if tail.len() == OSCILLATION_WINDOW && tail.windows(3).all(|w| w[0] == w[2] && w[0] != w[1]) {
+        return Ok(Some(NonFinishReason::Oscillated));
+    }
+
+    let ticks = positions.len() - 1;
+    let still = positions.windows(2).filter(|w| w[0] == w[1]).count();
+    if ticks > 0 && still * 2 >= ticks {
+        return Ok(Some(NonFinishReason::StuckOnTile));
+    }
+
+    let span = |coord: fn(&(u32, u32)) -> u32| {
+        let max = positions.iter().map(coord).max().unwrap_or(0);
+        let min = positions.iter().map(coord).min().unwrap_or(0);
+        max - min + 1
+    };
+    if span(|position| position.0) <= BOXED_IN_SPAN && span(|position| position.1) <= BOXED_IN_SPAN {
+        return Ok(Some(NonFinishReason::BoxedIn));
+    }
+
+    Ok(Some(NonFinishReason::RanOutOfTicks))
+}
+
+/// Find a race in the recent races without knowing its track
+/// - Races stay stored while any car or track list still holds them
+pub fn query_race_result_by_id(
+    deps: Deps,
+    race_id: String,
+) -> Result<RaceResultResponse, ContractError> {
+    for item in RECENT_RACE_RESULTS.range(deps.storage, None, None, cosmwasm_std::Order::Ascending) {
+        let (_, stored) = item?;
+        if stored.result.race_id == race_id {
+            return Ok(RaceResultResponse { result: stored.result });
+        }
+    }
+    Err(ContractError::RaceNotFound { race_id })
+}
+
+/// Inputs of a recent SimulateRace, None for the other kinds of race
+fn load_race_inputs(storage: &dyn Storage, race_id: &str) -> Result<Option<(RaceInputs, Option<RaceReplayInputs>)>, ContractError> {
+    for item in RECENT_RACE_RESULTS.range(storage, None, None, cosmwasm_std::Order::Ascending) {
+        let (_, stored) = item?;
+        if stored.result.race_id == race_id {
+            return Ok(stored.inputs.map(|inputs| (inputs, stored.replay)));
+        }
+    }
+    Ok(None)
+}
+
+pub fn query_race_inputs(deps: Deps, race_id: String) -> Result<RaceInputs, ContractError> {
+    load_race_inputs(deps.storage, &race_id)?
+        .map(|(inputs, _)| inputs)
+        .ok_or(ContractError::RaceNotFound { race_id })
+}
+
 pub fn query_recent_races(
     deps: Deps,
     car_id: Option<u128>,
@@ -1235,10 +2812,168 @@ pub fn query_recent_races(
         rankings: r.rankings.clone(),
         play_by_play: r.play_by_play.clone(),
         steps_taken: r.steps_taken.clone(),
+        eliminations: r.eliminations.clone(),
+        teams: r.teams.clone(),
+        team_results: r.team_results.clone(),
     }).collect();
     Ok(RecentRacesResponse { races: msg_races })
 }
 
+pub fn query_car_wins(
+    deps: Deps,
+    car_id: u128,
+    track_id: Option<u128>,
+    limit: Option<u32>,
+) -> Result<CarWinsResponse, ContractError> {
+    let limit = limit.unwrap_or(MAX_LIMIT).min(MAX_LIMIT) as usize;
+    // Only the car's recent races are kept, so older wins aren't reported
+    let races = get_recent_races(deps.storage, Some(car_id), None).unwrap_or_default();
+
+    let wins = races.iter().rev()
+        .filter(|race| race.winner_ids.contains(&car_id))
+        .filter(|race| track_id.map_or(true, |track_id| race.track_id == Uint128::from(track_id)))
+        .take(limit)
+        .map(|race| CarWin {
+            race_id: race.race_id.clone(),
+            track_id: race.track_id,
+            steps_taken: race.steps_taken.iter()
+                .find(|step| step.car_id == car_id)
+                .map(|step| step.steps_taken)
+                .unwrap_or(0),
+        })
+        .collect();
+
+    Ok(CarWinsResponse { car_id, wins })
+}
+
+pub fn query_export_q_table(deps: Deps, car_id: u128, track_id: Option<u128>) -> Result<QTableExport, ContractError> {
+    // Same full-table walk GetQ pages through, for off-chain use
+    let config = CONFIG.load(deps.storage)?;
+    let prefix = match track_id {
+        Some(track_id) => TRACK_Q_TABLE.prefix((car_id, track_id)),
+        None => Q_TABLE.prefix(car_id),
+    };
+    let entries = prefix
+        .range(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .map(|item| item.map(|(key, action_values)| QTableEntry {
+            state_hash: state_hash_from_key(&key),
+            action_values,
+        }))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(QTableExport {
+        car_id,
+        version: Q_TABLE_EXPORT_VERSION,
+        state_encoding: config.state_encoding,
+        entries,
+    })
+}
+
+pub fn query_export_policy(
+    deps: Deps,
+    car_id: u128,
+    track_id: Option<u128>,
+    start_after: Option<[u8; 32]>,
+    limit: Option<u32>,
+) -> Result<Vec<PolicyEntry>, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let limit = limit.unwrap_or(MAX_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after.as_ref()
+        .map(|hash| Bound::exclusive(state_key(hash, &config.state_encoding)));
+
+    let prefix = match track_id {
+        Some(track_id) => TRACK_Q_TABLE.prefix((car_id, track_id)),
+        None => Q_TABLE.prefix(car_id),
+    };
+    let policy = prefix
+        .range(deps.storage, start_after, None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(key, action_values)| PolicyEntry {
+            state_hash: state_hash_from_key(&key),
+            best_action: greedy_action(&action_values),
+        }))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(policy)
+}
+
+pub fn query_verify_replay(deps: Deps, race_id: String, expected_hash: [u8; 32]) -> Result<VerifyReplayResponse, ContractError> {
+    let (inputs, replay) = match load_race_inputs(deps.storage, &race_id)? {
+        Some((inputs, Some(replay))) => (inputs, replay),
+        _ => return Err(ContractError::RaceNotFound { race_id }),
+    };
+
+    // Re-run against the recorded Q-values in scratch storage, queries can't write
+    let mut storage = cosmwasm_std::MemoryStorage::new();
+    for (car_id, entries) in &replay.q_tables {
+        for entry in entries {
+            set_q_values(&mut storage, *car_id, None, &entry.state_hash, &replay.state_encoding, entry.action_values)?;
+        }
+    }
+    let mut race_state = init_race_state(replay.track_layout, &inputs.car_ids, replay.state_encoding);
+    race_state.distinguish_edges = replay.distinguish_edges;
+    race_state.shuffle_order = inputs.shuffle_order;
+    for car in race_state.cars.iter_mut() {
+        if let Some((_, failed_races)) = inputs.failed_races.iter().find(|(car_id, _)| *car_id == car.car_id) {
+            car.failed_races = *failed_races;
+        }
+    }
+    let race_result = simulate_race(&mut storage, &mut race_state, inputs.training_config, inputs.max_ticks, None, inputs.end_on_first_finish, &inputs.ranking_mode, None)?;
+
+    let replay_hash = play_by_play_hash(&race_result.play_by_play)?;
+    Ok(VerifyReplayResponse {
+        race_id,
+        valid: replay_hash == expected_hash,
+        replay_hash,
+    })
+}
+
+pub fn query_car_tracks(deps: Deps, car_id: u128) -> Result<Vec<u128>, ContractError> {
+    // Keys only, the stats themselves aren't deserialized
+    let track_ids = CAR_TRACK_TRAINING_STATS.prefix(car_id)
+        .keys(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .collect::<StdResult<Vec<u128>>>()?;
+    Ok(track_ids)
+}
+
+pub fn query_car_stats_summary(deps: Deps, car_id: u128) -> Result<CarStatsSummaryResponse, ContractError> {
+    let mut solo_races = 0u32;
+    let mut pvp_races = 0u32;
+    let mut wins = 0u32;
+    let mut fastest = u32::MAX;
+
+    for item in CAR_TRACK_TRAINING_STATS.prefix(car_id).range(deps.storage, None, None, cosmwasm_std::Order::Ascending) {
+        let (_, stats) = item?;
+        for (mode, races) in [(&stats.solo, &mut solo_races), (&stats.pvp, &mut pvp_races)] {
+            *races += mode.tally;
+            wins += mode.wins;
+            // u32::MAX means no race in this mode on this track
+            fastest = fastest.min(mode.fastest);
+        }
+    }
+
+    let total = solo_races + pvp_races;
+    Ok(CarStatsSummaryResponse {
+        car_id,
+        solo_races,
+        pvp_races,
+        win_rate: if total == 0 { 0 } else { wins * 100 / total },
+        fastest: (fastest != u32::MAX).then_some(fastest),
+    })
+}
+
+/// Single-track stats for each car, capped at MAX_LIMIT cars
+pub fn query_multi_car_stats(
+    deps: Deps,
+    car_ids: Vec<u128>,
+    track_id: u128,
+) -> Result<Vec<GetTrackTrainingStatsResponse>, ContractError> {
+    let mut stats = vec![];
+    for car_id in car_ids.into_iter().take(MAX_LIMIT as usize) {
+        stats.extend(query_track_training_stats(deps, car_id, Some(track_id), None, None)?);
+    }
+    Ok(stats)
+}
+
 pub fn query_track_training_stats(
     deps: Deps,
     car_id: u128,
@@ -1253,11 +2988,13 @@ pub fn query_track_training_stats(
                 .unwrap_or_else(|_| racing::types::TrackTrainingStats {
                     solo: racing::types::TrainingStats {
                         tally: 0,
+                        wins: 0,
                         win_rate: 0,
                         fastest: u32::MAX,
                     },
                     pvp: racing::types::TrainingStats {
                         tally: 0,
+                        wins: 0,
                         win_rate: 0,
                         fastest: u32::MAX,
                     },
@@ -1307,7 +3044,7 @@ pub fn query_track_training_stats(
 // - test that it doesn't get stuck 
 // 
 /// Apply Q-learning updates directly to car contracts based on race results and car actions
-fn apply_q_learning_updates(
+pub(crate) fn apply_q_learning_updates(
     storage: &mut dyn Storage,
     race_state: &RaceState,
     race_result: &RaceResult,
@@ -1315,18 +3052,26 @@ fn apply_q_learning_updates(
     config: Config,
     querier: QuerierWrapper,
     fastest_track_tick_time: u64,
+    lambda: f32,
+    reward_time_decay: Option<f32>,
 ) -> Result<(), ContractError> {
     
+    // Steps the runner-up took, unfinished runners-up count as racing the whole race
+    let runner_up_steps = race_result.rankings.get(1)
+        .and_then(|rank| race_state.cars.iter().find(|car| car.car_id == rank.car_id))
+        .map(|car| if car.finished { car.steps_taken } else { race_state.tick });
+
     // Collect all Q-updates for each car
-    let mut car_updates: std::collections::HashMap<u128, Vec<( [u8; 32], u8, i32, Option< [u8; 32]>)>> = std::collections::HashMap::new();
+    let mut car_updates: std::collections::HashMap<u128, Vec<QUpdateTuple>> = std::collections::HashMap::new();
     
     for car in &race_state.cars {
         let mut updates = vec![];
+        let mut breakdown = RewardBreakdown::default();
         
         // Process each action in the car's history
         for (i, (state_hash, action, tile)) in car.action_history.iter().enumerate() {
             // Calculate reward for this specific action
-            let action_reward = calculate_action_reward(
+            let action_breakdown = calculate_reward_breakdown(
                 car,
                 race_result,
                 *action,
@@ -1339,7 +3084,10 @@ fn apply_q_learning_updates(
                 car.action_history.len(),
                 reward_config.clone(),
                 fastest_track_tick_time,
+                runner_up_steps,
             )?;
+            breakdown.add(&action_breakdown);
+            let action_reward = decay_reward(clamp_reward(action_breakdown.total(), &reward_config), reward_time_decay, i);
             
             // Determine next state hash (if not the last action)
             let next_state_hash = if i < car.action_history.len() - 1 {
@@ -1349,16 +3097,22 @@ fn apply_q_learning_updates(
             };
             
             // Collect update: (state_hash, action, reward, next_state_hash)
-            updates.push((state_hash.clone(), *action as u8, action_reward, next_state_hash));
+            // - Skipping what online updates already applied mid-race
+            if i >= car.q_updates_applied {
+                updates.push((state_hash.clone(), *action as u8, action_reward, next_state_hash));
+            }
         }
         
         car_updates.insert(car.car_id.clone(), updates);
+        LAST_REWARD_BREAKDOWN.save(storage, car.car_id, &breakdown)?;
     }
     
     // Apply batched updates to each car's model in storage
+    let batch_size = config.q_update_batch_size.unwrap_or(DEFAULT_Q_UPDATE_BATCH_SIZE).max(1) as usize;
     for car in &race_state.cars {
         if let Some(updates) = car_updates.get(&car.car_id) {
-            apply_batched_q_updates(storage, car, updates.clone(), config.clone(), querier.clone())?;
+            let chunks = updates.chunks(batch_size).map(|chunk| chunk.to_vec());
+            apply_batched_q_updates(storage, car, race_state.q_track, chunks, config.clone(), querier.clone(), lambda)?;
         }
     }
     
@@ -1366,7 +3120,7 @@ fn apply_q_learning_updates(
 }
 
 /// Calculate reward for a specific action
-fn calculate_action_reward(
+pub fn calculate_action_reward(
     car: &CarState,
     race_result: &RaceResult,
     action: usize,
@@ -1376,14 +3130,68 @@ fn calculate_action_reward(
     total_actions: usize,
     reward_config: RewardNumbers,
     fastest_track_tick_time: u64,
+    runner_up_steps: Option<u32>,
+    reward_time_decay: Option<f32>,
 ) -> Result<i32, ContractError> {
+    let reward = calculate_reward_breakdown(
+        car,
+        race_result,
+        action,
+        last_tile,
+        tile,
+        action_index,
+        total_actions,
+        reward_config.clone(),
+        fastest_track_tick_time,
+        runner_up_steps,
+    )?.total();
+    let reward = decay_reward(clamp_reward(reward, &reward_config), reward_time_decay, action_index);
+    println!("Reward: {}", reward);
+    Ok(reward)
+}
+
+/// Keep a summed reward within the configured reward_min/reward_max
+fn clamp_reward(reward: i32, reward_config: &RewardNumbers) -> i32 {
+    let reward = reward_config.reward_min.map_or(reward, |reward_min| reward.max(reward_min));
+    reward_config.reward_max.map_or(reward, |reward_max| reward.min(reward_max))
+}
+
+/// Scale a step's reward by decay^action_index, the action index being the tick it was taken on
+fn decay_reward(reward: i32, reward_time_decay: Option<f32>, action_index: usize) -> i32 {
+    match reward_time_decay {
+        Some(decay) => (reward as f32 * decay.powi(action_index as i32)) as i32,
+        None => reward,
+    }
+}
+
+/// Reward for a specific action, split into its components
+pub(crate) fn calculate_reward_breakdown(
+    car: &CarState,
+    race_result: &RaceResult,
+    _action: usize,
+    last_tile: racing::types::TrackTile,
+    tile: racing::types::TrackTile,
+    action_index: usize,
+    total_actions: usize,
+    reward_config: RewardNumbers,
+    fastest_track_tick_time: u64,
+    runner_up_steps: Option<u32>,
+) -> Result<RewardBreakdown, ContractError> {
 
     let mut rank = 0;
-    let mut reward = 0i32;
+    let mut breakdown = RewardBreakdown::default();
     // Check if car finished
     if car.finished {
         // Check if car is a winner
-        if race_result.winner_ids.contains(&car.car_id) {
+        // - In team races the car takes its team's rank
+        let team_rank = race_result.teams.as_ref()
+            .and_then(|teams| teams.iter().position(|team| team.contains(&car.car_id)))
+            .and_then(|team_id| race_result.team_results.as_ref()?
+                .iter()
+                .position(|(id, _)| *id as usize == team_id));
+        if let Some(team_rank) = team_rank {
+            rank = team_rank as u8;
+        } else if race_result.winner_ids.contains(&car.car_id) {
             rank = 0;
         } else {
             // Find car's ranking
@@ -1395,40 +3203,103 @@ fn calculate_action_reward(
         }
 
         //Add rank reward
-        reward += match rank {
-            0 => reward_config.rank.first,
-            1 => reward_config.rank.second,
-            2 => reward_config.rank.third,
-            _ => reward_config.rank.other,
-        };
+        breakdown.rank = reward_config.rank.ranks.get(rank as usize)
+            .copied()
+            .unwrap_or(reward_config.rank.other);
+
+        // Reward winning by a wide margin over the runner-up
+        if rank == 0 {
+            if let Some(runner_up_steps) = runner_up_steps {
+                let margin = runner_up_steps.saturating_sub(car.steps_taken) as i32;
+                let margin_reward = reward_config.rank.margin_bonus.unwrap_or(0).saturating_mul(margin);
+                breakdown.rank += margin_reward.min(MAX_MARGIN_REWARD);
+            }
+        }
 
         //Add reward for speed
-        let r_ticks = 100.0 * (fastest_track_tick_time as f32) / (total_actions as f32);
-        reward += r_ticks as i32;
+        // - Very short (or empty) histories would blow up the ratio, so clamp the divisor and cap the result
+        let r_ticks = 100.0 * (fastest_track_tick_time as f32) / (total_actions.max(1) as f32);
+        let max_speed_reward = reward_config.max_speed_reward.unwrap_or(MAX_SPEED_REWARD);
+        breakdown.speed = (r_ticks as i32).min(max_speed_reward);
+    }
+
+    // No-progress penalties, at most one applies per action
+    match car.outcomes.get(action_index) {
+        Some(ActionOutcome::HitWall) => breakdown.wall = reward_config.wall,
+        Some(ActionOutcome::Stuck) => breakdown.stuck = reward_config.stuck,
+        Some(ActionOutcome::NoMove) => breakdown.no_move = reward_config.no_move,
+        Some(ActionOutcome::BlockedByCar) => breakdown.collision = reward_config.collision_penalty.unwrap_or(reward_config.no_move),
+        Some(ActionOutcome::Moved) | None => {},
+    };
+
+    if car.drafting.get(action_index).copied().unwrap_or(false) {
+        breakdown.draft = reward_config.draft_bonus.unwrap_or(0);
+    }
+
+    if car.first_to_checkpoint.get(action_index).copied().unwrap_or(false) {
+        breakdown.checkpoint = reward_config.checkpoint_bonus.unwrap_or(0);
+    }
+
+    if car.evaded.get(action_index).copied().unwrap_or(false) {
+        breakdown.evasion = reward_config.evasion_bonus.unwrap_or(0);
+    }
+
+    if car.new_best.get(action_index).copied().unwrap_or(false) {
+        breakdown.new_best = reward_config.new_best_bonus.unwrap_or(0);
+    }
+
+    // Comeback: last at the halfway tick, better placed at the end
+    // - Credited once, on the final action
+    if action_index + 1 == total_actions {
+        let last_rank = race_result.rankings.len().saturating_sub(1) as u32;
+        let final_rank = race_result.rankings.iter()
+            .find(|rank| rank.car_id == car.car_id)
+            .map(|rank| rank.rank);
+        if let (Some(midpoint_rank), Some(final_rank)) = (car.midpoint_rank, final_rank) {
+            if midpoint_rank == last_rank && final_rank < midpoint_rank {
+                breakdown.comeback = reward_config.comeback_bonus.unwrap_or(0) * (midpoint_rank - final_rank) as i32;
+            }
+        }
+    }
+
+    // Path efficiency: the start to finish progress span over the cells driven to cover it
+    // - Credited once, on the final action, and capped at the full bonus when a teleporter shortened the trip
+    if car.finished && action_index + 1 == total_actions && car.cells_traveled > 0 {
+        let span = car.tile.progress_towards_finish.abs_diff(car.start_progress) as i64;
+        let bonus = reward_config.path_efficiency_bonus.unwrap_or(0) as i64;
+        breakdown.path_efficiency = (bonus * span.min(car.cells_traveled as i64) / car.cells_traveled as i64) as i32;
+    }
+
+    // Frustration: still not finishing a track the car has never won
+    if action_index + 1 == total_actions && !car.finished && car.failed_races > 0 {
+        breakdown.frustration = reward_config.frustration_penalty.unwrap_or(0);
     }
 
-    // **NEW**: Use hit_wall field instead of checking tile type
-    if car.hit_wall {
-        reward += reward_config.wall;
+    // Tile variety: every kind of tile driven onto, finished or not
+    if action_index + 1 == total_actions {
+        breakdown.tile_variety = reward_config.tile_variety_bonus.unwrap_or(0) * car.tile_kinds.len() as i32;
     }
 
-    // Base Tile penalties (excluding wall since we handle it above)
-    if tile.properties.skip_next_turn {
-        reward += reward_config.stuck;
+    // Overtime: every step past the target costs, finished or not
+    if action_index + 1 == total_actions {
+        if let Some(target_ticks) = reward_config.target_ticks {
+            let overtime = car.steps_taken.saturating_sub(target_ticks) as i32;
+            breakdown.overtime = reward_config.overtime_penalty_per_tick.unwrap_or(0).saturating_mul(overtime);
+        }
     }
 
     // Movement reward
 
     let delta = tile.progress_towards_finish as i32 - last_tile.progress_towards_finish as i32;
     // println!("Delta: {}", delta);
-    if delta == 0 {
-        reward += reward_config.no_move;
+    if delta < 0 {
+        // Retreating can cost more than advancing earns, to discourage dithering
+        breakdown.distance += reward_config.distance * delta * reward_config.backward_penalty_multiplier.unwrap_or(1);
     } else {
-        reward += reward_config.distance * delta;
-    } 
+        breakdown.distance += reward_config.distance * delta;
+    }
     if delta > 0 {
-        reward += reward_config.distance * tile.progress_towards_finish as i32;
+        breakdown.distance += reward_config.distance * tile.progress_towards_finish as i32;
     }
-    println!("Reward: {}", reward);
-    Ok(reward)
+    Ok(breakdown)
 }
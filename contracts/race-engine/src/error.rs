@@ -9,6 +9,9 @@ pub enum ContractError {
     #[error("Invalid car count: expected {expected}, got {actual}")]
     InvalidCarCount { expected: u32, actual: u32 },
 
+    #[error("Car {car_id} is entered more than once")]
+    DuplicateCar { car_id: u128 },
+
     #[error("Invalid action: {action}")]
     InvalidAction { action: usize },
 
@@ -21,6 +24,18 @@ pub enum ContractError {
     #[error("Race not found: {race_id}")]
     RaceNotFound { race_id: String },
 
+    #[error("No ghost for car {car_id} on track {track_id}")]
+    GhostNotFound { car_id: u128, track_id: u128 },
+
+    #[error("Invalid max ticks: {max_ticks}, must be between 1 and {limit}")]
+    InvalidMaxTicks { max_ticks: u32, limit: u32 },
+
+    #[error("Invalid track: {reason}")]
+    InvalidTrack { reason: String },
+
+    #[error("Unsupported Q-table export: version {version} with {state_encoding} encoding, expected version {expected_version} with {expected_encoding}")]
+    UnsupportedQTableExport { version: u32, state_encoding: String, expected_version: u32, expected_encoding: String },
+
     #[error("Invalid race configuration")]
     InvalidRaceConfig,
 
@@ -2,36 +2,127 @@ use cosmwasm_std::{StdError, StdResult, Storage};
 use cw_storage_plus::{Item, Map};
 use serde::{Deserialize, Serialize};
 
-use racing::race_engine::{Config, RaceResult};
-use racing::types::{TrackTrainingStats, TrainingStats};
+use racing::race_engine::{Config, Ghost, LastRaceSummary, RaceInputs, RaceResult, StateEncoding};
+use racing::types::{QTableEntry, RewardBreakdown, Track, TrackTile, TrackTrainingStats, TrainingStats};
 
 pub const CONFIG: Item<Config> = Item::new("config");
-pub const CAR_RECENT_RACES: Map<u128, Vec<RaceResult>> = Map::new("car_recent_races");
-pub const TRACK_RECENT_RACES: Map<u128, Vec<RaceResult>> = Map::new("track_recent_races");
+// Each race result is stored once, the per-car and per-track lists hold keys into RECENT_RACE_RESULTS
+// - Keyed by a counter, which also ends the race_id so races on a track in the same block don't share one
+pub const RECENT_RACE_RESULTS: Map<u64, StoredRace> = Map::new("recent_race_results");
+pub const NEXT_RACE_KEY: Item<u64> = Item::new("next_race_key");
+pub const CAR_RECENT_RACES: Map<u128, Vec<u64>> = Map::new("car_recent_race_keys");
+pub const TRACK_RECENT_RACES: Map<u128, Vec<u64>> = Map::new("track_recent_race_keys");
+// Tracks fetched from the track manager, until invalidated by the admin
+pub const TRACK_CACHE: Map<u128, Track> = Map::new("track_cache");
+// Reward components summed over each car's last training race
+pub const LAST_REWARD_BREAKDOWN: Map<u128, RewardBreakdown> = Map::new("last_reward_breakdown");
+// Engine-wide SimulateRace counters
+pub const TOTAL_RACES: Item<u64> = Item::new("total_races");
+pub const TOTAL_TICKS: Item<u64> = Item::new("total_ticks");
+// Kept up to date as cars first race and Q entries come and go, GetEngineStats doesn't scan for them
+pub const TOTAL_CARS: Item<u64> = Item::new("total_cars");
+pub const TOTAL_Q_ENTRIES: Item<u64> = Item::new("total_q_entries");
+// Each car's result in the last SimulateRace it took part in
+pub const LAST_RACE_SUMMARY: Map<u128, LastRaceSummary> = Map::new("last_race_summary");
+// Fastest finished run per (car_id, track_id), raced against by SimulateGhostRace
+pub const GHOSTS: Map<(u128, u128), Ghost> = Map::new("ghosts");
+// Highest progress_towards_finish reached per (car_id, track_id) in training races
+pub const BEST_PROGRESS: Map<(u128, u128), u16> = Map::new("best_progress");
+
+/// A race result with the number of recent race lists still holding it, removed when that reaches 0
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StoredRace {
+    pub result: RaceResult,
+    pub refs: u32,
+    // What the race was run with, None for races that weren't a SimulateRace
+    #[serde(default)]
+    pub inputs: Option<RaceInputs>,
+    // Snapshot to re-run the race with, only for races run with record_replay
+    #[serde(default)]
+    pub replay: Option<RaceReplayInputs>,
+}
+
+/// What a race's RaceInputs don't hold but re-running it needs, the track and engine config can change after it
+/// - q_tables holds the values each car read during the race, later training doesn't change a replay
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RaceReplayInputs {
+    pub track_layout: Vec<Vec<TrackTile>>,
+    pub state_encoding: StateEncoding,
+    #[serde(default)]
+    pub distinguish_edges: bool,
+    pub q_tables: Vec<(u128, Vec<QTableEntry>)>,
+}
 
 // Constants
 pub const MAX_CAR_RECENT_RACES: usize = 9;
 pub const MAX_TRACK_RECENT_RACES: usize = 32;
 pub const MAX_TICKS: u32 = 100;
+// Hard cap on per-race max_ticks to bound gas usage
+pub const MAX_TICKS_LIMIT: u32 = 500;
 
 
-// Q-table storage: (car_id, state_hash) -> [i32; 4] action values
-pub const Q_TABLE: Map<(u128, &[u8; 32]), [i32; 4]> = Map::new("q_table");
+// Q-table storage: (car_id, state key) -> [i32; 4] action values
+// - The state key is the full 32-byte state hash, or its first 4 bytes with StateEncoding::Packed
+pub const Q_TABLE: Map<(u128, &[u8]), [i32; 4]> = Map::new("q_table");
+// Per-track Q-tables, used instead of Q_TABLE when Config.per_track_q is set: (car_id, track_id, state key) -> [i32; 4]
+pub const TRACK_Q_TABLE: Map<(u128, u128, &[u8]), [i32; 4]> = Map::new("track_q_table");
+pub const PACKED_STATE_KEY_BYTES: usize = 4;
 
 // Training stats storage: (car_id, track_id) -> TrackTrainingStats
 pub const CAR_TRACK_TRAINING_STATS: Map<(u128, u128), TrackTrainingStats> = Map::new("car_track_training_stats");
 
-pub fn get_q_values(storage: &dyn Storage, car_id: u128, state_hash: & [u8; 32]) -> StdResult<[i32; 4]> {
-    Q_TABLE.load(storage, (car_id, state_hash))
+/// Q_TABLE key for a state hash
+pub fn state_key<'a>(state_hash: &'a [u8; 32], encoding: &StateEncoding) -> &'a [u8] {
+    match encoding {
+        StateEncoding::Hashed => state_hash,
+        StateEncoding::Packed => &state_hash[..PACKED_STATE_KEY_BYTES],
+    }
+}
+
+/// State hash for a Q_TABLE key, packed keys are zero-padded
+pub fn state_hash_from_key(key: &[u8]) -> [u8; 32] {
+    let len = key.len().min(32);
+    let mut state_hash = [0u8; 32];
+    state_hash[..len].copy_from_slice(&key[..len]);
+    state_hash
+}
+
+/// Q-values for a state, from the car's table for `track_id` or its shared table when None
+pub fn get_q_values(storage: &dyn Storage, car_id: u128, track_id: Option<u128>, state_hash: & [u8; 32], encoding: &StateEncoding) -> StdResult<[i32; 4]> {
+    match track_id {
+        Some(track_id) => TRACK_Q_TABLE.load(storage, (car_id, track_id, state_key(state_hash, encoding))),
+        None => Q_TABLE.load(storage, (car_id, state_key(state_hash, encoding))),
+    }
 }
 
 pub fn set_q_values(
     storage: &mut dyn Storage,
     car_id: u128,
+    track_id: Option<u128>,
     state_hash: &[u8; 32],
+    encoding: &StateEncoding,
     q_values: [i32; 4],
 ) -> StdResult<()> {
-    Q_TABLE.save(storage, (car_id, state_hash), &q_values)
+    let key = state_key(state_hash, encoding);
+    let new_entry = match track_id {
+        Some(track_id) => !TRACK_Q_TABLE.has(storage, (car_id, track_id, key)),
+        None => !Q_TABLE.has(storage, (car_id, key)),
+    };
+    match track_id {
+        Some(track_id) => TRACK_Q_TABLE.save(storage, (car_id, track_id, key), &q_values)?,
+        None => Q_TABLE.save(storage, (car_id, key), &q_values)?,
+    }
+    if new_entry {
+        let total = TOTAL_Q_ENTRIES.may_load(storage)?.unwrap_or_default();
+        TOTAL_Q_ENTRIES.save(storage, &(total + 1))?;
+    }
+    Ok(())
+}
+
+/// Take Q entries removed outside set_q_values off TOTAL_Q_ENTRIES
+pub fn release_q_entries(storage: &mut dyn Storage, count: u64) -> StdResult<()> {
+    let total = TOTAL_Q_ENTRIES.may_load(storage)?.unwrap_or_default();
+    TOTAL_Q_ENTRIES.save(storage, &total.saturating_sub(count))
 }
 
 
@@ -44,50 +135,75 @@ pub fn set_config(storage: &mut dyn cosmwasm_std::Storage, config: Config) -> St
 }
 
 pub fn get_recent_races(storage: &dyn cosmwasm_std::Storage, car_id: Option<u128>, track_id: Option<u128>) -> StdResult<Vec<RaceResult>> {
-    if let Some(car_id) = car_id {
-        CAR_RECENT_RACES.load(storage, car_id)
+    let keys = if let Some(car_id) = car_id {
+        CAR_RECENT_RACES.load(storage, car_id)?
     } else if let Some(track_id) = track_id {
-        TRACK_RECENT_RACES.load(storage, track_id)
+        TRACK_RECENT_RACES.load(storage, track_id)?
     } else {
         return Err(StdError::generic_err("No car or track ID provided"));
+    };
+
+    keys.into_iter()
+        .map(|key| RECENT_RACE_RESULTS.load(storage, key).map(|stored| stored.result))
+        .collect()
+}
+
+/// Reserve the key the next race is stored under
+pub fn next_race_key(storage: &mut dyn Storage) -> StdResult<u64> {
+    let key = NEXT_RACE_KEY.may_load(storage)?.unwrap_or_default();
+    NEXT_RACE_KEY.save(storage, &(key + 1))?;
+    Ok(key)
+}
+
+/// Store a race once under its key and add it to the recent races of its track and each of its cars
+pub fn add_recent_race(
+    storage: &mut dyn cosmwasm_std::Storage,
+    key: u64,
+    race_result: &RaceResult,
+    inputs: Option<RaceInputs>,
+    replay: Option<RaceReplayInputs>,
+) -> StdResult<()> {
+    RECENT_RACE_RESULTS.save(storage, key, &StoredRace {
+        result: race_result.clone(),
+        refs: race_result.car_ids.len() as u32 + 1,
+        inputs,
+        replay,
+    })?;
+
+    push_recent_race(storage, &TRACK_RECENT_RACES, race_result.track_id.u128(), key, MAX_TRACK_RECENT_RACES)?;
+    // A car's recent race list is never removed, its first race is the first time it's seen
+    // - Ghost races need a ghost from an earlier race, so every car that raced comes through here
+    let new_cars = race_result.car_ids.iter().filter(|car_id| !CAR_RECENT_RACES.has(storage, **car_id)).count() as u64;
+    if new_cars > 0 {
+        let total = TOTAL_CARS.may_load(storage)?.unwrap_or_default();
+        TOTAL_CARS.save(storage, &(total + new_cars))?;
+    }
+    for car_id in &race_result.car_ids {
+        push_recent_race(storage, &CAR_RECENT_RACES, *car_id, key, MAX_CAR_RECENT_RACES)?;
     }
+
+    Ok(())
 }
 
-pub fn add_recent_race(storage: &mut dyn cosmwasm_std::Storage, race_result: RaceResult, car_id: Option<u128>, track_id: Option<u128>) -> StdResult<()> {
-    let mut races = if let Some(car_id) = car_id.clone() {
-        CAR_RECENT_RACES.load(storage, car_id).unwrap_or_default()
-    } else if let Some(track_id) = track_id.clone() {
-        TRACK_RECENT_RACES.load(storage, track_id).unwrap_or_default()
-    } else {
-        return Err(StdError::generic_err("No car or track ID provided"));
-    };
-    
-    races.push(race_result);
+/// Append a race key to a recent race list, releasing the keys that fall off the front
+fn push_recent_race(storage: &mut dyn Storage, recent_races: &Map<u128, Vec<u64>>, id: u128, key: u64, max: usize) -> StdResult<()> {
+    let mut keys = recent_races.may_load(storage, id)?.unwrap_or_default();
+    keys.push(key);
 
-    //Set max length
-    let max: usize = if let Some(_) = car_id {
-        MAX_CAR_RECENT_RACES
-    } else if let Some(_) = track_id {
-        MAX_TRACK_RECENT_RACES
-    } else {
-        return Err(StdError::generic_err("No car or track ID provided"));
-    };
-    
-    
     // Keep only the most recent races
-    if races.len() > max {
-        races = races.into_iter().rev().take(max).collect();
-        races.reverse();
-    }
-    
-    if let Some(car_id) = car_id {
-        CAR_RECENT_RACES.save(storage, car_id, &races)?;
-    } else if let Some(track_id) = track_id {
-        TRACK_RECENT_RACES.save(storage, track_id, &races)?;
-    } else {
-        return Err(StdError::generic_err("No car or track ID provided"));
+    let evicted: Vec<u64> = keys.drain(..keys.len().saturating_sub(max)).collect();
+    recent_races.save(storage, id, &keys)?;
+
+    for key in evicted {
+        let mut stored = RECENT_RACE_RESULTS.load(storage, key)?;
+        stored.refs = stored.refs.saturating_sub(1);
+        if stored.refs == 0 {
+            RECENT_RACE_RESULTS.remove(storage, key);
+        } else {
+            RECENT_RACE_RESULTS.save(storage, key, &stored)?;
+        }
     }
-    
+
     Ok(())
 }
 
@@ -105,39 +221,61 @@ pub fn set_track_training_stats(
     CAR_TRACK_TRAINING_STATS.save(storage, (car_id, track_id), &stats)
 }
 
+// Count a run towards one mode's stats
+fn record_run(stats: &mut TrainingStats, won: bool, finished: bool, completion_time: u32) {
+    // Rows saved before wins was tracked only have the rounded rate
+    if stats.wins == 0 && stats.win_rate > 0 {
+        stats.wins = stats.win_rate * stats.tally / 100;
+    }
+
+    stats.tally += 1;
+    if won {
+        stats.wins += 1;
+    }
+    stats.win_rate = stats.wins * 100 / stats.tally;
+
+    // Only a finished run has a completion time
+    if finished && completion_time < stats.fastest {
+        stats.fastest = completion_time;
+    }
+}
+
+/// Races a car has run on a track without ever winning there, 0 once it has won
+pub fn get_failed_races(storage: &dyn Storage, car_id: u128, track_id: u128) -> StdResult<u32> {
+    let Some(stats) = CAR_TRACK_TRAINING_STATS.may_load(storage, (car_id, track_id))? else {
+        return Ok(0);
+    };
+    // Rows saved before wins was tracked only have the rounded rate
+    let won = stats.solo.wins + stats.pvp.wins + stats.solo.win_rate + stats.pvp.win_rate > 0;
+    Ok(if won { 0 } else { stats.solo.tally + stats.pvp.tally })
+}
+
 pub fn update_solo_training_stats(
     storage: &mut dyn Storage,
     car_id: u128,
     track_id: u128,
     won: bool,
+    finished: bool,
     completion_time: u32,
 ) -> StdResult<TrackTrainingStats> {
     let mut stats = CAR_TRACK_TRAINING_STATS.load(storage, (car_id, track_id))
         .unwrap_or_else(|_| TrackTrainingStats {
             solo: TrainingStats {
                 tally: 0,
+                wins: 0,
                 win_rate: 0,
                 fastest: u32::MAX,
             },
             pvp: TrainingStats {
                 tally: 0,
+                wins: 0,
                 win_rate: 0,
                 fastest: u32::MAX,
             },
         });
     
     // Update solo stats
-    stats.solo.tally += 1;
-    
-    // Calculate new win rate
-    let total_wins = (stats.solo.win_rate * (stats.solo.tally - 1)) / 100;
-    let new_wins = if won { total_wins + 1 } else { total_wins };
-    stats.solo.win_rate = (new_wins * 100) / stats.solo.tally;
-    
-    // Update fastest time if this run was faster
-    if completion_time < stats.solo.fastest {
-        stats.solo.fastest = completion_time;
-    }
+    record_run(&mut stats.solo, won, finished, completion_time);
     
     CAR_TRACK_TRAINING_STATS.save(storage, (car_id, track_id), &stats)?;
     Ok(stats)
@@ -148,34 +286,27 @@ pub fn update_pvp_training_stats(
     car_id: u128,
     track_id: u128,
     won: bool,
+    finished: bool,
     completion_time: u32,
 ) -> StdResult<TrackTrainingStats> {
     let mut stats = CAR_TRACK_TRAINING_STATS.load(storage, (car_id, track_id))
         .unwrap_or_else(|_| TrackTrainingStats {
             solo: TrainingStats {
                 tally: 0,
+                wins: 0,
                 win_rate: 0,
                 fastest: u32::MAX,
             },
             pvp: TrainingStats {
                 tally: 0,
+                wins: 0,
                 win_rate: 0,
                 fastest: u32::MAX,
             },
         });
     
     // Update PvP stats
-    stats.pvp.tally += 1;
-    
-    // Calculate new win rate
-    let total_wins = (stats.pvp.win_rate * (stats.pvp.tally - 1)) / 100;
-    let new_wins = if won { total_wins + 1 } else { total_wins };
-    stats.pvp.win_rate = (new_wins * 100) / stats.pvp.tally;
-    
-    // Update fastest time if this run was faster
-    if completion_time < stats.pvp.fastest {
-        stats.pvp.fastest = completion_time;
-    }
+    record_run(&mut stats.pvp, won, finished, completion_time);
     
     CAR_TRACK_TRAINING_STATS.save(storage, (car_id, track_id), &stats)?;
     Ok(stats)
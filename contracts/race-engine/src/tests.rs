@@ -2,7 +2,8 @@ use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
 use cosmwasm_std::{from_json, to_json_binary, Addr, Binary, OwnedDeps, Querier, QuerierResult, QueryRequest, SystemResult, ContractResult};
 
 use crate::contract::{execute, instantiate, query};
-use racing::race_engine::{ExecuteMsg, InstantiateMsg, QueryMsg, TrainingConfig, GetTrackTrainingStatsResponse};
+use crate::error::ContractError;
+use racing::race_engine::{ExecuteMsg, InstantiateMsg, QueryMsg, RankingMode, StateEncoding, TrainingConfig, GetTrackTrainingStatsResponse};
 use racing::types::{RewardNumbers, Track, TrackTile, TileProperties};
 
 const ADMIN: &str = "admin";
@@ -11,32 +12,25 @@ const TRACK_CONTRACT: &str = "track_contract";
 
 // Mock track for testing
 fn create_test_track() -> Track {
-    let mut layout = vec![vec![TrackTile {
-        properties: TileProperties::normal(),
-        progress_towards_finish: 0,
-        x: 0,
-        y: 0,
-    }; 5]; 5];
-    
-    // Set finish line at the top
-    for x in 0..5 {
-        layout[0][x] = TrackTile {
-            properties: TileProperties::finish(),
-            progress_towards_finish: 0,
-            x: x as u8,
-            y: 0,
-        };
-    }
-    
-    // Set start line at the bottom
-    for x in 0..5 {
-        layout[4][x] = TrackTile {
-            properties: TileProperties::start(),
-            progress_towards_finish: 4,
-            x: x as u8,
-            y: 4,
-        };
-    }
+    // Finish line at the top, start line at the bottom
+    // - Progress climbs one per row towards the finish, as the track manager lays it out
+    let layout = (0..5u8)
+        .map(|y| {
+            let properties = match y {
+                0 => TileProperties::finish(),
+                4 => TileProperties::start(),
+                _ => TileProperties::normal(),
+            };
+            (0..5u8)
+                .map(|x| TrackTile {
+                    properties: properties.clone(),
+                    progress_towards_finish: 4 - y as u16,
+                    x,
+                    y,
+                })
+                .collect()
+        })
+        .collect();
     
     Track {
         creator: "creator".to_string(),
@@ -50,6 +44,10 @@ fn create_test_track() -> Track {
 }
 
 fn setup_test_app() -> OwnedDeps<cosmwasm_std::MemoryStorage, cosmwasm_std::testing::MockApi, cosmwasm_std::testing::MockQuerier<cosmwasm_std::Empty>> {
+    setup_test_app_with_encoding(None)
+}
+
+fn setup_test_app_with_encoding(state_encoding: Option<StateEncoding>) -> OwnedDeps<cosmwasm_std::MemoryStorage, cosmwasm_std::testing::MockApi, cosmwasm_std::testing::MockQuerier<cosmwasm_std::Empty>> {
     let mut deps = mock_dependencies();
     let track = create_test_track();
     
@@ -73,6 +71,10 @@ fn setup_test_app() -> OwnedDeps<cosmwasm_std::MemoryStorage, cosmwasm_std::test
         admin: ADMIN.to_string(),
         track_contract: TRACK_CONTRACT.to_string(),
         car_contract: CAR_CONTRACT.to_string(),
+        state_encoding,
+        q_update_batch_size: None,
+        per_track_q: None,
+        distinguish_edges: None,
     };
     
     instantiate(deps.as_mut(), env.clone(), info.clone(), instantiate_msg).unwrap();
@@ -80,11 +82,40 @@ fn setup_test_app() -> OwnedDeps<cosmwasm_std::MemoryStorage, cosmwasm_std::test
     deps
 }
 
+// Race on the given track with every optional setting left at its default
+fn race_msg(track_id: u128, car_ids: Vec<u128>, train: bool, training_config: Option<TrainingConfig>) -> ExecuteMsg {
+    ExecuteMsg::SimulateRace {
+        track_id: cosmwasm_std::Uint128::from(track_id),
+        car_ids,
+        train,
+        training_config,
+        reward_config: None,
+        max_ticks: None,
+        response_detail: None,
+        teams: None,
+        end_on_first_finish: None,
+        record_replay: None,
+        ranking_mode: None,
+        report_records: None,
+        competitive: None,
+        shuffle_order: None,
+    }
+}
+
+// Default training race on track 1 for the given cars
+fn simulate_msg(car_ids: Vec<u128>) -> ExecuteMsg {
+    race_msg(1u128, car_ids, true, Some(TrainingConfig {
+        training_mode: true,
+        epsilon: 0.1,
+        ..Default::default()
+    }))
+}
+
 #[test]
 fn test_training_stats_after_race() {
     let mut deps = setup_test_app();
     let env = mock_env();
-    let info = mock_info("test_user", &[]);
+    let info = mock_info(ADMIN, &[]);
     
     // Test that we can query training stats (should return default values)
     let query_msg = QueryMsg::GetTrackTrainingStats {
@@ -109,20 +140,9 @@ fn test_training_stats_after_race() {
     println!("✅ Basic training stats query test passed");
     
     // Simulate a solo race with training enabled
-    let simulate_msg = ExecuteMsg::SimulateRace {
-        track_id: cosmwasm_std::Uint128::from(1u128),
-        car_ids: vec![1u128],
-        train: true,
-        training_config: Some(TrainingConfig {
-            training_mode: true,
-            epsilon: 0.1,
-            temperature: 0.0,
-            enable_epsilon_decay: false,
-        }),
-        reward_config: None,
-    };
+    let msg = simulate_msg(vec![1u128]);
     
-    let result = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg.clone());
+    let result = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
     assert!(result.is_ok());
     
     // Query training stats after the race
@@ -146,18 +166,7 @@ fn test_training_stats_after_race() {
     println!("✅ Training stats updated after solo race");
     
     // Test PvP race
-    let pvp_simulate_msg = ExecuteMsg::SimulateRace {
-        track_id: cosmwasm_std::Uint128::from(1u128),
-        car_ids: vec![1u128, 2u128],
-        train: true,
-        training_config: Some(TrainingConfig {
-                training_mode: true,
-                epsilon: 0.1,
-            temperature: 0.0,
-            enable_epsilon_decay: false,
-        }),
-        reward_config: None,
-    };
+    let pvp_simulate_msg = simulate_msg(vec![1u128, 2u128]);
     
     let pvp_result = execute(deps.as_mut(), env.clone(), info.clone(), pvp_simulate_msg);
     assert!(pvp_result.is_ok());
@@ -188,26 +197,19 @@ fn test_training_stats_after_race() {
 fn test_multiple_tracks_query() {
     let mut deps = setup_test_app();
     let env = mock_env();
-    let info = mock_info("test_user", &[]);
+    let info = mock_info(ADMIN, &[]);
     
     // Simulate races on multiple tracks for the same car
     let tracks = vec!["track_1", "track_2", "track_3"];
     
     for (i, track_id) in tracks.iter().enumerate() {
-        let simulate_msg = ExecuteMsg::SimulateRace {
-            track_id: cosmwasm_std::Uint128::from((i + 1) as u128),
-            car_ids: vec![1u128],
-            train: true,
-            training_config: Some(TrainingConfig {
+        let msg = race_msg((i + 1) as u128, vec![1u128], true, Some(TrainingConfig {
             training_mode: true,
             epsilon: 0.1,
-            temperature: 0.0,
-            enable_epsilon_decay: false,
-            }),
-            reward_config: None,
-        };
+            ..Default::default()
+        }));
         
-        let result = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg);
+        let result = execute(deps.as_mut(), env.clone(), info.clone(), msg);
         assert!(result.is_ok(), "Race simulation failed for track {}", track_id);
     }
     
@@ -238,26 +240,19 @@ fn test_multiple_tracks_query() {
 fn test_random_behavior_variability() {
     let mut deps = setup_test_app();
     let env = mock_env();
-    let info = mock_info("test_user", &[]);
+    let info = mock_info(ADMIN, &[]);
     
     // Test with high epsilon (90% random) to show variability
     let mut completion_times = vec![];
     
     for i in 0..5 {
-        let simulate_msg = ExecuteMsg::SimulateRace {
-            track_id: cosmwasm_std::Uint128::from(1u128),
-            car_ids: vec![1u128],
-            train: true,
-            training_config: Some(TrainingConfig {
-                training_mode: true,
-                epsilon: 0.9, // 90% random exploration
-                temperature: 0.0,
-                enable_epsilon_decay: false,
-            }),
-            reward_config: None,
-        };
+        let msg = race_msg(1u128, vec![1u128], true, Some(TrainingConfig {
+            training_mode: true,
+            epsilon: 0.9, // 90% random exploration
+            ..Default::default()
+        }));
         
-        let result = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg);
+        let result = execute(deps.as_mut(), env.clone(), info.clone(), msg);
         assert!(result.is_ok());
         
         // Query stats to get completion time
@@ -276,7 +271,7 @@ fn test_random_behavior_variability() {
         println!("Race {}: Fastest time = {} ticks", i + 1, stats.stats.solo.fastest);
         
         // Check if the car actually finished or hit the time limit
-        if stats.stats.solo.fastest == 100 {
+        if stats.stats.solo.fastest == u32::MAX {
             println!("  -> Car hit MAX_TICKS limit (didn't finish)");
             } else {
             println!("  -> Car finished successfully");
@@ -304,21 +299,14 @@ fn test_random_behavior_variability() {
 fn test_deterministic_vs_random() {
     let mut deps = setup_test_app();
     let env = mock_env();
-    let info = mock_info("test_user", &[]);
+    let info = mock_info(ADMIN, &[]);
     
     // Test 1: Deterministic behavior (epsilon = 0.0, no randomness)
-    let deterministic_msg = ExecuteMsg::SimulateRace {
-        track_id: cosmwasm_std::Uint128::from(1u128),
-        car_ids: vec![1u128],
-        train: true,
-        training_config: Some(TrainingConfig {
-            training_mode: true,
-            epsilon: 0.0, // No randomness
-            temperature: 0.0,
-            enable_epsilon_decay: false,
-        }),
-        reward_config: None,
-    };
+    let deterministic_msg = race_msg(1u128, vec![1u128], true, Some(TrainingConfig {
+        training_mode: true,
+        epsilon: 0.0, // No randomness
+        ..Default::default()
+    }));
     
     let result = execute(deps.as_mut(), env.clone(), info.clone(), deterministic_msg);
     assert!(result.is_ok());
@@ -337,18 +325,11 @@ fn test_deterministic_vs_random() {
     println!("Deterministic behavior: {} ticks", deterministic_time);
     
     // Test 2: Random behavior (epsilon = 1.0, 100% random)
-    let random_msg = ExecuteMsg::SimulateRace {
-        track_id: cosmwasm_std::Uint128::from(1u128),
-        car_ids: vec![1u128],
-        train: true,
-        training_config: Some(TrainingConfig {
-            training_mode: true,
-            epsilon: 1.0, // 100% random
-            temperature: 0.0,
-            enable_epsilon_decay: false,
-        }),
-        reward_config: None,
-    };
+    let random_msg = race_msg(1u128, vec![1u128], true, Some(TrainingConfig {
+        training_mode: true,
+        epsilon: 1.0, // 100% random
+        ..Default::default()
+    }));
     
     let result = execute(deps.as_mut(), env.clone(), info.clone(), random_msg);
     assert!(result.is_ok());
@@ -370,23 +351,16 @@ fn test_deterministic_vs_random() {
 fn test_empty_q_table_behavior() {
     let mut deps = setup_test_app();
     let env = mock_env();
-    let info = mock_info("test_user", &[]);
+    let info = mock_info(ADMIN, &[]);
     
     // Test with epsilon = 0.0 (no randomness) to see deterministic behavior
-    let simulate_msg = ExecuteMsg::SimulateRace {
-        track_id: cosmwasm_std::Uint128::from(1u128),
-        car_ids: vec![1u128],
-        train: true,
-        training_config: Some(TrainingConfig {
-            training_mode: true,
-            epsilon: 0.0, // No randomness - pure Q-learning
-                temperature: 0.0,
-                enable_epsilon_decay: false,
-        }),
-            reward_config: None,
-        };
+    let msg = race_msg(1u128, vec![1u128], true, Some(TrainingConfig {
+        training_mode: true,
+        epsilon: 0.0, // No randomness - pure Q-learning
+        ..Default::default()
+    }));
         
-    let result = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg);
+    let result = execute(deps.as_mut(), env.clone(), info.clone(), msg);
     assert!(result.is_ok());
     
     // Query stats to get completion time
@@ -404,18 +378,11 @@ fn test_empty_q_table_behavior() {
     println!("Deterministic behavior (epsilon=0.0): {} ticks", stats.stats.solo.fastest);
     
     // Run the same test again to see if it's consistent
-    let simulate_msg2 = ExecuteMsg::SimulateRace {
-        track_id: cosmwasm_std::Uint128::from(1u128),
-        car_ids: vec![1u128],
-        train: true,
-        training_config: Some(TrainingConfig {
-                training_mode: true,
-            epsilon: 0.0, // No randomness - pure Q-learning
-                temperature: 0.0,
-            enable_epsilon_decay: false,
-        }),
-        reward_config: None,
-    };
+    let simulate_msg2 = race_msg(1u128, vec![1u128], true, Some(TrainingConfig {
+        training_mode: true,
+        epsilon: 0.0, // No randomness - pure Q-learning
+        ..Default::default()
+    }));
     
     let result2 = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg2);
     assert!(result2.is_ok());
@@ -445,7 +412,7 @@ fn test_empty_q_table_behavior() {
 fn test_learning_process_investigation() {
     let mut deps = setup_test_app();
     let env = mock_env();
-    let info = mock_info("test_user", &[]);
+    let info = mock_info(ADMIN, &[]);
     
     // Run multiple races to see if the car learns and improves
     let mut completion_times = vec![];
@@ -457,20 +424,9 @@ fn test_learning_process_investigation() {
         };
         execute(deps.as_mut(), env.clone(), info.clone(), reset_msg).ok();
         
-        let simulate_msg = ExecuteMsg::SimulateRace {
-            track_id: cosmwasm_std::Uint128::from(1u128),
-            car_ids: vec![1u128],
-            train: true,
-            training_config: Some(TrainingConfig {
-                training_mode: true,
-                epsilon: 0.1, // 10% random
-                temperature: 0.0,
-                enable_epsilon_decay: false,
-            }),
-            reward_config: None,
-        };
+        let msg = simulate_msg(vec![1u128]);
         
-        let result = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg);
+        let result = execute(deps.as_mut(), env.clone(), info.clone(), msg);
         assert!(result.is_ok());
         
         // Query stats to get completion time
@@ -489,7 +445,7 @@ fn test_learning_process_investigation() {
         println!("Race {}: {} ticks", race_num + 1, stats.stats.solo.fastest);
         
         // Check if car finished or hit time limit
-        if stats.stats.solo.fastest == 100 {
+        if stats.stats.solo.fastest == u32::MAX {
             println!("  -> Hit time limit (didn't finish)");
             } else {
             println!("  -> Finished successfully");
@@ -519,7 +475,7 @@ fn test_learning_process_investigation() {
 fn test_seed_determinism_explanation() {
     let mut deps = setup_test_app();
     let env = mock_env();
-    let info = mock_info("test_user", &[]);
+    let info = mock_info(ADMIN, &[]);
     
     println!("🔍 Investigating why epsilon doesn't create variability between test runs...");
     
@@ -527,18 +483,11 @@ fn test_seed_determinism_explanation() {
     // This means the same "random" numbers are generated every time
     
     // Test 1: Run with epsilon = 0.5 (50% random)
-    let simulate_msg1 = ExecuteMsg::SimulateRace {
-        track_id: cosmwasm_std::Uint128::from(1u128),
-        car_ids: vec![1u128],
-        train: true,
-        training_config: Some(TrainingConfig {
-            training_mode: true,
-            epsilon: 0.5, // 50% random
-            temperature: 0.0,
-            enable_epsilon_decay: false,
-        }),
-        reward_config: None,
-    };
+    let simulate_msg1 = race_msg(1u128, vec![1u128], true, Some(TrainingConfig {
+        training_mode: true,
+        epsilon: 0.5, // 50% random
+        ..Default::default()
+    }));
     
     let result1 = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg1);
     assert!(result1.is_ok());
@@ -563,18 +512,11 @@ fn test_seed_determinism_explanation() {
     execute(deps.as_mut(), env.clone(), info.clone(), reset_msg).ok();
     
     // Test 2: Run again with same epsilon
-    let simulate_msg2 = ExecuteMsg::SimulateRace {
-        track_id: cosmwasm_std::Uint128::from(1u128),
-        car_ids: vec![1u128],
-        train: true,
-        training_config: Some(TrainingConfig {
-                training_mode: true,
-            epsilon: 0.5, // Same 50% random
-                temperature: 0.0,
-                enable_epsilon_decay: false,
-        }),
-        reward_config: None,
-    };
+    let simulate_msg2 = race_msg(1u128, vec![1u128], true, Some(TrainingConfig {
+        training_mode: true,
+        epsilon: 0.5, // Same 50% random
+        ..Default::default()
+    }));
     
     let result2 = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg2);
     assert!(result2.is_ok());
@@ -612,7 +554,7 @@ fn test_seed_determinism_explanation() {
 fn test_initial_q_values_investigation() {
     let mut deps = setup_test_app();
     let env = mock_env();
-    let info = mock_info("test_user", &[]);
+    let info = mock_info(ADMIN, &[]);
     
     println!("🔍 Investigating initial Q-values and action selection...");
     
@@ -622,7 +564,10 @@ fn test_initial_q_values_investigation() {
     // Query Q-values for the initial state
     let query_msg = QueryMsg::GetQ {
         car_id: 1u128,
+        track_id: None,
         state_hash: None, // Get all Q-values
+        start_after: None,
+        limit: None,
     };
     
     let response = query(deps.as_ref(), env.clone(), query_msg).unwrap();
@@ -643,20 +588,13 @@ fn test_initial_q_values_investigation() {
         };
         execute(deps.as_mut(), env.clone(), info.clone(), reset_msg).ok();
         
-        let simulate_msg = ExecuteMsg::SimulateRace {
-            track_id: cosmwasm_std::Uint128::from(1u128),
-            car_ids: vec![1u128],
-            train: true,
-            training_config: Some(TrainingConfig {
-                training_mode: true,
-                epsilon,
-                temperature: 0.0,
-                enable_epsilon_decay: false,
-            }),
-            reward_config: None,
-        };
+        let msg = race_msg(1u128, vec![1u128], true, Some(TrainingConfig {
+            training_mode: true,
+            epsilon: epsilon,
+            ..Default::default()
+        }));
         
-        let result = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg);
+        let result = execute(deps.as_mut(), env.clone(), info.clone(), msg);
         assert!(result.is_ok());
         
         let query_msg = QueryMsg::GetTrackTrainingStats {
@@ -698,7 +636,7 @@ fn test_initial_q_values_investigation() {
 fn test_epsilon_variance_investigation() {
     let mut deps = setup_test_app();
     let env = mock_env();
-    let info = mock_info("test_user", &[]);
+    let info = mock_info(ADMIN, &[]);
     
     println!("🔍 Investigating why epsilon 0.1-0.6 produces variance...");
     
@@ -712,20 +650,13 @@ fn test_epsilon_variance_investigation() {
         };
         execute(deps.as_mut(), env.clone(), info.clone(), reset_msg).ok();
         
-        let simulate_msg = ExecuteMsg::SimulateRace {
-            track_id: cosmwasm_std::Uint128::from(1u128),
-            car_ids: vec![1u128],
-            train: true,
-            training_config: Some(TrainingConfig {
-                training_mode: true,
-                epsilon,
-                temperature: 0.0,
-                enable_epsilon_decay: false,
-            }),
-            reward_config: None,
-        };
+        let msg = race_msg(1u128, vec![1u128], true, Some(TrainingConfig {
+            training_mode: true,
+            epsilon: epsilon,
+            ..Default::default()
+        }));
         
-        let result = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg);
+        let result = execute(deps.as_mut(), env.clone(), info.clone(), msg);
         assert!(result.is_ok());
         
         let query_msg = QueryMsg::GetTrackTrainingStats {
@@ -794,25 +725,18 @@ fn test_epsilon_variance_investigation() {
 fn test_epsilon_06_specific_investigation() {
     let mut deps = setup_test_app();
     let env = mock_env();
-    let info = mock_info("test_user", &[]);
+    let info = mock_info(ADMIN, &[]);
     
     println!("🔍 Investigating why epsilon 0.6 gives 60 ticks...");
     
     // Test epsilon 0.6 specifically
-    let simulate_msg = ExecuteMsg::SimulateRace {
-        track_id: cosmwasm_std::Uint128::from(1u128),
-        car_ids: vec![1u128],
-        train: true,
-        training_config: Some(TrainingConfig {
-            training_mode: true,
-            epsilon: 0.6, // 60% random
-            temperature: 0.0,
-            enable_epsilon_decay: false,
-        }),
-        reward_config: None,
-    };
+    let msg = race_msg(1u128, vec![1u128], true, Some(TrainingConfig {
+        training_mode: true,
+        epsilon: 0.6, // 60% random
+        ..Default::default()
+    }));
     
-    let result = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg);
+    let result = execute(deps.as_mut(), env.clone(), info.clone(), msg);
     assert!(result.is_ok());
     
     // Query training stats after the race
@@ -835,18 +759,7 @@ fn test_epsilon_06_specific_investigation() {
     };
     execute(deps.as_mut(), env.clone(), info.clone(), reset_msg).ok();
     
-    let simulate_msg2 = ExecuteMsg::SimulateRace {
-        track_id: cosmwasm_std::Uint128::from(1u128),
-        car_ids: vec![1u128],
-        train: true,
-        training_config: Some(TrainingConfig {
-            training_mode: true,
-            epsilon: 0.1, // 10% random
-            temperature: 0.0,
-            enable_epsilon_decay: false,
-        }),
-        reward_config: None,
-    };
+    let simulate_msg2 = simulate_msg(vec![1u128]);
     
     let result2 = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg2);
     assert!(result2.is_ok());
@@ -884,35 +797,41 @@ fn test_epsilon_06_specific_investigation() {
 fn test_pvp_training_stats() {
     let mut deps = setup_test_app();
     let env = mock_env();
-    let info = mock_info("test_user", &[]);
+    let info = mock_info(ADMIN, &[]);
     
     // Simulate a PvP race with multiple cars and training enabled
-    let simulate_msg = ExecuteMsg::SimulateRace {
-        track_id: cosmwasm_std::Uint128::from(1u128),
-        car_ids: vec![1u128, 2u128],
-        train: true,
-        training_config: Some(TrainingConfig {
-            training_mode: true,
-            epsilon: 0.1,
-            temperature: 0.0,
-            enable_epsilon_decay: false,
-        }),
-        reward_config: Some(RewardNumbers {
+    let mut msg = simulate_msg(vec![1u128, 2u128]);
+    if let ExecuteMsg::SimulateRace { reward_config, .. } = &mut msg {
+        *reward_config = Some(RewardNumbers {
             distance: 1,
             stuck: -5,
             wall: -8,
             no_move: 0,
             explore: 6,
             rank: racing::types::RankReward {
-                first: 100,
-                second: 50,
-                third: 25,
+                ranks: vec![100, 50, 25],
                 other: 0,
+                margin_bonus: None,
             },
-        }),
-    };
+            max_speed_reward: None,
+            draft_bonus: None,
+            checkpoint_bonus: None,
+            backward_penalty_multiplier: None,
+            collision_penalty: None,
+            reward_min: None,
+            reward_max: None,
+            comeback_bonus: None,
+            path_efficiency_bonus: None,
+            target_ticks: None,
+            overtime_penalty_per_tick: None,
+            evasion_bonus: None,
+            tile_variety_bonus: None,
+            frustration_penalty: None,
+            new_best_bonus: None,
+        });
+    }
     
-    let result = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg);
+    let result = execute(deps.as_mut(), env.clone(), info.clone(), msg);
     assert!(result.is_ok(), "PvP race simulation failed: {:?}", result.err());
     
     // Query training stats for both cars
@@ -944,18 +863,12 @@ fn test_pvp_training_stats() {
 fn test_no_training_stats_when_training_disabled() {
     let mut deps = setup_test_app();
     let env = mock_env();
-    let info = mock_info("test_user", &[]);
+    let info = mock_info(ADMIN, &[]);
     
     // Simulate a race with training disabled
-    let simulate_msg = ExecuteMsg::SimulateRace {
-        track_id: cosmwasm_std::Uint128::from(1u128),
-        car_ids: vec![1u128],
-        train: false, // Training disabled
-        training_config: None,
-        reward_config: None,
-    };
+    let msg = race_msg(1u128, vec![1u128], false, None);
     
-    let result = execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg);
+    let result = execute(deps.as_mut(), env.clone(), info.clone(), msg);
     assert!(result.is_ok(), "Race simulation failed: {:?}", result.err());
     
     // Query training stats after the race
@@ -977,4 +890,4456 @@ fn test_no_training_stats_when_training_disabled() {
     assert_eq!(stats.stats.pvp.fastest, u32::MAX, "PvP fastest should remain default");
     
     println!("✅ No training stats test passed!");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_simulate_race_requires_authorized_caller() {
+    let mut deps = setup_test_app();
+    let env = mock_env();
+
+    // A random sender can't run races or reset Q-tables
+    let stranger = mock_info("stranger", &[]);
+    let err = execute(deps.as_mut(), env.clone(), stranger.clone(), simulate_msg(vec![1u128])).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let err = execute(deps.as_mut(), env.clone(), stranger.clone(), ExecuteMsg::ResetQ {
+        car_id: cosmwasm_std::Uint128::from(1u128),
+    }).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // The admin is always allowed
+    let admin = mock_info(ADMIN, &[]);
+    execute(deps.as_mut(), env.clone(), admin.clone(), simulate_msg(vec![1u128])).unwrap();
+
+    // Only the admin can update the allowlist
+    let update_msg = ExecuteMsg::UpdateAuthorizedCallers {
+        authorized_callers: vec!["trainer".to_string()],
+    };
+    let err = execute(deps.as_mut(), env.clone(), stranger, update_msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), env.clone(), admin, update_msg).unwrap();
+
+    // Allowlisted callers can race but still can't reset Q-tables
+    let trainer = mock_info("trainer", &[]);
+    execute(deps.as_mut(), env.clone(), trainer.clone(), simulate_msg(vec![1u128])).unwrap();
+    let err = execute(deps.as_mut(), env, trainer, ExecuteMsg::ResetQ {
+        car_id: cosmwasm_std::Uint128::from(1u128),
+    }).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+}
+
+#[test]
+fn test_max_ticks_override() {
+    let mut deps = setup_test_app();
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+    let track = create_test_track();
+
+    // One tick isn't enough to cross the 5x5 test track
+    let mut msg = race_msg(1u128, vec![1u128, 2u128], true, None);
+    if let ExecuteMsg::SimulateRace { max_ticks, .. } = &mut msg {
+        *max_ticks = Some(1);
+    }
+    execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+    let query_msg = QueryMsg::ListRecentRaces {
+        car_id: None,
+        track_id: Some(1u128),
+        start_after: None,
+        limit: None,
+    };
+    let response = query(deps.as_ref(), env.clone(), query_msg).unwrap();
+    let races: racing::race_engine::RecentRacesResponse = from_json(response).unwrap();
+    let race = races.races.last().unwrap();
+
+    // Nobody finished, every car used exactly the tick budget
+    assert!(race.winner_ids.is_empty());
+    assert_eq!(race.rankings.len(), 2);
+    for step in &race.steps_taken {
+        assert_eq!(step.steps_taken, 1);
+    }
+
+    // Unfinished cars are ranked by the progress of the tile they ended on
+    let progress: Vec<u16> = race.rankings.iter().map(|rank| {
+        let last = race.play_by_play[&rank.car_id].actions.last().unwrap();
+        track.layout[last.resulting_position.y as usize][last.resulting_position.x as usize].progress_towards_finish
+    }).collect();
+    assert!(progress.windows(2).all(|pair| pair[0] >= pair[1]));
+
+    // Unfinished runs count towards the tally but never set a fastest time
+    let query_msg = QueryMsg::GetTrackTrainingStats {
+        car_id: 1u128,
+        track_id: Some(1u128),
+        start_after: None,
+        limit: None,
+    };
+    let response = query(deps.as_ref(), env.clone(), query_msg).unwrap();
+    let stats: Vec<GetTrackTrainingStatsResponse> = from_json(response).unwrap();
+    assert_eq!((stats[0].stats.pvp.tally, stats[0].stats.pvp.fastest), (1, u32::MAX));
+
+    // Zero and over-the-cap limits are rejected
+    for max_ticks in [0u32, crate::state::MAX_TICKS_LIMIT + 1] {
+        let mut msg = race_msg(1u128, vec![1u128], true, None);
+        if let ExecuteMsg::SimulateRace { max_ticks: limit, .. } = &mut msg {
+            *limit = Some(max_ticks);
+        }
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidMaxTicks { max_ticks, limit: crate::state::MAX_TICKS_LIMIT });
+    }
+}
+
+fn test_car_state(car_id: u128) -> racing::race_engine::CarState {
+    racing::race_engine::CarState {
+        car_id,
+        tile: TrackTile {
+            properties: TileProperties::normal(),
+            progress_towards_finish: 0,
+            x: 0,
+            y: 0,
+        },
+        x: 0,
+        y: 0,
+        stuck: false,
+        stuck_remaining: 0,
+        move_cooldown: 0,
+        midpoint_rank: None,
+        start_progress: 0,
+        cells_traveled: 0,
+        q_updates_applied: 0,
+        tile_kinds: vec![],
+        failed_races: 0,
+        best_progress: None,
+        eliminated: false,
+        finished: false,
+        steps_taken: 0,
+        last_action: 0,
+        action_history: vec![],
+        hit_wall: false,
+        wall_hits: 0,
+        drafting: vec![],
+        first_to_checkpoint: vec![],
+        evaded: vec![],
+        new_best: vec![],
+        outcomes: vec![],
+        current_speed: 1,
+        q_table: vec![],
+    }
+}
+
+#[test]
+fn test_eligibility_traces_credit_earlier_states() {
+    use crate::contract::apply_batched_q_updates;
+    use crate::state::{get_config, get_q_values};
+    use cosmwasm_std::QuerierWrapper;
+
+    // Five-step episode where only the final action is rewarded
+    let updates: Vec<([u8; 32], u8, i32, Option<[u8; 32]>)> = (0..5u8)
+        .map(|i| {
+            let reward = if i == 4 { 100 } else { 0 };
+            let next = if i == 4 { None } else { Some([i + 1; 32]) };
+            ([i; 32], 0, reward, next)
+        })
+        .collect();
+
+    let first_state_q = |lambda: f32| -> i32 {
+        let mut deps = setup_test_app();
+        let config = get_config(&deps.storage).unwrap();
+        let car = test_car_state(1);
+        apply_batched_q_updates(
+            &mut deps.storage,
+            &car,
+            None,
+            vec![updates.clone()],
+            config,
+            QuerierWrapper::new(&deps.querier),
+            lambda,
+        )
+        .unwrap();
+        get_q_values(&deps.storage, 1, None, &[0; 32], &StateEncoding::Hashed).unwrap()[0]
+    };
+
+    // 1-step Q-learning only credits the rewarded action
+    assert_eq!(first_state_q(0.0), 0);
+    // With traces the reward propagates back to the first state
+    assert!(first_state_q(0.9) > 0);
+}
+
+#[test]
+fn test_get_timeout_value() {
+    let mut deps = setup_test_app();
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+
+    let response = query(deps.as_ref(), env.clone(), QueryMsg::GetTimeoutValue { track_id: 1u128 }).unwrap();
+    let timeout: racing::race_engine::TimeoutValueResponse = from_json(response).unwrap();
+    let response = query(deps.as_ref(), env.clone(), QueryMsg::GetConfig {}).unwrap();
+    let config: racing::race_engine::Config = from_json(response).unwrap();
+    assert_eq!(timeout.track_id, 1u128);
+    assert_eq!(timeout.max_ticks, config.max_ticks);
+
+    // Races without an override never run past the reported timeout
+    execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg(vec![1u128, 2u128])).unwrap();
+    let query_msg = QueryMsg::ListRecentRaces {
+        car_id: None,
+        track_id: Some(1u128),
+        start_after: None,
+        limit: None,
+    };
+    let response = query(deps.as_ref(), env.clone(), query_msg).unwrap();
+    let races: racing::race_engine::RecentRacesResponse = from_json(response).unwrap();
+    for step in &races.races.last().unwrap().steps_taken {
+        assert!(step.steps_taken <= timeout.max_ticks);
+    }
+
+    // A fastest time is either a finish inside the timeout or the no-finish sentinel
+    for car_id in [1u128, 2u128] {
+        let query_msg = QueryMsg::GetTrackTrainingStats {
+            car_id,
+            track_id: Some(1u128),
+            start_after: None,
+            limit: None,
+        };
+        let response = query(deps.as_ref(), env.clone(), query_msg).unwrap();
+        let stats: Vec<GetTrackTrainingStatsResponse> = from_json(response).unwrap();
+        let fastest = stats[0].stats.pvp.fastest;
+        assert!(fastest == u32::MAX || fastest <= timeout.max_ticks);
+    }
+}
+
+#[test]
+fn test_finish_ties_broken_by_wall_hits_then_car_id() {
+    use crate::contract::calculate_results;
+
+    let track = create_test_track();
+    let finished_car = |car_id: u128, wall_hits: u32| {
+        let mut car = test_car_state(car_id);
+        car.finished = true;
+        car.steps_taken = 6;
+        car.wall_hits = wall_hits;
+        car
+    };
+
+    // Same tick, car 1 hit the wall more often
+    let cars = vec![finished_car(1, 2), finished_car(2, 0)];
+    let (winner_ids, rankings, _) = calculate_results(&cars, &track.layout, &RankingMode::FinishedFirst);
+    assert_eq!(winner_ids, vec![2u128, 1u128]);
+    assert_eq!(rankings[0].car_id, 2u128);
+    assert_eq!(rankings[0].rank, 0);
+
+    // Same tick and wall hits, the lower car_id wins regardless of input order
+    let cars = vec![finished_car(5, 1), finished_car(3, 1)];
+    let (winner_ids, _, _) = calculate_results(&cars, &track.layout, &RankingMode::FinishedFirst);
+    assert_eq!(winner_ids, vec![3u128, 5u128]);
+}
+
+#[test]
+fn test_progress_unified_ranks_near_finisher_above_slow_finisher() {
+    use crate::contract::calculate_results;
+
+    let track = create_test_track();
+    let car = |car_id: u128, finished: bool, progress: u16, steps_taken: u32| {
+        let mut car = test_car_state(car_id);
+        car.finished = finished;
+        car.tile.progress_towards_finish = progress;
+        car.steps_taken = steps_taken;
+        car
+    };
+    let order = |rankings: Vec<racing::race_engine::Rank>| rankings.iter().map(|rank| rank.car_id).collect::<Vec<_>>();
+
+    // Car 1 crossed the line on the last tick, car 2 stalled a row short of the finish after 6 steps
+    let cars = vec![car(1, true, 4, 10), car(2, false, 3, 6), car(3, false, 1, 10)];
+    let (winner_ids, rankings, _) = calculate_results(&cars, &track.layout, &RankingMode::FinishedFirst);
+    assert_eq!(order(rankings), vec![1, 2, 3]);
+    assert_eq!(winner_ids, vec![1u128]);
+
+    let (winner_ids, rankings, _) = calculate_results(&cars, &track.layout, &RankingMode::ProgressUnified);
+    assert_eq!(order(rankings), vec![2, 1, 3]);
+    assert_eq!(winner_ids, vec![1u128]);
+
+    // A finisher with ticks to spare stays ahead
+    let cars = vec![car(1, true, 4, 5), car(2, false, 3, 6), car(3, false, 1, 10)];
+    let (_, rankings, _) = calculate_results(&cars, &track.layout, &RankingMode::ProgressUnified);
+    assert_eq!(order(rankings), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_q_table_size_resets_with_q_table() {
+    let mut deps = setup_test_app();
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+
+    let size = |deps: cosmwasm_std::Deps| -> racing::race_engine::QTableSizeResponse {
+        let response = query(deps, mock_env(), QueryMsg::GetQTableSize { car_id: 1u128, track_id: None }).unwrap();
+        from_json(response).unwrap()
+    };
+    assert_eq!(size(deps.as_ref()).entries, 0);
+
+    execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg(vec![1u128])).unwrap();
+    let trained = size(deps.as_ref());
+    assert!(trained.entries > 0);
+    assert_eq!(trained.estimated_bytes, trained.entries * 48);
+
+    let reset_msg = ExecuteMsg::ResetQ { car_id: cosmwasm_std::Uint128::from(1u128) };
+    execute(deps.as_mut(), env.clone(), info.clone(), reset_msg).unwrap();
+    let reset = size(deps.as_ref());
+    assert_eq!(reset.entries, 0);
+    assert_eq!(reset.estimated_bytes, 0);
+}
+
+// Serve the standard test track as track 1 and a walled variant as track 2
+fn serve_two_tracks(deps: &mut OwnedDeps<cosmwasm_std::MemoryStorage, cosmwasm_std::testing::MockApi, cosmwasm_std::testing::MockQuerier<cosmwasm_std::Empty>>) {
+    let track_one = create_test_track();
+    let mut track_two = create_test_track();
+    track_two.id = 2;
+    for y in 1..4 {
+        track_two.layout[y][2] = TrackTile {
+            properties: TileProperties::wall(),
+            progress_towards_finish: 0,
+            x: 2,
+            y: y as u8,
+        };
+    }
+
+    deps.querier.update_wasm(move |w| {
+        match w {
+            cosmwasm_std::WasmQuery::Smart { contract_addr, msg } if *contract_addr == TRACK_CONTRACT => {
+                let racing::track_manager::QueryMsg::GetTrack { track_id } = from_json(msg).unwrap() else {
+                    return Ok(ContractResult::Err("Unknown query".to_string())).into();
+                };
+                let track = if track_id.u128() == 2 { &track_two } else { &track_one };
+                Ok(ContractResult::Ok(to_json_binary(track).unwrap())).into()
+            }
+            _ => Ok(ContractResult::Err(cosmwasm_std::StdError::generic_err("Unknown query").to_string())).into(),
+        }
+    });
+}
+
+#[test]
+fn test_race_state_does_not_leak_across_tracks() {
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+    let race_on = |track_id: u128| race_msg(track_id, vec![1u128], false, None);
+    let last_race = |deps: cosmwasm_std::Deps| -> racing::race_engine::RaceResult {
+        let query_msg = QueryMsg::ListRecentRaces {
+            car_id: None,
+            track_id: Some(2u128),
+            start_after: None,
+            limit: None,
+        };
+        let response = query(deps, mock_env(), query_msg).unwrap();
+        let races: racing::race_engine::RecentRacesResponse = from_json(response).unwrap();
+        races.races.last().unwrap().clone()
+    };
+
+    // Track 1 first, then track 2
+    let mut deps = setup_test_app();
+    serve_two_tracks(&mut deps);
+    execute(deps.as_mut(), env.clone(), info.clone(), race_on(1)).unwrap();
+    execute(deps.as_mut(), env.clone(), info.clone(), race_on(2)).unwrap();
+    let after_track_one = last_race(deps.as_ref());
+
+    // Track 2 on a fresh contract
+    let mut fresh_deps = setup_test_app();
+    serve_two_tracks(&mut fresh_deps);
+    execute(fresh_deps.as_mut(), env.clone(), info.clone(), race_on(2)).unwrap();
+    let fresh = last_race(fresh_deps.as_ref());
+
+    // Without training nothing from the first race should shape the second
+    assert_eq!(after_track_one.play_by_play, fresh.play_by_play);
+    assert_eq!(after_track_one.steps_taken, fresh.steps_taken);
+    assert_eq!(after_track_one.rankings, fresh.rankings);
+}
+
+#[test]
+fn test_compact_q_table_prunes_weak_entries() {
+    use crate::state::{get_q_values, set_q_values};
+
+    let mut deps = setup_test_app();
+    let env = mock_env();
+
+    set_q_values(&mut deps.storage, 1, None, &[1; 32], &StateEncoding::Hashed, [0, 0, 0, 0]).unwrap();
+    set_q_values(&mut deps.storage, 1, None, &[2; 32], &StateEncoding::Hashed, [1, -2, 0, 1]).unwrap();
+    set_q_values(&mut deps.storage, 1, None, &[3; 32], &StateEncoding::Hashed, [0, -40, 3, 0]).unwrap();
+    // Other cars are untouched
+    set_q_values(&mut deps.storage, 2, None, &[1; 32], &StateEncoding::Hashed, [0, 0, 0, 0]).unwrap();
+
+    let compact_msg = ExecuteMsg::CompactQTable {
+        car_id: cosmwasm_std::Uint128::from(1u128),
+        track_id: None,
+        threshold: 2,
+    };
+
+    // Admin only
+    let err = execute(deps.as_mut(), env.clone(), mock_info("stranger", &[]), compact_msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let res = execute(deps.as_mut(), env.clone(), mock_info(ADMIN, &[]), compact_msg).unwrap();
+    assert!(res.attributes.iter().any(|attr| attr.key == "pruned" && attr.value == "2"));
+
+    assert!(get_q_values(&deps.storage, 1, None, &[1; 32], &StateEncoding::Hashed).is_err());
+    assert!(get_q_values(&deps.storage, 1, None, &[2; 32], &StateEncoding::Hashed).is_err());
+    assert_eq!(get_q_values(&deps.storage, 1, None, &[3; 32], &StateEncoding::Hashed).unwrap(), [0, -40, 3, 0]);
+    assert_eq!(get_q_values(&deps.storage, 2, None, &[1; 32], &StateEncoding::Hashed).unwrap(), [0, 0, 0, 0]);
+}
+
+#[test]
+fn test_collision_priority_lets_one_car_through() {
+    use crate::contract::resolve_collisions;
+
+    let car_at = |car_id: u128, x: i32, y: i32, progress: u16| {
+        let mut car = test_car_state(car_id);
+        car.x = x;
+        car.y = y;
+        car.tile.progress_towards_finish = progress;
+        car
+    };
+
+    // Both cars head for (2, 2), car 2 sits on the higher-progress tile
+    let mut cars = vec![car_at(1, 1, 2, 1), car_at(2, 3, 2, 2)];
+    let final_positions = resolve_collisions(&cars, &[(2, 2), (2, 2)], None);
+    assert_eq!(final_positions, vec![(1, 2), (2, 2)]);
+
+    // Next tick car 2 moves on and car 1 takes the freed cell
+    cars[1].x = 2;
+    let final_positions = resolve_collisions(&cars, &[(2, 2), (2, 1)], None);
+    assert_eq!(final_positions, vec![(2, 2), (2, 1)]);
+
+    // Equal progress falls back to fewer steps, then lower car_id
+    let mut cars = vec![car_at(1, 1, 2, 1), car_at(2, 3, 2, 1)];
+    assert_eq!(resolve_collisions(&cars, &[(2, 2), (2, 2)], None), vec![(2, 2), (3, 2)]);
+    cars[0].steps_taken = 3;
+    assert_eq!(resolve_collisions(&cars, &[(2, 2), (2, 2)], None), vec![(1, 2), (2, 2)]);
+
+    // A car that isn't moving keeps its cell, and a car sent back blocks the one behind it
+    let cars = vec![car_at(1, 2, 2, 0), car_at(2, 2, 3, 4), car_at(3, 2, 4, 4)];
+    let final_positions = resolve_collisions(&cars, &[(2, 2), (2, 2), (2, 3)], None);
+    assert_eq!(final_positions, vec![(2, 2), (2, 3), (2, 4)]);
+}
+
+#[test]
+fn test_speed_reward_capped_for_short_finishes() {
+    use crate::contract::calculate_action_reward;
+
+    let finish_tile = TrackTile {
+        properties: TileProperties::finish(),
+        progress_towards_finish: 0,
+        x: 0,
+        y: 0,
+    };
+    let mut car = test_car_state(1);
+    car.finished = true;
+    car.steps_taken = 1;
+
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1u128],
+        winner_ids: vec![1u128],
+        rankings: vec![racing::race_engine::Rank { car_id: 1u128, rank: 0 }],
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![racing::race_engine::Step { car_id: 1u128, steps_taken: 1, finished: true }],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+    let mut reward_config = RewardNumbers {
+        distance: 1,
+        stuck: -5,
+        wall: -8,
+        no_move: 0,
+        explore: 6,
+        rank: racing::types::RankReward {
+            ranks: vec![100, 50, 25],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+
+    // One action against a 10 tick record would be a 1000 speed reward uncapped
+    for total_actions in [0usize, 1] {
+        let reward = calculate_action_reward(&car, &race_result, 0, finish_tile.clone(), finish_tile.clone(), 0, total_actions, reward_config.clone(), 10, None, None).unwrap();
+        assert_eq!(reward, 100 + 100);
+    }
+
+    // Custom cap
+    reward_config.max_speed_reward = Some(30);
+    let reward = calculate_action_reward(&car, &race_result, 0, finish_tile.clone(), finish_tile.clone(), 0, 1, reward_config, 10, None, None).unwrap();
+    assert_eq!(reward, 100 + 30);
+}
+
+#[test]
+fn test_get_car_wins_only_returns_wins() {
+    let mut deps = setup_test_app();
+    let info = mock_info(ADMIN, &[]);
+
+    // Alternate full races with one-tick races nobody can win
+    for i in 0..6u64 {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(i);
+        let mut msg = race_msg(1u128, vec![1u128, 2u128], true, None);
+        if let ExecuteMsg::SimulateRace { max_ticks, .. } = &mut msg {
+            *max_ticks = if i % 2 == 0 { None } else { Some(1) };
+        }
+        execute(deps.as_mut(), env, info.clone(), msg).unwrap();
+    }
+
+    let query_msg = QueryMsg::ListRecentRaces {
+        car_id: Some(1u128),
+        track_id: None,
+        start_after: None,
+        limit: None,
+    };
+    let response = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+    let races: racing::race_engine::RecentRacesResponse = from_json(response).unwrap();
+    let expected: Vec<String> = races.races.iter().rev()
+        .filter(|race| race.winner_ids.contains(&1u128))
+        .map(|race| race.race_id.clone())
+        .collect();
+    let losses: Vec<String> = races.races.iter()
+        .filter(|race| !race.winner_ids.contains(&1u128))
+        .map(|race| race.race_id.clone())
+        .collect();
+    assert!(losses.len() >= 3);
+
+    let query_msg = QueryMsg::GetCarWins { car_id: 1u128, track_id: Some(1u128), limit: None };
+    let response = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+    let wins: racing::race_engine::CarWinsResponse = from_json(response).unwrap();
+    let win_ids: Vec<String> = wins.wins.iter().map(|win| win.race_id.clone()).collect();
+    assert_eq!(win_ids, expected);
+    assert!(win_ids.iter().all(|race_id| !losses.contains(race_id)));
+    for win in &wins.wins {
+        assert!(win.steps_taken > 1);
+    }
+
+    // Other tracks have no wins
+    let query_msg = QueryMsg::GetCarWins { car_id: 1u128, track_id: Some(2u128), limit: None };
+    let response = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+    let wins: racing::race_engine::CarWinsResponse = from_json(response).unwrap();
+    assert!(wins.wins.is_empty());
+}
+
+#[test]
+fn test_swap_collision_blocks_both_cars() {
+    use crate::contract::resolve_collisions;
+
+    let car_at = |car_id: u128, x: i32, y: i32| {
+        let mut car = test_car_state(car_id);
+        car.x = x;
+        car.y = y;
+        car
+    };
+
+    // One-wide corridor in column 2, the cars drive head-on into each other
+    let cars = vec![car_at(1, 2, 1), car_at(2, 2, 2)];
+    let final_positions = resolve_collisions(&cars, &[(2, 2), (2, 1)], None);
+    assert_eq!(final_positions, vec![(2, 1), (2, 2)]);
+
+    // Following each other down the corridor is not a swap
+    let cars = vec![car_at(1, 2, 1), car_at(2, 2, 2)];
+    let final_positions = resolve_collisions(&cars, &[(2, 2), (2, 3)], None);
+    assert_eq!(final_positions, vec![(2, 2), (2, 3)]);
+}
+
+#[test]
+fn test_shuffled_processing_order_rotates_the_lead() {
+    use crate::contract::{processing_order, resolve_collisions};
+
+    let cars: Vec<_> = [7u128, 3, 5, 1].into_iter().map(test_car_state).collect();
+
+    // Unshuffled, cars go in their own order every tick
+    for tick in 0..10 {
+        assert_eq!(processing_order(&cars, tick, false), vec![0, 1, 2, 3]);
+    }
+
+    // Shuffled, every car leads about a quarter of the ticks
+    let ticks = 4000;
+    let mut leads = [0u32; 4];
+    for tick in 0..ticks {
+        let order = processing_order(&cars, tick, true);
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted, vec![0, 1, 2, 3]);
+        leads[order[0]] += 1;
+    }
+    for lead in leads {
+        assert!(lead.abs_diff(ticks / 4) < ticks / 20, "leads {:?}", leads);
+    }
+
+    // The same tick always gives the same order
+    assert_eq!(processing_order(&cars, 17, true), processing_order(&cars, 17, true));
+
+    // A tie for a contested cell goes to whichever car the shuffled order puts first, unshuffled to the lower car_id
+    let car_at = |car_id: u128, x: i32| {
+        let mut car = test_car_state(car_id);
+        car.x = x;
+        car.y = 2;
+        car
+    };
+    let cars = vec![car_at(2, 1), car_at(1, 3)];
+    assert_eq!(resolve_collisions(&cars, &[(2, 2), (2, 2)], Some(&[0, 1][..])), vec![(2, 2), (3, 2)]);
+    assert_eq!(resolve_collisions(&cars, &[(2, 2), (2, 2)], Some(&[1, 0][..])), vec![(1, 2), (2, 2)]);
+    assert_eq!(resolve_collisions(&cars, &[(2, 2), (2, 2)], None), vec![(1, 2), (2, 2)]);
+}
+
+#[test]
+fn test_trailing_car_accrues_draft_bonus() {
+    use crate::contract::{calculate_action_reward, generate_state_hash, simulate_tick};
+    use crate::state::set_q_values;
+
+    // Straight track where progress grows towards the finish row
+    let layout = create_test_track().layout;
+
+    let car_at = |car_id: u128, x: i32, y: i32| {
+        let mut car = test_car_state(car_id);
+        car.x = x;
+        car.y = y;
+        car.tile = layout[y as usize][x as usize].clone();
+        car
+    };
+    let mut race_state = racing::race_engine::RaceState {
+        cars: vec![car_at(1, 2, 2), car_at(2, 2, 3)],
+        track_layout: layout.clone(),
+        tick: 0,
+        play_by_play: std::collections::HashMap::new(),
+        claimed_checkpoints: vec![],
+        state_encoding: StateEncoding::Hashed,
+        finish_tiles: vec![],
+        q_track: None,
+        distinguish_edges: false,
+        shuffle_order: false,
+    };
+
+    // Both cars drive straight up for two ticks, car 2 right behind car 1
+    let mut deps = setup_test_app();
+    for (leader_y, trailer_y) in [(2, 3), (1, 2)] {
+        let leader_state = generate_state_hash(&layout, 2, leader_y, 1, &[(2, trailer_y)], &[], &StateEncoding::Hashed, false);
+        let trailer_state = generate_state_hash(&layout, 2, trailer_y, 1, &[(2, leader_y)], &[], &StateEncoding::Hashed, false);
+        set_q_values(&mut deps.storage, 1, None, &leader_state, &StateEncoding::Hashed, [10, 0, 0, 0]).unwrap();
+        set_q_values(&mut deps.storage, 2, None, &trailer_state, &StateEncoding::Hashed, [10, 0, 0, 0]).unwrap();
+    }
+    let training_config = TrainingConfig {
+        ..Default::default()
+    };
+    for tick in 0..2 {
+        simulate_tick(&mut deps.storage, &mut race_state, training_config.clone(), tick, 10).unwrap();
+    }
+
+    let leader = &race_state.cars[0];
+    let trailer = &race_state.cars[1];
+    assert!(leader.finished);
+    assert_eq!((trailer.x, trailer.y), (2, 1));
+    assert_eq!(leader.drafting, vec![false, false]);
+    assert_eq!(trailer.drafting, vec![true, true]);
+
+    // Only the trailing car's actions pick up the bonus
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1u128, 2u128],
+        winner_ids: vec![1u128],
+        rankings: vec![],
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+    let reward_config = |draft_bonus: Option<i32>| RewardNumbers {
+        distance: 0,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0, 0, 0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+    let (_, _, tile) = &trailer.action_history[0];
+    let with_bonus = calculate_action_reward(trailer, &race_result, 0, tile.clone(), tile.clone(), 0, 2, reward_config(Some(7)), 10, None, None).unwrap();
+    let without_bonus = calculate_action_reward(trailer, &race_result, 0, tile.clone(), tile.clone(), 0, 2, reward_config(None), 10, None, None).unwrap();
+    assert_eq!(with_bonus - without_bonus, 7);
+    let leader_reward = calculate_action_reward(leader, &race_result, 0, tile.clone(), tile.clone(), 0, 2, reward_config(Some(7)), 10, None, None).unwrap();
+    let leader_base = calculate_action_reward(leader, &race_result, 0, tile.clone(), tile.clone(), 0, 2, reward_config(None), 10, None, None).unwrap();
+    assert_eq!(leader_reward, leader_base);
+}
+
+#[test]
+fn test_simulate_race_response_detail() {
+    use racing::race_engine::{ResponseDetail, SimulateRaceResponse};
+
+    let mut deps = setup_test_app();
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+    let race_with = |response_detail: Option<ResponseDetail>| {
+        let mut msg = race_msg(1u128, vec![1u128, 2u128], false, None);
+        if let ExecuteMsg::SimulateRace { response_detail: detail, .. } = &mut msg {
+            *detail = response_detail;
+        }
+        msg
+    };
+
+    // Summary by default
+    for response_detail in [None, Some(ResponseDetail::Summary)] {
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), race_with(response_detail)).unwrap();
+        let data: SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+        assert_eq!(data.rankings.len(), 2);
+        assert!(data.play_by_play.is_none());
+        assert!(data.steps_taken.is_none());
+    }
+
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), race_with(Some(ResponseDetail::Full))).unwrap();
+    let data: SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+    assert_eq!(data.rankings.len(), 2);
+    let play_by_play = data.play_by_play.unwrap();
+    assert!(play_by_play.contains_key(&1u128) && play_by_play.contains_key(&2u128));
+    assert_eq!(data.steps_taken.unwrap().len(), 2);
+}
+
+#[test]
+fn test_checkpoint_bonus_only_for_first_arrival() {
+    use crate::contract::{calculate_action_reward, generate_state_hash, simulate_tick};
+    use crate::state::set_q_values;
+
+    let mut layout = create_test_track().layout;
+    for x in [1usize, 2] {
+        layout[1][x].properties = TileProperties::checkpoint();
+    }
+
+    let car_at = |car_id: u128, x: i32, y: i32| {
+        let mut car = test_car_state(car_id);
+        car.x = x;
+        car.y = y;
+        car.tile = layout[y as usize][x as usize].clone();
+        car
+    };
+    let mut race_state = racing::race_engine::RaceState {
+        cars: vec![car_at(1, 2, 2), car_at(2, 1, 2)],
+        track_layout: layout.clone(),
+        tick: 0,
+        play_by_play: std::collections::HashMap::new(),
+        claimed_checkpoints: vec![],
+        state_encoding: StateEncoding::Hashed,
+        finish_tiles: vec![],
+        q_track: None,
+        distinguish_edges: false,
+        shuffle_order: false,
+    };
+
+    // Tick 0: both cars drive up onto a checkpoint each
+    // Tick 1: car 1 drives on to the finish, car 2 turns right onto car 1's checkpoint
+    let mut deps = setup_test_app();
+    let moves = [
+        (1u128, (2, 2), (1, 2), [10, 0, 0, 0]),
+        (2u128, (1, 2), (2, 2), [10, 0, 0, 0]),
+        (1u128, (2, 1), (1, 1), [10, 0, 0, 0]),
+        (2u128, (1, 1), (2, 1), [0, 0, 0, 10]),
+    ];
+    for (car_id, (x, y), other, q_values) in moves {
+        let state_hash = generate_state_hash(&layout, x, y, 1, &[other], &[], &StateEncoding::Hashed, false);
+        set_q_values(&mut deps.storage, car_id, None, &state_hash, &StateEncoding::Hashed, q_values).unwrap();
+    }
+    let training_config = TrainingConfig {
+        ..Default::default()
+    };
+    for tick in 0..2 {
+        simulate_tick(&mut deps.storage, &mut race_state, training_config.clone(), tick, 10).unwrap();
+    }
+
+    let (first, second) = (&race_state.cars[0], &race_state.cars[1]);
+    assert_eq!((second.x, second.y), (2, 1));
+    assert_eq!(first.first_to_checkpoint, vec![true, false]);
+    assert_eq!(second.first_to_checkpoint, vec![true, false]);
+    assert_eq!(race_state.claimed_checkpoints, vec![(2, 1), (1, 1)]);
+
+    // The bonus follows the first arrivals only
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1u128, 2u128],
+        winner_ids: vec![1u128],
+        rankings: vec![],
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+    let mut reward_config = RewardNumbers {
+        distance: 0,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0, 0, 0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+    let tile = layout[1][2].clone();
+    let reward = |reward_config: &RewardNumbers, action_index: usize| {
+        calculate_action_reward(second, &race_result, 0, tile.clone(), tile.clone(), action_index, 2, reward_config.clone(), 10, None, None).unwrap()
+    };
+    let base = [reward(&reward_config, 0), reward(&reward_config, 1)];
+    reward_config.checkpoint_bonus = Some(15);
+    assert_eq!(reward(&reward_config, 0) - base[0], 15);
+    assert_eq!(reward(&reward_config, 1), base[1]);
+}
+
+#[test]
+fn test_evasion_bonus_for_steering_around_contested_cell() {
+    use crate::contract::{calculate_reward_breakdown, generate_state_hash, simulate_tick};
+    use crate::state::set_q_values;
+    use racing::race_engine::ActionOutcome;
+
+    let layout = create_test_track().layout;
+    let car_at = |car_id: u128, x: i32, y: i32, last_action: usize| {
+        let mut car = test_car_state(car_id);
+        car.x = x;
+        car.y = y;
+        car.last_action = last_action;
+        car.tile = layout[y as usize][x as usize].clone();
+        car
+    };
+    let training_config = TrainingConfig {
+        ..Default::default()
+    };
+
+    // Car 1 drives down into (2, 2), the cell car 2 is heading for, and is nearer the finish so it wins it
+    let run_tick = |car_2_q: [i32; 4]| {
+        let mut deps = setup_test_app();
+        let moves = [
+            (1u128, (2, 1), (1, 2), [0, 10, 0, 0]),
+            (2u128, (1, 2), (2, 1), car_2_q),
+        ];
+        for (car_id, (x, y), other, q_values) in moves {
+            let state_hash = generate_state_hash(&layout, x, y, 1, &[other], &[], &StateEncoding::Hashed, false);
+            set_q_values(&mut deps.storage, car_id, None, &state_hash, &StateEncoding::Hashed, q_values).unwrap();
+        }
+        let mut race_state = racing::race_engine::RaceState {
+            cars: vec![car_at(1, 2, 1, 1), car_at(2, 1, 2, 3)],
+            track_layout: layout.clone(),
+            tick: 0,
+            play_by_play: std::collections::HashMap::new(),
+            claimed_checkpoints: vec![],
+            state_encoding: StateEncoding::Hashed,
+            finish_tiles: vec![],
+            q_track: None,
+            distinguish_edges: false,
+            shuffle_order: false,
+        };
+        simulate_tick(&mut deps.storage, &mut race_state, training_config.clone(), 0, 10).unwrap();
+        race_state
+    };
+
+    // Car 2 turns up out of the way, or holds its heading and loses the cell to car 1
+    let evaded = run_tick([10, 0, 0, 0]);
+    assert_eq!((evaded.cars[1].x, evaded.cars[1].y), (1, 1));
+    assert_eq!(evaded.cars[0].evaded, vec![false]);
+    assert_eq!(evaded.cars[1].evaded, vec![true]);
+
+    let blocked = run_tick([0, 0, 0, 10]);
+    assert_eq!((blocked.cars[0].x, blocked.cars[0].y), (2, 2));
+    assert_eq!(blocked.cars[1].outcomes, vec![ActionOutcome::BlockedByCar]);
+    assert_eq!(blocked.cars[1].evaded, vec![false]);
+
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1u128, 2u128],
+        winner_ids: vec![],
+        rankings: vec![],
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+    let reward_config = RewardNumbers {
+        distance: 0,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0, 0, 0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: Some(25),
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+    let tile = layout[2][2].clone();
+    let evasion = |car: &racing::race_engine::CarState| {
+        calculate_reward_breakdown(car, &race_result, 0, tile.clone(), tile.clone(), 0, 1, reward_config.clone(), 10, None).unwrap().evasion
+    };
+    assert_eq!(evasion(&evaded.cars[1]), 25);
+    assert_eq!(evasion(&evaded.cars[0]), 0);
+    assert_eq!(evasion(&blocked.cars[1]), 0);
+}
+
+#[test]
+fn test_no_progress_penalties_are_exclusive() {
+    use crate::contract::{calculate_action_reward, generate_state_hash, simulate_tick};
+    use crate::state::set_q_values;
+    use racing::race_engine::ActionOutcome;
+
+    let reward_config = RewardNumbers {
+        distance: 1,
+        stuck: -5,
+        wall: -8,
+        no_move: -2,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0, 0, 0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1u128],
+        winner_ids: vec![],
+        rankings: vec![],
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+    let tile = create_test_track().layout[2][2].clone();
+
+    // Each no-progress cause costs exactly its own penalty
+    for (outcome, expected) in [
+        (ActionOutcome::HitWall, -8),
+        (ActionOutcome::Stuck, -5),
+        (ActionOutcome::NoMove, -2),
+        (ActionOutcome::Moved, 0),
+    ] {
+        let mut car = test_car_state(1);
+        car.outcomes = vec![outcome];
+        let reward = calculate_action_reward(&car, &race_result, 0, tile.clone(), tile.clone(), 0, 1, reward_config.clone(), 10, None, None).unwrap();
+        assert_eq!(reward, expected);
+    }
+
+    // Driving into the track edge is recorded as a wall hit, not a wasted move
+    let layout = create_test_track().layout;
+    let mut car = test_car_state(1);
+    car.x = 0;
+    car.y = 2;
+    car.tile = layout[2][0].clone();
+    let mut race_state = racing::race_engine::RaceState {
+        cars: vec![car],
+        track_layout: layout.clone(),
+        tick: 0,
+        play_by_play: std::collections::HashMap::new(),
+        claimed_checkpoints: vec![],
+        state_encoding: StateEncoding::Hashed,
+        finish_tiles: vec![],
+        q_track: None,
+        distinguish_edges: false,
+        shuffle_order: false,
+    };
+    let mut deps = setup_test_app();
+    let state_hash = generate_state_hash(&layout, 0, 2, 1, &[], &[], &StateEncoding::Hashed, false);
+    set_q_values(&mut deps.storage, 1, None, &state_hash, &StateEncoding::Hashed, [0, 0, 10, 0]).unwrap();
+    let training_config = TrainingConfig {
+        ..Default::default()
+    };
+    simulate_tick(&mut deps.storage, &mut race_state, training_config, 0, 10).unwrap();
+    let car = &race_state.cars[0];
+    assert_eq!((car.x, car.y), (0, 2));
+    assert_eq!(car.outcomes, vec![ActionOutcome::HitWall]);
+}
+
+#[test]
+fn test_mud_tile_stuns_for_stun_ticks() {
+    use crate::contract::{generate_state_hash, simulate_tick};
+    use crate::state::set_q_values;
+    use racing::race_engine::ActionOutcome;
+
+    // Mud pit right above the start, the car always prefers UP
+    let mut layout = create_test_track().layout;
+    layout[3][2].properties = TileProperties::mud(3);
+    let mut car = test_car_state(1);
+    car.x = 2;
+    car.y = 4;
+    car.tile = layout[4][2].clone();
+    let mut race_state = racing::race_engine::RaceState {
+        cars: vec![car],
+        track_layout: layout.clone(),
+        tick: 0,
+        play_by_play: std::collections::HashMap::new(),
+        claimed_checkpoints: vec![],
+        state_encoding: StateEncoding::Hashed,
+        finish_tiles: vec![],
+        q_track: None,
+        distinguish_edges: false,
+        shuffle_order: false,
+    };
+    let mut deps = setup_test_app();
+    for y in [4, 3] {
+        let state_hash = generate_state_hash(&layout, 2, y, 1, &[], &[], &StateEncoding::Hashed, false);
+        set_q_values(&mut deps.storage, 1, None, &state_hash, &StateEncoding::Hashed, [10, 0, 0, 0]).unwrap();
+    }
+    let training_config = TrainingConfig {
+        ..Default::default()
+    };
+
+    let mut positions = vec![];
+    for tick in 0..5 {
+        simulate_tick(&mut deps.storage, &mut race_state, training_config.clone(), tick, 10).unwrap();
+        positions.push((race_state.cars[0].x, race_state.cars[0].y));
+    }
+
+    // Enters the mud, idles three ticks, then drives on
+    assert_eq!(positions, vec![(2, 3), (2, 3), (2, 3), (2, 3), (2, 2)]);
+    let car = &race_state.cars[0];
+    assert_eq!(car.outcomes, vec![
+        ActionOutcome::Moved,
+        ActionOutcome::Stuck,
+        ActionOutcome::Stuck,
+        ActionOutcome::Stuck,
+        ActionOutcome::Moved,
+    ]);
+    assert!(!car.stuck);
+    assert_eq!(car.stuck_remaining, 0);
+}
+
+#[test]
+fn test_slow_tiles_halve_travel_speed() {
+    use crate::contract::{generate_state_hash, simulate_tick};
+    use crate::state::set_q_values;
+
+    // Straight run up column 2, the car always prefers UP
+    let drive = |slow: bool| {
+        let mut layout = create_test_track().layout;
+        if slow {
+            for y in 1..=3 {
+                layout[y][2].properties = TileProperties::slow(1);
+            }
+        }
+        let mut car = test_car_state(1);
+        car.x = 2;
+        car.y = 4;
+        car.tile = layout[4][2].clone();
+        let mut race_state = racing::race_engine::RaceState {
+            cars: vec![car],
+            track_layout: layout.clone(),
+            tick: 0,
+            play_by_play: std::collections::HashMap::new(),
+            claimed_checkpoints: vec![],
+            state_encoding: StateEncoding::Hashed,
+            finish_tiles: vec![],
+            q_track: None,
+            distinguish_edges: false,
+            shuffle_order: false,
+        };
+        let mut deps = setup_test_app();
+        for y in 1..=4 {
+            let state_hash = generate_state_hash(&layout, 2, y, 1, &[], &[], &StateEncoding::Hashed, false);
+            set_q_values(&mut deps.storage, 1, None, &state_hash, &StateEncoding::Hashed, [10, 0, 0, 0]).unwrap();
+        }
+        let training_config = TrainingConfig {
+            ..Default::default()
+        };
+        let mut positions = vec![];
+        let mut tick = 0;
+        while !race_state.cars[0].finished && tick < 10 {
+            simulate_tick(&mut deps.storage, &mut race_state, training_config.clone(), tick, 10).unwrap();
+            positions.push((race_state.cars[0].x, race_state.cars[0].y));
+            tick += 1;
+        }
+        positions
+    };
+
+    assert_eq!(drive(false), vec![(2, 3), (2, 2), (2, 1), (2, 0)]);
+    // Every slow tile costs a tick of waiting before the car moves on
+    assert_eq!(drive(true), vec![(2, 3), (2, 3), (2, 2), (2, 2), (2, 1), (2, 1), (2, 0)]);
+}
+
+#[test]
+fn test_action_masking_turns_car_out_of_dead_end() {
+    use crate::contract::{generate_state_hash, simulate_tick};
+    use crate::state::set_q_values;
+    use racing::race_engine::ActionOutcome;
+
+    // Start corner boxed in by the track edges and a wall, the only way out is UP
+    // - The car has learned to prefer DOWN, into the edge
+    let mut layout = create_test_track().layout;
+    layout[4][1].properties = TileProperties::wall();
+    let race = |enable_action_masking: bool, ticks: u32| {
+        let mut car = test_car_state(1);
+        car.x = 0;
+        car.y = 4;
+        car.tile = layout[4][0].clone();
+        let mut race_state = racing::race_engine::RaceState {
+            cars: vec![car],
+            track_layout: layout.clone(),
+            tick: 0,
+            play_by_play: std::collections::HashMap::new(),
+            claimed_checkpoints: vec![],
+            state_encoding: StateEncoding::Hashed,
+            finish_tiles: vec![],
+            q_track: None,
+            distinguish_edges: false,
+            shuffle_order: false,
+        };
+        let mut deps = setup_test_app();
+        let state_hash = generate_state_hash(&layout, 0, 4, 1, &[], &[], &StateEncoding::Hashed, false);
+        set_q_values(&mut deps.storage, 1, None, &state_hash, &StateEncoding::Hashed, [0, 10, 5, 5]).unwrap();
+        let training_config = TrainingConfig {
+            enable_action_masking: enable_action_masking,
+            ..Default::default()
+        };
+        for tick in 0..ticks {
+            simulate_tick(&mut deps.storage, &mut race_state, training_config.clone(), tick, 10).unwrap();
+        }
+        race_state.cars.remove(0)
+    };
+
+    // Unmasked, the car keeps bouncing off the edge
+    let car = race(false, 3);
+    assert_eq!((car.x, car.y), (0, 4));
+    assert_eq!(car.wall_hits, 3);
+    assert_eq!(car.outcomes, vec![ActionOutcome::HitWall; 3]);
+
+    // Masked, it takes the one open move
+    let car = race(true, 1);
+    assert_eq!((car.x, car.y), (0, 3));
+    assert_eq!(car.wall_hits, 0);
+    assert_eq!(car.outcomes, vec![ActionOutcome::Moved]);
+}
+
+#[test]
+fn test_teleporters_relocate_cars_instantly() {
+    use crate::contract::{generate_state_hash, simulate_tick};
+    use crate::state::set_q_values;
+
+    // Paired teleporters, the car drives UP from the start onto the first one
+    let mut layout = create_test_track().layout;
+    layout[3][2].properties = TileProperties::teleporter(4, 1);
+    layout[1][4].properties = TileProperties::teleporter(2, 3);
+    let run = |layout: Vec<Vec<TrackTile>>, ticks: u32| {
+        let mut car = test_car_state(1);
+        car.x = 2;
+        car.y = 4;
+        car.tile = layout[4][2].clone();
+        let mut race_state = racing::race_engine::RaceState {
+            cars: vec![car],
+            track_layout: layout.clone(),
+            tick: 0,
+            play_by_play: std::collections::HashMap::new(),
+            claimed_checkpoints: vec![],
+            state_encoding: StateEncoding::Hashed,
+            finish_tiles: vec![],
+            q_track: None,
+            distinguish_edges: false,
+            shuffle_order: false,
+        };
+        let mut deps = setup_test_app();
+        for (x, y) in [(2, 4), (4, 1), (2, 3)] {
+            let state_hash = generate_state_hash(&layout, x, y, 1, &[], &[], &StateEncoding::Hashed, false);
+            set_q_values(&mut deps.storage, 1, None, &state_hash, &StateEncoding::Hashed, [10, 0, 0, 0]).unwrap();
+        }
+        let training_config = TrainingConfig {
+            ..Default::default()
+        };
+        for tick in 0..ticks {
+            simulate_tick(&mut deps.storage, &mut race_state, training_config.clone(), tick, 10).unwrap();
+        }
+        race_state.cars.remove(0)
+    };
+
+    // Lands on the partner in the same tick without bouncing back
+    let car = run(layout.clone(), 1);
+    assert_eq!((car.x, car.y), (4, 1));
+    assert_eq!(car.tile.properties, TileProperties::teleporter(2, 3));
+
+    // The next action is taken from the partner tile
+    let car = run(layout.clone(), 2);
+    assert_eq!((car.x, car.y), (4, 0));
+    assert!(car.finished);
+    assert_eq!(car.action_history[1].0, generate_state_hash(&layout, 4, 1, 1, &[], &[], &StateEncoding::Hashed, false));
+
+    // A teleporter aimed at a wall leaves the car where it landed
+    let mut walled = layout.clone();
+    walled[1][4].properties = TileProperties::wall();
+    let car = run(walled, 1);
+    assert_eq!((car.x, car.y), (2, 3));
+}
+
+#[test]
+fn test_one_way_gate_bounces_reverse_moves() {
+    use crate::contract::calculate_new_position;
+    use racing::types::DIRECTION_UP;
+
+    // Gate in the middle of the track that can only be left heading UP
+    let mut layout = create_test_track().layout;
+    layout[2][2].properties = TileProperties::one_way(DIRECTION_UP);
+
+    // Entering from below is a normal move
+    assert_eq!(calculate_new_position(2, 3, 0, 1, &layout).unwrap(), (2, 2, false));
+    // Backing out bounces like a wall
+    assert_eq!(calculate_new_position(2, 2, 1, 1, &layout).unwrap(), (2, 2, true));
+    assert_eq!(calculate_new_position(2, 2, 2, 1, &layout).unwrap(), (2, 2, true));
+    // Carrying on forward is allowed
+    assert_eq!(calculate_new_position(2, 2, 0, 1, &layout).unwrap(), (2, 1, false));
+}
+
+#[test]
+fn test_packed_state_encoding_matches_hashed() {
+    use crate::state::Q_TABLE;
+
+    let train = |state_encoding: StateEncoding| {
+        let mut deps = setup_test_app_with_encoding(Some(state_encoding));
+        let info = mock_info(ADMIN, &[]);
+        for i in 0..3u64 {
+            let mut env = mock_env();
+            env.block.time = env.block.time.plus_seconds(i);
+            execute(deps.as_mut(), env, info.clone(), simulate_msg(vec![1u128, 2u128])).unwrap();
+        }
+        deps
+    };
+    let hashed = train(StateEncoding::Hashed);
+    let packed = train(StateEncoding::Packed);
+
+    // Same races
+    let races = |deps: cosmwasm_std::Deps| -> Vec<racing::race_engine::RaceResult> {
+        let query_msg = QueryMsg::ListRecentRaces {
+            car_id: None,
+            track_id: Some(1u128),
+            start_after: None,
+            limit: None,
+        };
+        let response = query(deps, mock_env(), query_msg).unwrap();
+        let races: racing::race_engine::RecentRacesResponse = from_json(response).unwrap();
+        races.races
+    };
+    assert_eq!(races(hashed.as_ref()), races(packed.as_ref()));
+
+    // Same learned values
+    let q_values = |deps: cosmwasm_std::Deps| -> Vec<[i32; 4]> {
+        let response = query(deps, mock_env(), QueryMsg::GetQ { car_id: 1u128, track_id: None, state_hash: None, start_after: None, limit: None }).unwrap();
+        let q: racing::race_engine::GetQResponse = from_json(response).unwrap();
+        let mut values: Vec<[i32; 4]> = q.q_values.iter().map(|entry| entry.action_values).collect();
+        values.sort();
+        values
+    };
+    assert_eq!(q_values(hashed.as_ref()), q_values(packed.as_ref()));
+
+    // Smaller keys
+    let key_lengths = |deps: cosmwasm_std::Deps| -> Vec<usize> {
+        Q_TABLE.prefix(1u128)
+            .keys_raw(deps.storage, None, None, cosmwasm_std::Order::Ascending)
+            .map(|key| key.len())
+            .collect()
+    };
+    assert!(key_lengths(hashed.as_ref()).iter().all(|len| *len == 32));
+    assert!(key_lengths(packed.as_ref()).iter().all(|len| *len == 4));
+
+    let size = |deps: cosmwasm_std::Deps| -> racing::race_engine::QTableSizeResponse {
+        from_json(query(deps, mock_env(), QueryMsg::GetQTableSize { car_id: 1u128, track_id: None }).unwrap()).unwrap()
+    };
+    let (hashed_size, packed_size) = (size(hashed.as_ref()), size(packed.as_ref()));
+    assert_eq!(hashed_size.entries, packed_size.entries);
+    assert_eq!(hashed_size.estimated_bytes, hashed_size.entries * 48);
+    assert_eq!(packed_size.estimated_bytes, packed_size.entries * 20);
+}
+
+#[test]
+fn test_state_hash_encodes_finish_direction() {
+    use crate::contract::generate_state_hash;
+
+    // Open 7x3 track with a finish tile at each end of the middle row
+    let mut layout = vec![vec![TrackTile {
+        properties: TileProperties::normal(),
+        progress_towards_finish: 0,
+        x: 0,
+        y: 0,
+    }; 7]; 3];
+    layout[1][0].properties = TileProperties::finish();
+    layout[1][6].properties = TileProperties::finish();
+    let finish_tiles = [(0, 1), (6, 1)];
+
+    // (2, 1) and (4, 1) look identical up close, but the nearest finish is on opposite sides
+    for encoding in [StateEncoding::Hashed, StateEncoding::Packed] {
+        let west = generate_state_hash(&layout, 2, 1, 1, &[], &finish_tiles, &encoding, false);
+        let east = generate_state_hash(&layout, 4, 1, 1, &[], &finish_tiles, &encoding, false);
+        assert_ne!(west, east);
+
+        // Without finish tiles they share a state
+        let west_blind = generate_state_hash(&layout, 2, 1, 1, &[], &[], &encoding, false);
+        let east_blind = generate_state_hash(&layout, 4, 1, 1, &[], &[], &encoding, false);
+        assert_eq!(west_blind, east_blind);
+    }
+}
+
+#[test]
+fn test_state_hash_encodes_speed() {
+    use crate::contract::generate_state_hash;
+    use racing::race_engine::{DEFAULT_BOOST_SPEED, DEFAULT_SPEED};
+
+    // Open 7x7 track, every probe from the centre lands on a normal tile at either speed
+    let layout = vec![vec![TrackTile {
+        properties: TileProperties::normal(),
+        progress_towards_finish: 0,
+        x: 0,
+        y: 0,
+    }; 7]; 7];
+
+    for encoding in [StateEncoding::Hashed, StateEncoding::Packed] {
+        let normal = generate_state_hash(&layout, 3, 3, DEFAULT_SPEED as u32, &[], &[], &encoding, false);
+        let boosted = generate_state_hash(&layout, 3, 3, DEFAULT_BOOST_SPEED as u32, &[], &[], &encoding, false);
+        assert_ne!(normal, boosted);
+    }
+}
+
+#[test]
+fn test_state_hash_tells_slowed_cars_apart() {
+    use crate::contract::{generate_state_hash, simulate_tick};
+    use racing::race_engine::DEFAULT_SPEED;
+
+    // Column 2 runs over a slow tile at y=3, the probes from (2, 3) land on normal tiles either way
+    let mut layout = create_test_track().layout;
+    let normal = generate_state_hash(&layout, 2, 3, DEFAULT_SPEED as u32, &[], &[], &StateEncoding::Hashed, false);
+    layout[3][2].properties = TileProperties::slow(2);
+    let slowed = generate_state_hash(&layout, 2, 3, DEFAULT_SPEED as u32, &[], &[], &StateEncoding::Hashed, false);
+    assert_ne!(normal, slowed);
+
+    // A car driving onto the slow tile keeps its speed but sits out its cooldown in the slow state
+    let mut car = test_car_state(1);
+    car.x = 2;
+    car.y = 4;
+    car.tile = layout[4][2].clone();
+    let mut race_state = racing::race_engine::RaceState {
+        cars: vec![car],
+        track_layout: layout.clone(),
+        tick: 0,
+        play_by_play: std::collections::HashMap::new(),
+        claimed_checkpoints: vec![],
+        state_encoding: StateEncoding::Hashed,
+        finish_tiles: vec![],
+        q_track: None,
+        distinguish_edges: false,
+        shuffle_order: false,
+    };
+    let mut deps = setup_test_app();
+    let start = generate_state_hash(&layout, 2, 4, DEFAULT_SPEED as u32, &[], &[], &StateEncoding::Hashed, false);
+    crate::state::set_q_values(&mut deps.storage, 1, None, &start, &StateEncoding::Hashed, [10, 0, 0, 0]).unwrap();
+    simulate_tick(&mut deps.storage, &mut race_state, TrainingConfig::default(), 0, 10).unwrap();
+
+    let car = &race_state.cars[0];
+    assert_eq!((car.x, car.y), (2, 3));
+    assert!(car.move_cooldown > 0);
+    assert_eq!(car.current_speed, DEFAULT_SPEED as u32);
+    let state = generate_state_hash(&layout, car.x, car.y, car.current_speed, &[], &[], &StateEncoding::Hashed, false);
+    assert_eq!(state, slowed);
+}
+
+#[test]
+fn test_state_hash_tells_edges_from_interior_walls() {
+    use crate::contract::generate_state_hash;
+
+    // Open 7x7 track with one interior wall above the centre
+    let mut layout = vec![vec![TrackTile {
+        properties: TileProperties::normal(),
+        progress_towards_finish: 0,
+        x: 0,
+        y: 0,
+    }; 7]; 7];
+    layout[2][3].properties = TileProperties::wall();
+
+    // (3, 0) probes off the top edge, (3, 3) into the wall, both see a wall up and open track elsewhere
+    for encoding in [StateEncoding::Hashed, StateEncoding::Packed] {
+        let edge = generate_state_hash(&layout, 3, 0, 1, &[], &[], &encoding, false);
+        let interior = generate_state_hash(&layout, 3, 3, 1, &[], &[], &encoding, false);
+        assert_eq!(edge, interior);
+
+        let edge = generate_state_hash(&layout, 3, 0, 1, &[], &[], &encoding, true);
+        let interior = generate_state_hash(&layout, 3, 3, 1, &[], &[], &encoding, true);
+        assert_ne!(edge, interior);
+    }
+
+    // Off, keys are the same as before the flag existed
+    let packed = generate_state_hash(&layout, 3, 3, 1, &[], &[], &StateEncoding::Packed, false);
+    assert_eq!(packed[3], 0);
+}
+
+#[test]
+fn test_track_cache_skips_repeat_track_queries() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    let mut deps = setup_test_app();
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+
+    // Count every track query the engine makes
+    let track_queries = Arc::new(AtomicU32::new(0));
+    let counter = track_queries.clone();
+    let track = create_test_track();
+    deps.querier.update_wasm(move |w| {
+        match w {
+            cosmwasm_std::WasmQuery::Smart { contract_addr, .. } if *contract_addr == TRACK_CONTRACT => {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(ContractResult::Ok(to_json_binary(&track).unwrap())).into()
+            }
+            _ => Ok(ContractResult::Err(cosmwasm_std::StdError::generic_err("Unknown query").to_string())).into(),
+        }
+    });
+
+    execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg(vec![1u128])).unwrap();
+    assert_eq!(track_queries.load(Ordering::SeqCst), 1);
+    execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg(vec![1u128])).unwrap();
+    assert_eq!(track_queries.load(Ordering::SeqCst), 1);
+
+    // Admin only
+    let invalidate_msg = ExecuteMsg::InvalidateTrackCache { track_id: cosmwasm_std::Uint128::from(1u128) };
+    let err = execute(deps.as_mut(), env.clone(), mock_info("stranger", &[]), invalidate_msg.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // Invalidating forces a fresh query
+    execute(deps.as_mut(), env.clone(), info.clone(), invalidate_msg).unwrap();
+    execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg(vec![1u128])).unwrap();
+    assert_eq!(track_queries.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_validate_track_rejects_malformed_layouts() {
+    use crate::contract::validate_track;
+
+    let reason = |track: &Track| match validate_track(track) {
+        Err(ContractError::InvalidTrack { reason }) => reason,
+        other => panic!("expected InvalidTrack, got {:?}", other),
+    };
+
+    assert!(validate_track(&create_test_track()).is_ok());
+
+    let mut empty = create_test_track();
+    empty.layout = vec![];
+    assert_eq!(reason(&empty), "layout is empty");
+    empty.layout = vec![vec![]];
+    assert_eq!(reason(&empty), "layout is empty");
+
+    let mut ragged = create_test_track();
+    ragged.layout[2].pop();
+    assert_eq!(reason(&ragged), "rows have different lengths");
+
+    let mut no_start = create_test_track();
+    for tile in no_start.layout.iter_mut().flatten() {
+        tile.properties.is_start = false;
+    }
+    assert_eq!(reason(&no_start), "no start tile");
+
+    let mut no_finish = create_test_track();
+    for tile in no_finish.layout.iter_mut().flatten() {
+        tile.properties.is_finish = false;
+    }
+    assert_eq!(reason(&no_finish), "no finish tile");
+}
+
+#[test]
+fn test_simulate_race_rejects_track_without_finish() {
+    let mut deps = setup_test_app();
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+
+    let mut track = create_test_track();
+    for tile in track.layout.iter_mut().flatten() {
+        tile.properties.is_finish = false;
+    }
+    deps.querier.update_wasm(move |w| {
+        match w {
+            cosmwasm_std::WasmQuery::Smart { contract_addr, .. } if *contract_addr == TRACK_CONTRACT => {
+                Ok(ContractResult::Ok(to_json_binary(&track).unwrap())).into()
+            }
+            _ => Ok(ContractResult::Err(cosmwasm_std::StdError::generic_err("Unknown query").to_string())).into(),
+        }
+    });
+
+    let err = execute(deps.as_mut(), env, info, simulate_msg(vec![1u128])).unwrap_err();
+    assert_eq!(err, ContractError::InvalidTrack { reason: "no finish tile".to_string() });
+}
+
+#[test]
+fn test_competitive_races_need_two_cars_unless_training() {
+    let mut deps = setup_test_app();
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+    let competitive_msg = |car_ids: Vec<u128>, training: bool| {
+        let mut msg = simulate_msg(car_ids);
+        if let ExecuteMsg::SimulateRace { train, competitive, .. } = &mut msg {
+            *train = training;
+            *competitive = Some(true);
+        }
+        msg
+    };
+
+    // Solo training is fine
+    execute(deps.as_mut(), env.clone(), info.clone(), competitive_msg(vec![1u128], true)).unwrap();
+
+    let err = execute(deps.as_mut(), env.clone(), info.clone(), competitive_msg(vec![1u128], false)).unwrap_err();
+    assert_eq!(err, ContractError::InvalidCarCount { expected: 2, actual: 1 });
+
+    execute(deps.as_mut(), env, info, competitive_msg(vec![1u128, 2u128], false)).unwrap();
+}
+
+#[test]
+fn test_get_best_action_matches_stored_argmax() {
+    let mut deps = setup_test_app();
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+
+    for _ in 0..3 {
+        execute(deps.as_mut(), env.clone(), info.clone(), simulate_msg(vec![1u128])).unwrap();
+    }
+
+    let response = query(deps.as_ref(), env.clone(), QueryMsg::GetQ { car_id: 1u128, track_id: None, state_hash: None, start_after: None, limit: None }).unwrap();
+    let q: racing::race_engine::GetQResponse = from_json(response).unwrap();
+    assert!(!q.q_values.is_empty());
+
+    for entry in &q.q_values {
+        let response = query(deps.as_ref(), env.clone(), QueryMsg::GetBestAction {
+            car_id: 1u128,
+            track_id: None,
+            state_hash: entry.state_hash,
+        }).unwrap();
+        let best: racing::race_engine::BestActionResponse = from_json(response).unwrap();
+
+        let expected = entry.action_values.iter().enumerate()
+            .max_by_key(|(_, &val)| val)
+            .map(|(idx, _)| idx);
+        assert_eq!(best.best_action, expected);
+        assert_eq!(best.action_values, entry.action_values);
+    }
+
+    // Unvisited state
+    let response = query(deps.as_ref(), env.clone(), QueryMsg::GetBestAction {
+        car_id: 1u128,
+        track_id: None,
+        state_hash: [0xAB; 32],
+    }).unwrap();
+    let best: racing::race_engine::BestActionResponse = from_json(response).unwrap();
+    assert_eq!(best.best_action, None);
+    assert_eq!(best.action_values, [0; 4]);
+}
+
+#[test]
+fn test_export_policy_pages_argmax_per_state() {
+    use racing::race_engine::{PolicyEntry, QTableExport};
+
+    let mut deps = setup_test_app();
+    for _ in 0..3 {
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), simulate_msg(vec![1u128])).unwrap();
+    }
+
+    // Walk the policy a few states at a time
+    let mut policy: Vec<PolicyEntry> = vec![];
+    loop {
+        let start_after = policy.last().map(|entry| entry.state_hash);
+        let query_msg = QueryMsg::ExportPolicy { car_id: 1, track_id: None, start_after, limit: Some(5) };
+        let page: Vec<PolicyEntry> = from_json(query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
+        if page.is_empty() {
+            break;
+        }
+        assert!(page.len() <= 5);
+        policy.extend(page);
+    }
+
+    // One row per Q-table entry, its last highest action like the greedy strategy picks
+    let export: QTableExport = from_json(query(deps.as_ref(), mock_env(), QueryMsg::ExportQTable { car_id: 1, track_id: None }).unwrap()).unwrap();
+    assert!(export.entries.len() > 5);
+    assert_eq!(policy.len(), export.entries.len());
+    for (row, entry) in policy.iter().zip(&export.entries) {
+        let max = *entry.action_values.iter().max().unwrap();
+        assert_eq!(row.state_hash, entry.state_hash);
+        assert_eq!(Some(row.best_action), entry.action_values.iter().rposition(|&value| value == max));
+    }
+}
+
+#[test]
+fn test_backward_penalty_outweighs_forward_step() {
+    use crate::contract::calculate_action_reward;
+
+    let reward_config = |backward_penalty_multiplier: Option<i32>| RewardNumbers {
+        distance: 1,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0, 0, 0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1u128],
+        winner_ids: vec![],
+        rankings: vec![],
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+    // Two neighbouring tiles one step of progress apart
+    let tile = |progress_towards_finish: u16| TrackTile {
+        properties: TileProperties::normal(),
+        progress_towards_finish,
+        x: 2,
+        y: 2,
+    };
+    let (near, far) = (tile(1), tile(2));
+
+    let mut car = test_car_state(1);
+    car.outcomes = vec![racing::race_engine::ActionOutcome::Moved];
+    let reward = |config: RewardNumbers, from: &TrackTile, to: &TrackTile| {
+        calculate_action_reward(&car, &race_result, 0, from.clone(), to.clone(), 0, 1, config, 10, None, None).unwrap()
+    };
+
+    // Without a multiplier retreating costs the plain distance penalty
+    assert_eq!(reward(reward_config(None), &far, &near), -1);
+
+    // With one it outweighs what the opposite step earns
+    let gained = reward(reward_config(Some(4)), &near, &far);
+    let lost = reward(reward_config(Some(4)), &far, &near);
+    assert_eq!(lost, -4);
+    assert!(gained > 0);
+    assert!(-lost > gained);
+}
+
+#[test]
+fn test_reward_time_decay_discounts_later_steps() {
+    use crate::contract::calculate_action_reward;
+
+    let reward_config = RewardNumbers {
+        distance: 100,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0, 0, 0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1u128],
+        winner_ids: vec![],
+        rankings: vec![],
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+    let tile = |progress_towards_finish: u16| TrackTile {
+        properties: TileProperties::normal(),
+        progress_towards_finish,
+        x: 2,
+        y: 2,
+    };
+
+    // The same step taken on the first and the tenth tick
+    let mut car = test_car_state(1);
+    car.outcomes = vec![racing::race_engine::ActionOutcome::Moved; 10];
+    let reward = |action_index: usize, reward_time_decay: Option<f32>| {
+        calculate_action_reward(&car, &race_result, 0, tile(1), tile(2), action_index, 10, reward_config.clone(), 10, None, reward_time_decay).unwrap()
+    };
+
+    let undecayed = reward(0, None);
+    assert_ne!(undecayed, 0);
+    assert_eq!(reward(9, None), undecayed);
+
+    assert_eq!(reward(0, Some(0.9)), undecayed);
+    assert_eq!(reward(9, Some(0.9)), (undecayed as f32 * 0.9f32.powi(9)) as i32);
+    assert!(reward(9, Some(0.9)).abs() < undecayed.abs());
+}
+
+#[test]
+fn test_reward_breakdown_tracks_components() {
+    use crate::contract::apply_q_learning_updates;
+    use crate::state::get_config;
+    use racing::race_engine::{ActionOutcome, RewardBreakdownResponse};
+    use cosmwasm_std::QuerierWrapper;
+
+    let mut deps = setup_test_app();
+    let env = mock_env();
+
+    // Nothing recorded before the car trains
+    let response = query(deps.as_ref(), env.clone(), QueryMsg::GetRewardBreakdown { car_id: 1u128 }).unwrap();
+    let breakdown: RewardBreakdownResponse = from_json(response).unwrap();
+    assert_eq!(breakdown.breakdown, None);
+
+    // A wall in front of the start line
+    let mut layout = create_test_track().layout;
+    layout[3][2] = TrackTile {
+        properties: TileProperties::wall(),
+        progress_towards_finish: 0,
+        x: 2,
+        y: 3,
+    };
+
+    // The car bumps the wall, steps around it and then finishes first
+    let mut car = test_car_state(1);
+    car.tile = layout[4][2].clone();
+    car.finished = true;
+    car.wall_hits = 1;
+    car.action_history = vec![
+        ([1; 32], 0, layout[4][2].clone()),
+        ([2; 32], 2, layout[4][1].clone()),
+        ([3; 32], 0, layout[0][1].clone()),
+    ];
+    car.outcomes = vec![ActionOutcome::HitWall, ActionOutcome::Moved, ActionOutcome::Moved];
+    car.drafting = vec![false; 3];
+    car.first_to_checkpoint = vec![false; 3];
+    let race_state = racing::race_engine::RaceState {
+        cars: vec![car],
+        track_layout: layout,
+        tick: 3,
+        play_by_play: std::collections::HashMap::new(),
+        claimed_checkpoints: vec![],
+        state_encoding: StateEncoding::Hashed,
+        finish_tiles: vec![],
+        q_track: None,
+        distinguish_edges: false,
+        shuffle_order: false,
+    };
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1u128],
+        winner_ids: vec![1u128],
+        rankings: vec![racing::race_engine::Rank { car_id: 1u128, rank: 1 }],
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+    let reward_config = RewardNumbers {
+        distance: 1,
+        stuck: -5,
+        wall: -8,
+        no_move: -2,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![50, 0, 0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+
+    let config = get_config(&deps.storage).unwrap();
+    apply_q_learning_updates(
+        &mut deps.storage,
+        &race_state,
+        &race_result,
+        reward_config,
+        config,
+        QuerierWrapper::new(&deps.querier),
+        10,
+        0.0,
+        None,
+    )
+    .unwrap();
+
+    let response = query(deps.as_ref(), env.clone(), QueryMsg::GetRewardBreakdown { car_id: 1u128 }).unwrap();
+    let breakdown = from_json::<RewardBreakdownResponse>(response).unwrap().breakdown.unwrap();
+    // One wall hit
+    assert_eq!(breakdown.wall, -8);
+    // The rank reward is credited on every action of a finished car
+    assert_eq!(breakdown.rank, 150);
+    assert_eq!(breakdown.stuck, 0);
+    assert!(breakdown.speed > 0);
+}
+
+#[test]
+fn test_elimination_race_ranks_by_elimination_order() {
+    let mut deps = setup_test_app();
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+
+    // Zero interval is rejected
+    let err = execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::SimulateEliminationRace {
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1u128, 2u128],
+        elimination_interval: 0,
+    }).unwrap_err();
+    assert_eq!(err, ContractError::InvalidRaceConfig);
+
+    // One elimination per tick, nobody can reach the finish in three ticks
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::SimulateEliminationRace {
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1u128, 2u128, 3u128, 4u128],
+        elimination_interval: 1,
+    }).unwrap();
+    assert!(res.attributes.iter().any(|attr| attr.key == "ticks" && attr.value == "3"));
+
+    let query_msg = QueryMsg::ListRecentRaces {
+        car_id: None,
+        track_id: Some(1u128),
+        start_after: None,
+        limit: None,
+    };
+    let response = query(deps.as_ref(), env.clone(), query_msg).unwrap();
+    let races: racing::race_engine::RecentRacesResponse = from_json(response).unwrap();
+    let race = races.races.last().unwrap();
+
+    let eliminations = race.eliminations.clone().unwrap();
+    assert_eq!(eliminations.len(), 3);
+    let survivor = *[1u128, 2u128, 3u128, 4u128].iter().find(|id| !eliminations.contains(id)).unwrap();
+
+    let mut expected = vec![survivor];
+    expected.extend(eliminations.iter().rev());
+    let ranked: Vec<u128> = race.rankings.iter().map(|rank| rank.car_id).collect();
+    assert_eq!(ranked, expected);
+    assert_eq!(race.winner_ids, vec![survivor]);
+}
+
+#[test]
+fn test_eliminated_cars_leave_the_track() {
+    use crate::contract::{generate_state_hash, resolve_collisions, simulate_tick};
+    use crate::state::set_q_values;
+
+    // Car 2 was knocked out right in front of car 1, which always prefers UP from an empty view
+    let layout = create_test_track().layout;
+    let mut survivor = test_car_state(1);
+    survivor.x = 2;
+    survivor.y = 4;
+    survivor.tile = layout[4][2].clone();
+    let mut eliminated = test_car_state(2);
+    eliminated.x = 2;
+    eliminated.y = 3;
+    eliminated.tile = layout[3][2].clone();
+    eliminated.stuck = true;
+    eliminated.eliminated = true;
+
+    // It no longer holds its cell
+    let cars = vec![survivor.clone(), eliminated.clone()];
+    assert_eq!(resolve_collisions(&cars, &[(2, 3), (2, 3)], None), vec![(2, 3), (2, 3)]);
+    let mut parked = cars.clone();
+    parked[1].eliminated = false;
+    assert_eq!(resolve_collisions(&parked, &[(2, 3), (2, 3)], None), vec![(2, 4), (2, 3)]);
+
+    // And the survivor's state doesn't see it
+    let mut race_state = racing::race_engine::RaceState {
+        cars,
+        track_layout: layout.clone(),
+        tick: 0,
+        play_by_play: std::collections::HashMap::new(),
+        claimed_checkpoints: vec![],
+        state_encoding: StateEncoding::Hashed,
+        finish_tiles: vec![],
+        q_track: None,
+        distinguish_edges: false,
+        shuffle_order: false,
+    };
+    let mut deps = setup_test_app();
+    let state_hash = generate_state_hash(&layout, 2, 4, 1, &[], &[], &StateEncoding::Hashed, false);
+    set_q_values(&mut deps.storage, 1, None, &state_hash, &StateEncoding::Hashed, [10, 0, 0, 0]).unwrap();
+    simulate_tick(&mut deps.storage, &mut race_state, TrainingConfig::default(), 0, 10).unwrap();
+
+    let car = &race_state.cars[0];
+    assert_eq!(car.action_history[0].0, state_hash);
+    assert_eq!((car.x, car.y), (2, 3));
+    assert_eq!((race_state.cars[1].x, race_state.cars[1].y), (2, 3));
+}
+
+#[test]
+fn test_team_results_follow_best_finisher() {
+    use crate::contract::{calculate_action_reward, calculate_team_results};
+    use racing::race_engine::Rank;
+
+    // Car 3 wins, so its team beats the team with the 2nd and 3rd place cars
+    let teams = vec![vec![1u128, 2u128], vec![3u128, 4u128]];
+    let rankings = vec![
+        Rank { car_id: 3, rank: 0 },
+        Rank { car_id: 1, rank: 1 },
+        Rank { car_id: 2, rank: 2 },
+        Rank { car_id: 4, rank: 3 },
+    ];
+    let team_results = calculate_team_results(&teams, &rankings);
+    assert_eq!(team_results, vec![(1, 0), (0, 1)]);
+
+    // Last place on the winning team gets the first place reward
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1, 2, 3, 4],
+        winner_ids: vec![3],
+        rankings,
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: Some(teams),
+        team_results: Some(team_results),
+    };
+    let reward_config = RewardNumbers {
+        distance: 0,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![50, 20, 0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: Some(0),
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+    let tile = create_test_track().layout[2][2].clone();
+    let reward = |car_id: u128| {
+        let mut car = test_car_state(car_id);
+        car.finished = true;
+        calculate_action_reward(&car, &race_result, 0, tile.clone(), tile.clone(), 0, 1, reward_config.clone(), 10, None, None).unwrap()
+    };
+    assert_eq!(reward(4), 50);
+    assert_eq!(reward(1), 20);
+}
+
+#[test]
+fn test_team_race_attaches_team_results() {
+    let mut deps = setup_test_app();
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+    let team_race = |teams: Vec<Vec<u128>>| {
+        let mut msg = race_msg(1u128, vec![1u128, 2u128, 3u128, 4u128], true, None);
+        if let ExecuteMsg::SimulateRace { teams: race_teams, .. } = &mut msg {
+            *race_teams = Some(teams);
+        }
+        msg
+    };
+
+    // Cars missing from or repeated across teams are rejected
+    let err = execute(deps.as_mut(), env.clone(), info.clone(), team_race(vec![vec![1, 2], vec![3]])).unwrap_err();
+    assert_eq!(err, ContractError::InvalidRaceConfig);
+    let err = execute(deps.as_mut(), env.clone(), info.clone(), team_race(vec![vec![1, 2], vec![2, 3, 4]])).unwrap_err();
+    assert_eq!(err, ContractError::InvalidRaceConfig);
+
+    execute(deps.as_mut(), env.clone(), info.clone(), team_race(vec![vec![1, 2], vec![3, 4]])).unwrap();
+    let query_msg = QueryMsg::ListRecentRaces {
+        car_id: None,
+        track_id: Some(1u128),
+        start_after: None,
+        limit: None,
+    };
+    let response = query(deps.as_ref(), env.clone(), query_msg).unwrap();
+    let races: racing::race_engine::RecentRacesResponse = from_json(response).unwrap();
+    let race = races.races.last().unwrap();
+
+    // The leading team is the one with the best ranked car
+    let team_results = race.team_results.clone().unwrap();
+    assert_eq!(team_results.len(), 2);
+    let leader = race.rankings[0].car_id;
+    let leading_team = &race.teams.as_ref().unwrap()[team_results[0].0 as usize];
+    assert!(leading_team.contains(&leader));
+    assert_eq!(team_results[0].1, 0);
+}
+
+#[test]
+fn test_margin_bonus_rewards_dominant_wins() {
+    use crate::contract::calculate_action_reward;
+    use racing::race_engine::Rank;
+
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1, 2],
+        winner_ids: vec![1],
+        rankings: vec![Rank { car_id: 1, rank: 0 }, Rank { car_id: 2, rank: 1 }],
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+    let reward_config = RewardNumbers {
+        distance: 0,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![50, 20, 0],
+            other: 0,
+            margin_bonus: Some(3),
+        },
+        max_speed_reward: Some(0),
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+    let tile = create_test_track().layout[2][2].clone();
+    let mut winner = test_car_state(1);
+    winner.finished = true;
+    winner.steps_taken = 5;
+    let reward = |car: &racing::race_engine::CarState, runner_up_steps: Option<u32>| {
+        calculate_action_reward(car, &race_result, 0, tile.clone(), tile.clone(), 0, 5, reward_config.clone(), 10, runner_up_steps, None).unwrap()
+    };
+
+    let dominant = reward(&winner, Some(15));
+    let photo_finish = reward(&winner, Some(6));
+    assert_eq!(dominant, 50 + 30);
+    assert_eq!(photo_finish, 50 + 3);
+    assert!(dominant > photo_finish);
+
+    // Capped, and only the winner earns it
+    assert_eq!(reward(&winner, Some(500)), 50 + 100);
+    let mut runner_up = test_car_state(2);
+    runner_up.finished = true;
+    runner_up.steps_taken = 15;
+    assert_eq!(reward(&runner_up, Some(15)), 20);
+}
+
+#[test]
+fn test_car_stats_summary_rolls_up_tracks() {
+    let mut deps = setup_test_app();
+    serve_two_tracks(&mut deps);
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+    let race_on = |track_id: u128, car_ids: Vec<u128>| race_msg(track_id, car_ids, true, None);
+    let summary = |deps: cosmwasm_std::Deps| -> racing::race_engine::CarStatsSummaryResponse {
+        let response = query(deps, mock_env(), QueryMsg::GetCarStatsSummary { car_id: 1u128 }).unwrap();
+        from_json(response).unwrap()
+    };
+
+    // Nothing raced yet
+    let empty = summary(deps.as_ref());
+    assert_eq!((empty.solo_races, empty.pvp_races, empty.win_rate, empty.fastest), (0, 0, 0, None));
+
+    // Solo on three tracks, plus one PvP race on the last
+    for track_id in [1u128, 2u128, 3u128] {
+        execute(deps.as_mut(), env.clone(), info.clone(), race_on(track_id, vec![1u128])).unwrap();
+    }
+    execute(deps.as_mut(), env.clone(), info.clone(), race_on(3, vec![1u128, 2u128])).unwrap();
+
+    let response = query(deps.as_ref(), env.clone(), QueryMsg::GetTrackTrainingStats {
+        car_id: 1u128,
+        track_id: None,
+        start_after: None,
+        limit: None,
+    }).unwrap();
+    let per_track: Vec<racing::race_engine::GetTrackTrainingStatsResponse> = from_json(response).unwrap();
+    assert_eq!(per_track.len(), 3);
+
+    let summary = summary(deps.as_ref());
+    assert_eq!(summary.solo_races, 3);
+    assert_eq!(summary.pvp_races, 1);
+
+    // Tracks with no PvP races keep the u32::MAX sentinel, which must not win the fold
+    let fastest = per_track.iter()
+        .flat_map(|row| [row.stats.solo.fastest, row.stats.pvp.fastest])
+        .min()
+        .unwrap();
+    assert_ne!(fastest, u32::MAX);
+    assert_eq!(summary.fastest, Some(fastest));
+
+    let wins: u32 = per_track.iter()
+        .map(|row| row.stats.solo.wins + row.stats.pvp.wins)
+        .sum();
+    assert_eq!(summary.win_rate, wins * 100 / 4);
+}
+
+#[test]
+fn test_win_rate_is_running_percentage() {
+    use crate::state::{set_track_training_stats, update_pvp_training_stats, update_solo_training_stats};
+
+    let mut deps = setup_test_app();
+
+    // A solo car that always finishes always wins
+    for completion_time in [30, 20, 25] {
+        let stats = update_solo_training_stats(&mut deps.storage, 1, 1, true, true, completion_time).unwrap();
+        assert_eq!(stats.solo.win_rate, 100);
+    }
+    let stats = update_solo_training_stats(&mut deps.storage, 1, 1, true, true, 40).unwrap();
+    assert_eq!((stats.solo.tally, stats.solo.wins, stats.solo.win_rate, stats.solo.fastest), (4, 4, 100, 20));
+
+    // Mixed PvP results
+    let mut win_rates = vec![];
+    for won in [true, false, false, true, false] {
+        let stats = update_pvp_training_stats(&mut deps.storage, 1, 1, won, true, 50).unwrap();
+        win_rates.push(stats.pvp.win_rate);
+    }
+    assert_eq!(win_rates, vec![100, 50, 33, 50, 40]);
+    let stats = update_pvp_training_stats(&mut deps.storage, 1, 1, true, true, 50).unwrap();
+    assert_eq!((stats.pvp.tally, stats.pvp.wins, stats.pvp.win_rate), (6, 3, 50));
+    // Solo stats untouched
+    assert_eq!(stats.solo.tally, 4);
+
+    // Rows saved without a win count pick it up from the stored rate
+    let mut legacy = stats.clone();
+    legacy.pvp = racing::types::TrainingStats {
+        tally: 4,
+        wins: 0,
+        win_rate: 75,
+        fastest: 50,
+    };
+    set_track_training_stats(&mut deps.storage, 1, 2, legacy).unwrap();
+    let stats = update_pvp_training_stats(&mut deps.storage, 1, 2, false, true, 50).unwrap();
+    assert_eq!((stats.pvp.tally, stats.pvp.wins, stats.pvp.win_rate), (5, 3, 60));
+
+    // A run that timed out counts but doesn't set the fastest time
+    let stats = update_pvp_training_stats(&mut deps.storage, 1, 2, false, false, 10).unwrap();
+    assert_eq!((stats.pvp.tally, stats.pvp.fastest), (6, 50));
+}
+
+#[test]
+fn test_steps_flag_unfinished_cars() {
+    use crate::contract::calculate_results;
+
+    let track = create_test_track();
+    let mut finisher = test_car_state(1);
+    finisher.finished = true;
+    finisher.steps_taken = 40;
+    let mut stuck = test_car_state(2);
+    stuck.stuck = true;
+    stuck.steps_taken = 40;
+
+    let (_, _, steps_taken) = calculate_results(&[finisher, stuck], &track.layout, &RankingMode::FinishedFirst);
+    let step = |car_id: u128| steps_taken.iter().find(|step| step.car_id == car_id).unwrap().clone();
+    assert_eq!((step(1).steps_taken, step(1).finished), (40, true));
+    assert_eq!((step(2).steps_taken, step(2).finished), (40, false));
+
+    // Results stored before the flag existed still load
+    let json = String::from_utf8(to_json_binary(&step(1)).unwrap().to_vec()).unwrap();
+    let legacy_json = json.replace(",\"finished\":true", "");
+    assert_ne!(json, legacy_json);
+    let legacy: racing::race_engine::Step = from_json(legacy_json.as_bytes()).unwrap();
+    assert!(!legacy.finished);
+}
+
+#[test]
+fn test_chunked_q_updates_match_single_pass() {
+    use crate::contract::{apply_batched_q_updates, QUpdateTuple};
+    use crate::state::{get_config, Q_TABLE};
+    use cosmwasm_std::QuerierWrapper;
+
+    // Long episode over a small pool of states so chunks keep revisiting earlier states
+    let updates: Vec<QUpdateTuple> = (0..300u32)
+        .map(|i| {
+            let state = [(i * 7 % 13) as u8; 32];
+            let next = [((i + 1) * 7 % 13) as u8; 32];
+            let reward = if i % 5 == 0 { 20 } else { -3 };
+            (state, (i % 4) as u8, reward, (i < 299).then_some(next))
+        })
+        .collect();
+
+    let q_table_after = |batch_size: usize, lambda: f32| -> Vec<(Vec<u8>, [i32; 4])> {
+        let mut deps = setup_test_app();
+        let config = get_config(&deps.storage).unwrap();
+        let car = test_car_state(1);
+        apply_batched_q_updates(
+            &mut deps.storage,
+            &car,
+            None,
+            updates.chunks(batch_size).map(|chunk| chunk.to_vec()),
+            config,
+            QuerierWrapper::new(&deps.querier),
+            lambda,
+        )
+        .unwrap();
+        Q_TABLE.prefix(1u128)
+            .range(&deps.storage, None, None, cosmwasm_std::Order::Ascending)
+            .map(|item| item.unwrap())
+            .collect()
+    };
+
+    for lambda in [0.0, 0.8] {
+        let single_pass = q_table_after(updates.len(), lambda);
+        assert_eq!(single_pass.len(), 13);
+        for batch_size in [1, 7, 32] {
+            assert_eq!(q_table_after(batch_size, lambda), single_pass);
+        }
+    }
+}
+
+#[test]
+fn test_reset_training_stats() {
+    let mut deps = setup_test_app();
+    serve_two_tracks(&mut deps);
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+    let race_on = |track_id: u128| race_msg(track_id, vec![1u128], true, None);
+    let stats_on = |deps: cosmwasm_std::Deps, track_id: u128| -> racing::types::TrackTrainingStats {
+        let response = query(deps, mock_env(), QueryMsg::GetTrackTrainingStats {
+            car_id: 1u128,
+            track_id: Some(track_id),
+            start_after: None,
+            limit: None,
+        }).unwrap();
+        let stats: Vec<GetTrackTrainingStatsResponse> = from_json(response).unwrap();
+        stats[0].stats.clone()
+    };
+    let reset = |track_id: Option<u128>| ExecuteMsg::ResetTrainingStats {
+        car_id: cosmwasm_std::Uint128::from(1u128),
+        track_id,
+    };
+
+    execute(deps.as_mut(), env.clone(), info.clone(), race_on(1)).unwrap();
+    execute(deps.as_mut(), env.clone(), info.clone(), race_on(2)).unwrap();
+    assert_eq!(stats_on(deps.as_ref(), 1).solo.tally, 1);
+    assert_eq!(stats_on(deps.as_ref(), 2).solo.tally, 1);
+
+    // Admin only
+    let err = execute(deps.as_mut(), env.clone(), mock_info("stranger", &[]), reset(None)).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // One track
+    execute(deps.as_mut(), env.clone(), info.clone(), reset(Some(1))).unwrap();
+    let cleared = stats_on(deps.as_ref(), 1);
+    assert_eq!((cleared.solo.tally, cleared.solo.win_rate, cleared.solo.fastest), (0, 0, u32::MAX));
+    assert_eq!(stats_on(deps.as_ref(), 2).solo.tally, 1);
+
+    // Every track
+    execute(deps.as_mut(), env.clone(), info.clone(), race_on(1)).unwrap();
+    execute(deps.as_mut(), env.clone(), info.clone(), reset(None)).unwrap();
+    for track_id in [1u128, 2u128] {
+        let cleared = stats_on(deps.as_ref(), track_id);
+        assert_eq!((cleared.solo.tally, cleared.solo.fastest), (0, u32::MAX));
+    }
+}
+
+#[test]
+fn test_distance_biased_init_finishes_faster_than_random() {
+    use racing::race_engine::{QInitStrategy, ResponseDetail, SimulateRaceResponse};
+
+    // Served the standard test track, progress climbs row by row from the start to the finish
+    let total_ticks = |init_strategy: QInitStrategy| -> u32 {
+        let mut deps = setup_test_app();
+
+        // First race for each car, nothing learned yet
+        (1..=8u128).map(|car_id| {
+            let mut msg = race_msg(1u128, vec![car_id], false, Some(TrainingConfig {
+                init_strategy: Some(init_strategy.clone()),
+                ..Default::default()
+            }));
+            if let ExecuteMsg::SimulateRace { max_ticks, response_detail, .. } = &mut msg {
+                *max_ticks = Some(20);
+                *response_detail = Some(ResponseDetail::Full);
+            }
+            let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+            let data: SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+            let step = data.steps_taken.unwrap()[0].clone();
+            if step.finished { step.steps_taken } else { 20 }
+        }).sum()
+    };
+
+    // Straight up the track, 4 ticks per car
+    let biased = total_ticks(QInitStrategy::DistanceBiased);
+    assert_eq!(biased, 8 * 4);
+    assert!(biased < total_ticks(QInitStrategy::Random));
+}
+
+#[test]
+fn test_zero_init_learns_the_same_for_every_car() {
+    use racing::race_engine::{QInitStrategy, QTableExport};
+
+    // Three greedy training races, alone on a fresh contract
+    let learned = |init_strategy: QInitStrategy, car_id: u128| -> Vec<racing::types::QTableEntry> {
+        let mut deps = setup_test_app();
+        for _ in 0..3 {
+            let mut msg = simulate_msg(vec![car_id]);
+            if let ExecuteMsg::SimulateRace { training_config, .. } = &mut msg {
+                *training_config = Some(TrainingConfig {
+                    init_strategy: Some(init_strategy.clone()),
+                    ..Default::default()
+                });
+            }
+            execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        }
+        let export: QTableExport = from_json(query(deps.as_ref(), mock_env(), QueryMsg::ExportQTable { car_id, track_id: None }).unwrap()).unwrap();
+        export.entries
+    };
+
+    // Random init seeds unseen states from the car id, so two cars on the same track learn different tables
+    assert_ne!(learned(QInitStrategy::Random, 1), learned(QInitStrategy::Random, 2));
+
+    // Zero init leaves nothing car specific, every run and every car ends up with the same table
+    let zero = learned(QInitStrategy::Zero, 1);
+    assert!(!zero.is_empty());
+    assert_eq!(learned(QInitStrategy::Zero, 1), zero);
+    assert_eq!(learned(QInitStrategy::Zero, 2), zero);
+}
+
+#[test]
+fn test_engine_stats_count_races_and_ticks() {
+    use racing::race_engine::{EngineStatsResponse, QTableSizeResponse};
+
+    let mut deps = setup_test_app();
+    let stats = |deps: cosmwasm_std::Deps| -> EngineStatsResponse {
+        from_json(query(deps, mock_env(), QueryMsg::GetEngineStats {}).unwrap()).unwrap()
+    };
+    assert_eq!(stats(deps.as_ref()), EngineStatsResponse {
+        total_races: 0,
+        total_cars: 0,
+        total_q_entries: 0,
+        total_ticks: 0,
+        average_ticks: 0,
+    });
+
+    let mut ticks = 0u64;
+    for car_ids in [vec![1u128, 2], vec![2, 3], vec![1]] {
+        let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), simulate_msg(car_ids)).unwrap();
+        let attribute = res.attributes.iter().find(|attr| attr.key == "ticks").unwrap();
+        ticks += attribute.value.parse::<u64>().unwrap();
+    }
+
+    let q_entries: u64 = (1..=3u128)
+        .map(|car_id| {
+            let size: QTableSizeResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetQTableSize { car_id, track_id: None }).unwrap()).unwrap();
+            size.entries
+        })
+        .sum();
+    assert_eq!(stats(deps.as_ref()), EngineStatsResponse {
+        total_races: 3,
+        total_cars: 3,
+        total_q_entries: q_entries,
+        total_ticks: ticks,
+        average_ticks: ticks / 3,
+    });
+
+    // Cars that only ran an elimination race count too, and a reset takes the car's entries off
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), ExecuteMsg::SimulateEliminationRace {
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![3u128, 4u128, 5u128],
+        elimination_interval: 1,
+    }).unwrap();
+    let size: QTableSizeResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetQTableSize { car_id: 1, track_id: None }).unwrap()).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), ExecuteMsg::ResetQ { car_id: cosmwasm_std::Uint128::from(1u128) }).unwrap();
+    let after = stats(deps.as_ref());
+    assert_eq!(after.total_cars, 5);
+    assert_eq!(after.total_q_entries, q_entries - size.entries);
+}
+
+#[test]
+fn test_failing_car_explores_more_than_a_winning_one() {
+    use crate::contract::exploration_epsilon;
+    use crate::state::{get_failed_races, update_solo_training_stats};
+    use racing::race_engine::RewardBreakdownResponse;
+
+    // Car 1 has never finished track 1, car 2 always has
+    let mut deps = setup_test_app();
+    for _ in 0..4 {
+        update_solo_training_stats(&mut deps.storage, 1, 1, false, false, 100).unwrap();
+        update_solo_training_stats(&mut deps.storage, 2, 1, true, true, 8).unwrap();
+    }
+    assert_eq!(get_failed_races(&deps.storage, 1, 1).unwrap(), 4);
+    assert_eq!(get_failed_races(&deps.storage, 2, 1).unwrap(), 0);
+    assert_eq!(get_failed_races(&deps.storage, 1, 2).unwrap(), 0);
+
+    let training_config = TrainingConfig {
+        training_mode: true,
+        epsilon: 0.1,
+        failure_epsilon_step: Some(0.05),
+        ..Default::default()
+    };
+    assert!((exploration_epsilon(&training_config, 4) - 0.3).abs() < 1e-6);
+    assert_eq!(exploration_epsilon(&training_config, 0), 0.1);
+    assert_eq!(exploration_epsilon(&training_config, 100), 1.0);
+
+    // A one tick race nobody finishes, only the car that keeps failing is frustrated
+    let reward_config = RewardNumbers {
+        distance: 0,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0, 0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: Some(-7),
+        new_best_bonus: None,
+    };
+    let mut msg = simulate_msg(vec![1u128, 2u128]);
+    if let ExecuteMsg::SimulateRace { training_config: config, reward_config: rewards, max_ticks, .. } = &mut msg {
+        *config = Some(training_config);
+        *rewards = Some(reward_config);
+        *max_ticks = Some(1);
+    }
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    let frustration = |car_id: u128| {
+        let response = query(deps.as_ref(), mock_env(), QueryMsg::GetRewardBreakdown { car_id }).unwrap();
+        from_json::<RewardBreakdownResponse>(response).unwrap().breakdown.unwrap().frustration
+    };
+    assert_eq!(frustration(1), -7);
+    assert_eq!(frustration(2), 0);
+}
+
+#[test]
+fn test_new_best_progress_bonus_only_for_beating_the_record() {
+    use crate::contract::generate_state_hash;
+    use crate::state::{set_q_values, BEST_PROGRESS};
+    use racing::race_engine::RewardBreakdownResponse;
+
+    // Served the standard test track, progress climbs row by row from the start to the finish
+    let layout = create_test_track().layout;
+    let mut deps = setup_test_app();
+
+    // The car drives straight up from (0, 4)
+    let finish_tiles: Vec<(i32, i32)> = (0..5).map(|x| (x, 0)).collect();
+    for y in [4, 3] {
+        let state_hash = generate_state_hash(&layout, 0, y, 1, &[], &finish_tiles, &StateEncoding::Hashed, false);
+        set_q_values(&mut deps.storage, 1, None, &state_hash, &StateEncoding::Hashed, [50, 0, 0, 0]).unwrap();
+    }
+
+    let mut race = |ticks: u32| -> (i32, u16) {
+        let mut msg = simulate_msg(vec![1u128]);
+        if let ExecuteMsg::SimulateRace { training_config, reward_config, max_ticks, .. } = &mut msg {
+            if let Some(training_config) = training_config.as_mut() {
+                training_config.training_mode = false;
+            }
+            *reward_config = Some(RewardNumbers {
+                distance: 1,
+                stuck: 0,
+                wall: 0,
+                no_move: 0,
+                explore: 0,
+                rank: racing::types::RankReward {
+                    ranks: vec![0],
+                    other: 0,
+                    margin_bonus: None,
+                },
+                max_speed_reward: None,
+                draft_bonus: None,
+                checkpoint_bonus: None,
+                backward_penalty_multiplier: None,
+                collision_penalty: None,
+                reward_min: None,
+                reward_max: None,
+                comeback_bonus: None,
+                path_efficiency_bonus: None,
+                target_ticks: None,
+                overtime_penalty_per_tick: None,
+                evasion_bonus: None,
+                tile_variety_bonus: None,
+                frustration_penalty: None,
+                new_best_bonus: Some(15),
+            });
+            *max_ticks = Some(ticks);
+        }
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        let response = query(deps.as_ref(), mock_env(), QueryMsg::GetRewardBreakdown { car_id: 1 }).unwrap();
+        let new_best = from_json::<RewardBreakdownResponse>(response).unwrap().breakdown.unwrap().new_best;
+        (new_best, BEST_PROGRESS.load(&deps.storage, (1, 1)).unwrap())
+    };
+
+    // The first run only sets the record
+    assert_eq!(race(1), (0, 1));
+    // Getting a row further without finishing beats it
+    assert_eq!(race(2), (15, 2));
+    // A shorter run falls short and keeps the record
+    assert_eq!(race(1), (0, 2));
+}
+
+#[test]
+fn test_get_car_tracks() {
+    let mut deps = setup_test_app();
+    serve_two_tracks(&mut deps);
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+    let car_tracks = |deps: cosmwasm_std::Deps, car_id: u128| -> Vec<u128> {
+        from_json(query(deps, mock_env(), QueryMsg::GetCarTracks { car_id }).unwrap()).unwrap()
+    };
+    assert!(car_tracks(deps.as_ref(), 1).is_empty());
+
+    for track_id in [9u128, 1u128, 5u128, 9u128] {
+        execute(deps.as_mut(), env.clone(), info.clone(), race_msg(track_id, vec![1u128], true, None)).unwrap();
+    }
+
+    assert_eq!(car_tracks(deps.as_ref(), 1), vec![1, 5, 9]);
+    assert!(car_tracks(deps.as_ref(), 2).is_empty());
+}
+
+#[test]
+fn test_end_on_first_finish_stops_race() {
+    use racing::race_engine::{QInitStrategy, ResponseDetail, SimulateRaceResponse};
+
+    // Distance-graded track with a wall in front of the second start tile
+    let mut track = create_test_track();
+    track.layout[3][1].properties = TileProperties::wall();
+
+    let sprint = |end_on_first_finish: Option<bool>| -> (cosmwasm_std::Response, SimulateRaceResponse) {
+        let mut deps = setup_test_app();
+        let served = track.clone();
+        deps.querier.update_wasm(move |w| {
+            match w {
+                cosmwasm_std::WasmQuery::Smart { contract_addr, .. } if *contract_addr == TRACK_CONTRACT => {
+                    Ok(ContractResult::Ok(to_json_binary(&served).unwrap())).into()
+                }
+                _ => Ok(ContractResult::Err(cosmwasm_std::StdError::generic_err("Unknown query").to_string())).into(),
+            }
+        });
+        // Car 1 drives straight up, car 2 has to go around the wall first
+        let mut msg = race_msg(1u128, vec![1u128, 2u128], false, Some(TrainingConfig {
+            init_strategy: Some(QInitStrategy::DistanceBiased),
+            ..Default::default()
+        }));
+        if let ExecuteMsg::SimulateRace { response_detail, end_on_first_finish: end_early, .. } = &mut msg {
+            *response_detail = Some(ResponseDetail::Full);
+            *end_early = end_on_first_finish;
+        }
+        let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        let data: SimulateRaceResponse = from_json(res.data.clone().unwrap()).unwrap();
+        (res, data)
+    };
+    let ticks = |res: &cosmwasm_std::Response| -> u32 {
+        res.attributes.iter().find(|attr| attr.key == "ticks").unwrap().value.parse().unwrap()
+    };
+
+    let (res, data) = sprint(Some(true));
+    assert_eq!(ticks(&res), 4);
+    assert_eq!(data.winner_ids, vec![1u128]);
+    let ranked: Vec<u128> = data.rankings.iter().map(|rank| rank.car_id).collect();
+    assert_eq!(ranked, vec![1u128, 2u128]);
+    let steps = data.steps_taken.unwrap();
+    let car_two = steps.iter().find(|step| step.car_id == 2).unwrap();
+    assert!(!car_two.finished);
+    assert_eq!(car_two.steps_taken, 4);
+
+    // By default the race runs on past the first finisher
+    let (res, _) = sprint(None);
+    assert!(ticks(&res) > 4);
+}
+
+#[test]
+fn test_get_q_pages_without_overlap() {
+    use crate::state::set_q_values;
+
+    let mut deps = setup_test_app();
+    for i in 0..70u8 {
+        set_q_values(&mut deps.storage, 1, None, &[i; 32], &StateEncoding::Hashed, [i as i32, 0, 0, 0]).unwrap();
+    }
+
+    // Unbounded requests are capped
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetQ { car_id: 1u128, track_id: None, state_hash: None, start_after: None, limit: Some(1000) }).unwrap();
+    let q: racing::race_engine::GetQResponse = from_json(response).unwrap();
+    assert_eq!(q.q_values.len(), 32);
+
+    let mut seen = vec![];
+    let mut start_after = None;
+    loop {
+        let response = query(deps.as_ref(), mock_env(), QueryMsg::GetQ { car_id: 1u128, track_id: None, state_hash: None, start_after, limit: Some(25) }).unwrap();
+        let q: racing::race_engine::GetQResponse = from_json(response).unwrap();
+        if q.q_values.is_empty() {
+            break;
+        }
+        assert!(q.q_values.len() <= 25);
+        start_after = q.q_values.last().map(|entry| entry.state_hash);
+        seen.extend(q.q_values.into_iter().map(|entry| entry.state_hash));
+    }
+
+    let expected: Vec<[u8; 32]> = (0..70u8).map(|i| [i; 32]).collect();
+    assert_eq!(seen, expected);
+}
+
+#[test]
+fn test_verify_replay_matches_recorded_race() {
+    use crate::contract::play_by_play_hash;
+
+    let mut deps = setup_test_app();
+    let info = mock_info(ADMIN, &[]);
+
+    // Some learned values so the recorded race reads stored Q-values
+    for i in 0..2u64 {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(i);
+        execute(deps.as_mut(), env, info.clone(), simulate_msg(vec![1u128, 2u128])).unwrap();
+    }
+
+    let mut recorded = simulate_msg(vec![1u128, 2u128]);
+    if let ExecuteMsg::SimulateRace { response_detail, record_replay, .. } = &mut recorded {
+        *response_detail = Some(racing::race_engine::ResponseDetail::Full);
+        *record_replay = Some(true);
+    }
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(10);
+    let res = execute(deps.as_mut(), env, info.clone(), recorded).unwrap();
+    let data: racing::race_engine::SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+    let expected_hash = play_by_play_hash(&data.play_by_play.unwrap()).unwrap();
+
+    // Training after the race doesn't change the replay
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(20);
+    execute(deps.as_mut(), env, info.clone(), simulate_msg(vec![1u128, 2u128])).unwrap();
+
+    let verify = |expected_hash: [u8; 32]| -> racing::race_engine::VerifyReplayResponse {
+        let msg = QueryMsg::VerifyReplay { race_id: data.race_id.clone(), expected_hash };
+        from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+    };
+    let response = verify(expected_hash);
+    assert!(response.valid);
+    assert_eq!(response.replay_hash, expected_hash);
+
+    let mut tampered = expected_hash;
+    tampered[0] ^= 1;
+    let response = verify(tampered);
+    assert!(!response.valid);
+    assert_eq!(response.replay_hash, expected_hash);
+
+    // Races run without record_replay can't be verified
+    let msg = QueryMsg::VerifyReplay { race_id: "race_1_0".to_string(), expected_hash };
+    assert!(query(deps.as_ref(), mock_env(), msg).is_err());
+}
+
+#[test]
+fn test_replays_are_kept_per_race_until_it_drops_out() {
+    use crate::contract::play_by_play_hash;
+    use crate::state::{MAX_TRACK_RECENT_RACES, RECENT_RACE_RESULTS};
+
+    let mut deps = setup_test_app();
+    let info = mock_info(ADMIN, &[]);
+    let mut recorded = simulate_msg(vec![1u128, 2u128]);
+    if let ExecuteMsg::SimulateRace { response_detail, record_replay, .. } = &mut recorded {
+        *response_detail = Some(racing::race_engine::ResponseDetail::Full);
+        *record_replay = Some(true);
+    }
+
+    // Two recorded races in the same block, the second doesn't replace the first's replay
+    let races: Vec<(String, [u8; 32])> = (0..2).map(|_| {
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), recorded.clone()).unwrap();
+        let data: racing::race_engine::SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+        (data.race_id, play_by_play_hash(&data.play_by_play.unwrap()).unwrap())
+    }).collect();
+    assert_ne!(races[0].0, races[1].0);
+    let verify = |deps: &OwnedDeps<_, _, _>, (race_id, expected_hash): &(String, [u8; 32])| {
+        let msg = QueryMsg::VerifyReplay { race_id: race_id.clone(), expected_hash: *expected_hash };
+        query(deps.as_ref(), mock_env(), msg).map(|res| from_json::<racing::race_engine::VerifyReplayResponse>(res).unwrap().valid)
+    };
+    for race in &races {
+        assert_eq!(verify(&deps, race), Ok(true));
+    }
+
+    // Once a race falls off every recent race list its replay goes with it
+    for _ in 0..MAX_TRACK_RECENT_RACES {
+        execute(deps.as_mut(), mock_env(), info.clone(), simulate_msg(vec![1u128, 2u128])).unwrap();
+    }
+    for race in &races {
+        assert!(verify(&deps, race).is_err());
+    }
+    let replays = RECENT_RACE_RESULTS
+        .range(&deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .filter(|item| item.as_ref().unwrap().1.replay.is_some())
+        .count();
+    assert_eq!(replays, 0);
+}
+
+#[test]
+fn test_verify_replay_restores_failed_races() {
+    use crate::contract::play_by_play_hash;
+    use crate::state::update_solo_training_stats;
+
+    // Car 1 has never won track 1, so its epsilon is raised all the way to 1
+    let mut deps = setup_test_app();
+    for _ in 0..10 {
+        update_solo_training_stats(&mut deps.storage, 1, 1, false, false, 100).unwrap();
+    }
+
+    let mut msg = race_msg(1u128, vec![1u128, 2u128], true, Some(TrainingConfig {
+        training_mode: true,
+        failure_epsilon_step: Some(0.1),
+        ..Default::default()
+    }));
+    if let ExecuteMsg::SimulateRace { response_detail, record_replay, .. } = &mut msg {
+        *response_detail = Some(racing::race_engine::ResponseDetail::Full);
+        *record_replay = Some(true);
+    }
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+    let data: racing::race_engine::SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+    let expected_hash = play_by_play_hash(&data.play_by_play.unwrap()).unwrap();
+
+    // The inputs carry the failures that boosted the epsilon
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetRaceInputs { race_id: data.race_id.clone() }).unwrap();
+    let inputs: racing::race_engine::RaceInputs = from_json(response).unwrap();
+    assert_eq!(inputs.failed_races, vec![(1u128, 10u32), (2u128, 0u32)]);
+
+    // The replay explores as much as the race did, not at the plain epsilon of 0
+    let msg = QueryMsg::VerifyReplay { race_id: data.race_id, expected_hash };
+    let response: racing::race_engine::VerifyReplayResponse = from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert!(response.valid);
+}
+
+#[test]
+fn test_race_inputs_reproduce_the_race() {
+    use racing::race_engine::{RaceInputs, RecentRacesResponse};
+
+    let mut deps = setup_test_app();
+    let info = mock_info(ADMIN, &[]);
+
+    // An exploring race that doesn't train, so the Q-tables are the same for the re-run
+    let mut msg = simulate_msg(vec![1u128, 2u128, 3u128]);
+    if let ExecuteMsg::SimulateRace { train, max_ticks, teams, shuffle_order, .. } = &mut msg {
+        *train = false;
+        *max_ticks = Some(12);
+        *teams = Some(vec![vec![1u128, 3u128], vec![2u128]]);
+        *shuffle_order = Some(true);
+    }
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let data: racing::race_engine::SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetRaceInputs { race_id: data.race_id }).unwrap();
+    let inputs: RaceInputs = from_json(response).unwrap();
+    assert_eq!(inputs.car_ids, vec![1u128, 2u128, 3u128]);
+    assert!(!inputs.train);
+    assert_eq!(inputs.max_ticks, 12);
+    assert!(inputs.shuffle_order);
+    // Defaults come back filled in
+    assert_eq!(inputs.reward_config.distance, 1);
+
+    let mut rerun = race_msg(inputs.track_id.u128(), inputs.car_ids, inputs.train, Some(inputs.training_config));
+    if let ExecuteMsg::SimulateRace { reward_config, max_ticks, teams, end_on_first_finish, ranking_mode, shuffle_order, .. } = &mut rerun {
+        *reward_config = Some(inputs.reward_config);
+        *max_ticks = Some(inputs.max_ticks);
+        *teams = inputs.teams;
+        *end_on_first_finish = Some(inputs.end_on_first_finish);
+        *ranking_mode = Some(inputs.ranking_mode);
+        *shuffle_order = Some(inputs.shuffle_order);
+    }
+    execute(deps.as_mut(), mock_env(), info, rerun).unwrap();
+
+    let query_msg = QueryMsg::ListRecentRaces { car_id: None, track_id: Some(1u128), start_after: None, limit: None };
+    let mut races: RecentRacesResponse = from_json(query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
+    assert_eq!(races.races.len(), 2);
+    // Same race under its own race_id
+    assert_ne!(races.races[0].race_id, races.races[1].race_id);
+    races.races[1].race_id = races.races[0].race_id.clone();
+    assert_eq!(races.races[0], races.races[1]);
+
+    // Unknown races have no inputs
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetRaceInputs { race_id: "race_1_1".to_string() });
+    assert!(response.is_err());
+}
+
+#[test]
+fn test_collision_penalty_teaches_car_to_stop_ramming() {
+    use crate::contract::{apply_q_learning_updates, generate_state_hash, simulate_tick};
+    use crate::state::{get_config, get_q_values, set_q_values};
+    use cosmwasm_std::QuerierWrapper;
+    use racing::race_engine::ActionOutcome;
+
+    // Car 2 is parked right in front of car 1, which prefers driving UP into it over going RIGHT
+    let layout = create_test_track().layout;
+    let mut rammer = test_car_state(1);
+    rammer.x = 2;
+    rammer.y = 3;
+    rammer.tile = layout[3][2].clone();
+    let mut parked = test_car_state(2);
+    parked.x = 2;
+    parked.y = 2;
+    parked.tile = layout[2][2].clone();
+    parked.stuck = true;
+    let mut race_state = racing::race_engine::RaceState {
+        cars: vec![rammer, parked],
+        track_layout: layout.clone(),
+        tick: 0,
+        play_by_play: std::collections::HashMap::new(),
+        claimed_checkpoints: vec![],
+        state_encoding: StateEncoding::Hashed,
+        finish_tiles: vec![],
+        q_track: None,
+        distinguish_edges: false,
+        shuffle_order: false,
+    };
+    let mut deps = setup_test_app();
+    let state_hash = generate_state_hash(&layout, 2, 3, 1, &[(2, 2)], &[], &StateEncoding::Hashed, false);
+    set_q_values(&mut deps.storage, 1, None, &state_hash, &StateEncoding::Hashed, [10, 0, 0, 8]).unwrap();
+    let training_config = TrainingConfig {
+        ..Default::default()
+    };
+    for tick in 0..5 {
+        simulate_tick(&mut deps.storage, &mut race_state, training_config.clone(), tick, 10).unwrap();
+        race_state.tick = tick + 1;
+    }
+
+    // Every ram is recorded against the UP it tried
+    let rammer = &race_state.cars[0];
+    assert_eq!((rammer.x, rammer.y), (2, 3));
+    assert_eq!(rammer.outcomes, vec![ActionOutcome::BlockedByCar; 5]);
+    assert!(rammer.action_history.iter().all(|(hash, action, _)| *hash == state_hash && *action == 0));
+
+    let reward_config = RewardNumbers {
+        distance: 0,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0, 0, 0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: Some(-20),
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1u128, 2u128],
+        winner_ids: vec![],
+        rankings: vec![
+            racing::race_engine::Rank { car_id: 1, rank: 0 },
+            racing::race_engine::Rank { car_id: 2, rank: 1 },
+        ],
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+    let config = get_config(&deps.storage).unwrap();
+    apply_q_learning_updates(&mut deps.storage, &race_state, &race_result, reward_config, config, QuerierWrapper::new(&deps.querier), 0, 0.0, None).unwrap();
+
+    // Ramming now looks worse than driving around
+    let q_values = get_q_values(&deps.storage, 1, None, &state_hash, &StateEncoding::Hashed).unwrap();
+    assert!(q_values[0] < q_values[3], "{:?}", q_values);
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetRewardBreakdown { car_id: 1u128 }).unwrap();
+    let breakdown: racing::race_engine::RewardBreakdownResponse = from_json(response).unwrap();
+    assert_eq!(breakdown.breakdown.unwrap().collision, -100);
+}
+
+#[test]
+fn test_reward_clamp_applies_before_q_update() {
+    use crate::contract::{apply_q_learning_updates, calculate_action_reward, generate_state_hash};
+    use crate::state::{get_config, get_q_values, set_q_values};
+    use cosmwasm_std::QuerierWrapper;
+
+    let reward_config = |reward_max: Option<i32>| RewardNumbers {
+        distance: 1000,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0, 0, 0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: Some(-50),
+        reward_max,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1u128],
+        winner_ids: vec![],
+        rankings: vec![racing::race_engine::Rank { car_id: 1, rank: 0 }],
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+
+    // One step of progress onto a tile with progress 2 is worth 1000 * (1 + 2) before clamping
+    let layout = create_test_track().layout;
+    let tile = |progress_towards_finish: u16| TrackTile {
+        properties: TileProperties::normal(),
+        progress_towards_finish,
+        x: 2,
+        y: 2,
+    };
+    let state_hash = generate_state_hash(&layout, 2, 3, 1, &[], &[], &StateEncoding::Hashed, false);
+    let mut car = test_car_state(1);
+    car.tile = tile(1);
+    car.outcomes = vec![racing::race_engine::ActionOutcome::Moved];
+    car.action_history = vec![(state_hash, 0, tile(2))];
+
+    let reward = |reward_max: Option<i32>| {
+        calculate_action_reward(&car, &race_result, 0, tile(1), tile(2), 0, 1, reward_config(reward_max), 10, None, None).unwrap()
+    };
+    assert_eq!(reward(None), 3000);
+    assert_eq!(reward(Some(50)), 50);
+    // The floor applies the other way
+    assert_eq!(calculate_action_reward(&car, &race_result, 0, tile(2), tile(1), 0, 1, reward_config(None), 10, None, None).unwrap(), -50);
+
+    // The Q update only sees the clamped reward: 0 + 0.1 * 50
+    let learned = |reward_max: Option<i32>| {
+        let mut deps = setup_test_app();
+        set_q_values(&mut deps.storage, 1, None, &state_hash, &StateEncoding::Hashed, [0, 0, 0, 0]).unwrap();
+        let race_state = racing::race_engine::RaceState {
+            cars: vec![car.clone()],
+            track_layout: layout.clone(),
+            tick: 1,
+            play_by_play: std::collections::HashMap::new(),
+            claimed_checkpoints: vec![],
+            state_encoding: StateEncoding::Hashed,
+            finish_tiles: vec![],
+            q_track: None,
+            distinguish_edges: false,
+            shuffle_order: false,
+        };
+        let config = get_config(&deps.storage).unwrap();
+        apply_q_learning_updates(&mut deps.storage, &race_state, &race_result, reward_config(reward_max), config, QuerierWrapper::new(&deps.querier), 0, 0.0, None).unwrap();
+        get_q_values(&deps.storage, 1, None, &state_hash, &StateEncoding::Hashed).unwrap()[0]
+    };
+    assert_eq!(learned(Some(50)), 5);
+    // Unclamped, the single step pins the Q-value to its ceiling
+    assert_eq!(learned(None), 100);
+}
+
+#[test]
+fn test_export_import_q_table_round_trip() {
+    let mut deps = setup_test_app();
+    let info = mock_info(ADMIN, &[]);
+    for i in 0..3u64 {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(i);
+        execute(deps.as_mut(), env, info.clone(), simulate_msg(vec![1u128])).unwrap();
+    }
+
+    let export_of = |deps: cosmwasm_std::Deps| -> racing::race_engine::QTableExport {
+        from_json(query(deps, mock_env(), QueryMsg::ExportQTable { car_id: 1u128, track_id: None }).unwrap()).unwrap()
+    };
+    let export = export_of(deps.as_ref());
+    assert_eq!(export.version, racing::race_engine::Q_TABLE_EXPORT_VERSION);
+    assert!(!export.entries.is_empty());
+
+    execute(deps.as_mut(), mock_env(), info.clone(), ExecuteMsg::ResetQ { car_id: cosmwasm_std::Uint128::from(1u128) }).unwrap();
+    assert!(export_of(deps.as_ref()).entries.is_empty());
+
+    // Admin only, and only the current format
+    let import = |export: racing::race_engine::QTableExport| ExecuteMsg::ImportQTable {
+        car_id: cosmwasm_std::Uint128::from(1u128),
+        track_id: None,
+        export,
+    };
+    let err = execute(deps.as_mut(), mock_env(), mock_info("someone", &[]), import(export.clone())).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let mut stale = export.clone();
+    stale.version += 1;
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), import(stale)).unwrap_err();
+    assert!(matches!(err, ContractError::UnsupportedQTableExport { .. }));
+
+    let res = execute(deps.as_mut(), mock_env(), info, import(export.clone())).unwrap();
+    assert!(res.attributes.iter().any(|attr| attr.key == "entries" && attr.value == export.entries.len().to_string()));
+    assert_eq!(export_of(deps.as_ref()), export);
+}
+
+#[test]
+fn test_rank_rewards_cover_every_position() {
+    use crate::contract::calculate_reward_breakdown;
+
+    let car_ids: Vec<u128> = (1..=8).collect();
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: car_ids.clone(),
+        winner_ids: vec![],
+        rankings: car_ids.iter()
+            .enumerate()
+            .map(|(rank, car_id)| racing::race_engine::Rank { car_id: *car_id, rank: rank as u32 })
+            .collect(),
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+    let reward_config = |ranks: Vec<i32>| RewardNumbers {
+        distance: 0,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks,
+            other: -1,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+    let tile = create_test_track().layout[2][2].clone();
+    let rank_reward = |car_id: u128, ranks: Vec<i32>| {
+        let mut car = test_car_state(car_id);
+        car.finished = true;
+        calculate_reward_breakdown(&car, &race_result, 0, tile.clone(), tile.clone(), 0, 1, reward_config(ranks), 0, None)
+            .unwrap()
+            .rank
+    };
+
+    let ranks = vec![80, 70, 60, 50, 40, 30, 20, 10];
+    for (position, car_id) in car_ids.iter().enumerate() {
+        assert_eq!(rank_reward(*car_id, ranks.clone()), ranks[position]);
+    }
+
+    // Positions past the table fall back to other
+    let short = vec![80, 70, 60];
+    assert_eq!(rank_reward(3, short.clone()), 60);
+    assert_eq!(rank_reward(4, short.clone()), -1);
+    assert_eq!(rank_reward(8, short), -1);
+}
+
+#[test]
+fn test_ghost_race_beats_slower_stored_run() {
+    use crate::contract::generate_state_hash;
+    use crate::state::{set_q_values, GHOSTS};
+
+    let mut deps = setup_test_app();
+    let info = mock_info(ADMIN, &[]);
+    let layout = create_test_track().layout;
+    let finish_tiles: Vec<(i32, i32)> = (0..5).map(|x| (x, 0)).collect();
+    let steer = |storage: &mut dyn cosmwasm_std::Storage, x: i32, y: i32, q_values: [i32; 4]| {
+        let state_hash = generate_state_hash(&layout, x, y, 1, &[], &finish_tiles, &StateEncoding::Hashed, false);
+        set_q_values(storage, 1, None, &state_hash, &StateEncoding::Hashed, q_values).unwrap();
+    };
+    let ghost_race = ExecuteMsg::SimulateGhostRace { track_id: cosmwasm_std::Uint128::from(1u128), car_id: 1 };
+
+    // Nothing to race against yet
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), ghost_race.clone()).unwrap_err();
+    assert_eq!(err, ContractError::GhostNotFound { car_id: 1, track_id: 1 });
+
+    // First run detours one column right before heading up: 5 steps
+    steer(&mut deps.storage, 0, 4, [0, 0, 0, 10]);
+    for y in 1..=4 {
+        steer(&mut deps.storage, 1, y, [10, 0, 0, 0]);
+    }
+    let mut first_run = simulate_msg(vec![1u128]);
+    if let ExecuteMsg::SimulateRace { train, training_config, .. } = &mut first_run {
+        *train = false;
+        *training_config = Some(TrainingConfig {
+            ..Default::default()
+        });
+    }
+    execute(deps.as_mut(), mock_env(), info.clone(), first_run).unwrap();
+    assert_eq!(GHOSTS.load(&deps.storage, (1, 1)).unwrap().steps, 5);
+
+    // Second run drives straight up: 4 steps
+    steer(&mut deps.storage, 0, 4, [10, 0, 0, 0]);
+    for y in 1..=3 {
+        steer(&mut deps.storage, 0, y, [10, 0, 0, 0]);
+    }
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(1);
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), ghost_race.clone()).unwrap();
+    let data: racing::race_engine::GhostRaceResponse = from_json(res.data.unwrap()).unwrap();
+    assert_eq!(data.steps, Some(4));
+    assert_eq!(data.ghost_steps, 5);
+    assert!(data.beat_ghost);
+    assert_eq!(data.ghost.actions.len(), 5);
+    assert_eq!(data.play_by_play.actions.len(), 4);
+
+    // The faster run is the new ghost, matching it isn't a win
+    let ghost = GHOSTS.load(&deps.storage, (1, 1)).unwrap();
+    assert_eq!((ghost.steps, ghost.race_id), (4, data.race_id));
+    let res = execute(deps.as_mut(), env, info, ghost_race).unwrap();
+    let data: racing::race_engine::GhostRaceResponse = from_json(res.data.unwrap()).unwrap();
+    assert_eq!(data.ghost_steps, 4);
+    assert!(!data.beat_ghost);
+}
+
+mod integration {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{from_json, to_json_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128};
+    use cw_multi_test::{App, AppResponse, Contract, ContractWrapper, Executor};
+    use cw_storage_plus::Item;
+
+    use super::{create_test_track, race_msg, simulate_msg, ADMIN, CAR_CONTRACT};
+    use crate::contract::{execute, generate_state_hash, instantiate, query, reply};
+    use racing::car::{ExecuteMsg as CarExecuteMsg, GetCarInfoResponse, InstantiateMsg as CarInstantiateMsg, QueryMsg as CarQueryMsg};
+    use racing::race_engine::{
+        ExecuteMsg, InstantiateMsg, QInitStrategy, QTableExport, QueryMsg, ResponseDetail, RewardBreakdownResponse, SimulateRaceResponse,
+        StateEncoding, TrainingConfig, Q_TABLE_EXPORT_VERSION,
+    };
+    use racing::track_manager::{ExecuteMsg as TrackExecuteMsg, InstantiateMsg as TrackInstantiateMsg, QueryMsg as TrackQueryMsg};
+    use racing::types::{QTableEntry, RankReward, RewardNumbers, TileProperties, Track};
+
+    // Track manager stand-in serving the test track with a settable record
+    const FASTEST_TICK_TIME: Item<u64> = Item::new("fastest_tick_time");
+
+    #[cw_serde]
+    struct MockTrackInstantiateMsg {
+        fastest_tick_time: u64,
+    }
+
+    fn mock_instantiate(deps: DepsMut, _env: Env, _info: MessageInfo, msg: MockTrackInstantiateMsg) -> StdResult<Response> {
+        FASTEST_TICK_TIME.save(deps.storage, &msg.fastest_tick_time)?;
+        Ok(Response::new())
+    }
+
+    fn mock_execute(deps: DepsMut, _env: Env, _info: MessageInfo, msg: TrackExecuteMsg) -> StdResult<Response> {
+        match msg {
+            TrackExecuteMsg::UpdateFastestTime { fastest_tick_time, .. } => {
+                FASTEST_TICK_TIME.save(deps.storage, &fastest_tick_time)?;
+                Ok(Response::new().add_attribute("method", "update_fastest_time"))
+            },
+            _ => Err(StdError::generic_err("not supported by the mock")),
+        }
+    }
+
+    fn mock_query(deps: Deps, _env: Env, msg: TrackQueryMsg) -> StdResult<Binary> {
+        match msg {
+            TrackQueryMsg::GetTrack { .. } => {
+                let mut track = create_test_track();
+                track.fastest_tick_time = FASTEST_TICK_TIME.load(deps.storage)?;
+                to_json_binary(&track)
+            },
+            _ => Err(StdError::generic_err("not supported by the mock")),
+        }
+    }
+
+    fn race_engine_contract() -> Box<dyn Contract<cosmwasm_std::Empty>> {
+        Box::new(ContractWrapper::new(execute, instantiate, query).with_reply(reply))
+    }
+
+    fn mock_track_manager_contract() -> Box<dyn Contract<cosmwasm_std::Empty>> {
+        Box::new(ContractWrapper::new(mock_execute, mock_instantiate, mock_query))
+    }
+
+    fn reported_record(res: &AppResponse) -> bool {
+        res.events.iter().any(|event| {
+            event.ty == "wasm" && event.attributes.iter().any(|attr| attr.key == "method" && attr.value == "update_fastest_time")
+        })
+    }
+
+    #[test]
+    fn test_report_records_updates_track_manager_only_on_a_new_record() {
+        let mut app = App::default();
+        let admin = Addr::unchecked(ADMIN);
+        let track_code = app.store_code(mock_track_manager_contract());
+        let track_manager = app
+            .instantiate_contract(track_code, admin.clone(), &MockTrackInstantiateMsg { fastest_tick_time: 10 }, &[], "track_manager", None)
+            .unwrap();
+        let engine_code = app.store_code(race_engine_contract());
+        let engine = app
+            .instantiate_contract(
+                engine_code,
+                admin.clone(),
+                &InstantiateMsg {
+                    admin: ADMIN.to_string(),
+                    track_contract: track_manager.to_string(),
+                    car_contract: CAR_CONTRACT.to_string(),
+                    state_encoding: None,
+                    q_update_batch_size: None,
+                    per_track_q: None,
+                    distinguish_edges: None,
+                },
+                &[],
+                "race_engine",
+                None,
+            )
+            .unwrap();
+
+        // Car 1 drives straight up from (0,4), finishing in 4 steps
+        let layout = create_test_track().layout;
+        let finish_tiles: Vec<(i32, i32)> = (0..5).map(|x| (x, 0)).collect();
+        let entries = (1..=4)
+            .map(|y| QTableEntry {
+                state_hash: generate_state_hash(&layout, 0, y, 1, &[], &finish_tiles, &StateEncoding::Hashed, false),
+                action_values: [10, 0, 0, 0],
+            })
+            .collect();
+        let export = QTableExport { car_id: 1, version: Q_TABLE_EXPORT_VERSION, state_encoding: StateEncoding::Hashed, entries };
+        app.execute_contract(admin.clone(), engine.clone(), &ExecuteMsg::ImportQTable { car_id: Uint128::from(1u128), track_id: None, export }, &[])
+            .unwrap();
+
+        let race = |report_records: bool| {
+            let mut msg = simulate_msg(vec![1u128]);
+            if let ExecuteMsg::SimulateRace { train, training_config, report_records: report, .. } = &mut msg {
+                *train = false;
+                if let Some(training_config) = training_config {
+                    training_config.training_mode = false;
+                    training_config.epsilon = 0.0;
+                }
+                *report = Some(report_records);
+            }
+            msg
+        };
+        let fastest = |app: &App| app.wrap().query_wasm_smart::<Track>(&track_manager, &TrackQueryMsg::GetTrack { track_id: Uint128::from(1u128) }).unwrap().fastest_tick_time;
+
+        // Beats the record, but reporting is off
+        let res = app.execute_contract(admin.clone(), engine.clone(), &race(false), &[]).unwrap();
+        assert!(!reported_record(&res));
+        assert_eq!(fastest(&app), 10);
+
+        // Beats the record and reports it
+        let res = app.execute_contract(admin.clone(), engine.clone(), &race(true), &[]).unwrap();
+        assert!(reported_record(&res));
+        assert_eq!(fastest(&app), 4);
+
+        // Only matches the new record, nothing to report
+        let res = app.execute_contract(admin, engine, &race(true), &[]).unwrap();
+        assert!(!reported_record(&res));
+        assert_eq!(fastest(&app), 4);
+    }
+
+    fn car_contract() -> Box<dyn Contract<cosmwasm_std::Empty>> {
+        Box::new(ContractWrapper::new(car::contract::execute, car::contract::instantiate, car::contract::query))
+    }
+
+    #[test]
+    fn test_races_record_outcomes_in_the_car_contract() {
+        let mut app = App::default();
+        let admin = Addr::unchecked(ADMIN);
+        let track_code = app.store_code(mock_track_manager_contract());
+        let track_manager = app
+            .instantiate_contract(track_code, admin.clone(), &MockTrackInstantiateMsg { fastest_tick_time: 10 }, &[], "track_manager", None)
+            .unwrap();
+        let car_code = app.store_code(car_contract());
+        let car = app
+            .instantiate_contract(
+                car_code,
+                admin.clone(),
+                &CarInstantiateMsg { name: "Membrane Cars".to_string(), symbol: "CAR".to_string(), payment_options: None },
+                &[],
+                "car",
+                None,
+            )
+            .unwrap();
+        // Cars 0 and 1
+        for _ in 0..2 {
+            let mint = CarExecuteMsg::MintCar { owner: "alice".to_string(), token_uri: None, extension: None };
+            app.execute_contract(Addr::unchecked("alice"), car.clone(), &mint, &[]).unwrap();
+        }
+        let engine_code = app.store_code(race_engine_contract());
+        let engine = app
+            .instantiate_contract(
+                engine_code,
+                admin.clone(),
+                &InstantiateMsg {
+                    admin: ADMIN.to_string(),
+                    track_contract: track_manager.to_string(),
+                    car_contract: car.to_string(),
+                    state_encoding: None,
+                    q_update_batch_size: None,
+                    per_track_q: None,
+                    distinguish_edges: None,
+                },
+                &[],
+                "race_engine",
+                None,
+            )
+            .unwrap();
+        let allow_engine = CarExecuteMsg::UpdateConfig { payment_options: None, new_owner: None, race_engine: Some(engine.to_string()) };
+        app.execute_contract(admin.clone(), car.clone(), &allow_engine, &[]).unwrap();
+
+        // Car 2 was never minted, its outcome is skipped and the race still goes through
+        let res = app.execute_contract(admin, engine, &simulate_msg(vec![0u128, 1u128, 2u128]), &[]).unwrap();
+        let data: SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+        let skipped = res.events.iter().filter(|event| {
+            event.ty == "wasm" && event.attributes.iter().any(|attr| attr.key == "method" && attr.value == "record_race_outcome_skipped")
+        }).count();
+        assert_eq!(skipped, 1);
+
+        for car_id in [0u128, 1u128] {
+            let info: GetCarInfoResponse = app.wrap().query_wasm_smart(&car, &CarQueryMsg::GetCarInfo { car_id: car_id.to_string() }).unwrap();
+            assert_eq!(info.total_races, 1);
+            assert_eq!(info.wins, data.winner_ids.contains(&car_id) as u32);
+        }
+    }
+
+    fn track_manager_contract() -> Box<dyn Contract<cosmwasm_std::Empty>> {
+        Box::new(ContractWrapper::new(
+            track_manager::contract::execute,
+            track_manager::contract::instantiate,
+            track_manager::contract::query,
+        ))
+    }
+
+    #[test]
+    fn test_engine_reads_track_manager_progress_towards_the_finish() {
+        let mut app = App::default();
+        let admin = Addr::unchecked(ADMIN);
+        let track_code = app.store_code(track_manager_contract());
+        let track_manager = app
+            .instantiate_contract(track_code, admin.clone(), &TrackInstantiateMsg { admin: ADMIN.to_string() }, &[], "track_manager", None)
+            .unwrap();
+
+        // Finish row on top, start row at the bottom
+        let layout: Vec<Vec<TileProperties>> = (0..5)
+            .map(|y| {
+                let tile = match y {
+                    0 => TileProperties::finish(),
+                    4 => TileProperties::start(),
+                    _ => TileProperties::normal(),
+                };
+                vec![tile; 5]
+            })
+            .collect();
+        let add_track = TrackExecuteMsg::AddTrack { name: "straight".to_string(), width: 5, height: 5, layout };
+        app.execute_contract(admin.clone(), track_manager.clone(), &add_track, &[]).unwrap();
+        let track: Track = app.wrap().query_wasm_smart(&track_manager, &TrackQueryMsg::GetTrack { track_id: Uint128::zero() }).unwrap();
+        let progress: Vec<u16> = track.layout.iter().map(|row| row[0].progress_towards_finish).collect();
+        assert_eq!(progress, vec![4, 3, 2, 1, 0]);
+
+        let engine_code = app.store_code(race_engine_contract());
+        let engine = app
+            .instantiate_contract(
+                engine_code,
+                admin.clone(),
+                &InstantiateMsg {
+                    admin: ADMIN.to_string(),
+                    track_contract: track_manager.to_string(),
+                    car_contract: CAR_CONTRACT.to_string(),
+                    state_encoding: None,
+                    q_update_batch_size: None,
+                    per_track_q: None,
+                    distinguish_edges: None,
+                },
+                &[],
+                "race_engine",
+                None,
+            )
+            .unwrap();
+
+        // DistanceBiased heads for the highest-progress neighbour, the new-best bonus needs progress to grow
+        let race = |app: &mut App, ticks: u32| -> (bool, i32) {
+            let mut msg = race_msg(0, vec![1u128], true, Some(TrainingConfig {
+                init_strategy: Some(QInitStrategy::DistanceBiased),
+                ..Default::default()
+            }));
+            if let ExecuteMsg::SimulateRace { reward_config, max_ticks, response_detail, .. } = &mut msg {
+                *reward_config = Some(RewardNumbers {
+                    distance: 1,
+                    stuck: 0,
+                    wall: 0,
+                    no_move: 0,
+                    explore: 0,
+                    rank: RankReward {
+                        ranks: vec![0],
+                        other: 0,
+                        margin_bonus: None,
+                    },
+                    max_speed_reward: None,
+                    draft_bonus: None,
+                    checkpoint_bonus: None,
+                    backward_penalty_multiplier: None,
+                    collision_penalty: None,
+                    reward_min: None,
+                    reward_max: None,
+                    comeback_bonus: None,
+                    path_efficiency_bonus: None,
+                    target_ticks: None,
+                    overtime_penalty_per_tick: None,
+                    evasion_bonus: None,
+                    tile_variety_bonus: None,
+                    frustration_penalty: None,
+                    new_best_bonus: Some(15),
+                });
+                *max_ticks = Some(ticks);
+                *response_detail = Some(ResponseDetail::Full);
+            }
+            let res = app.execute_contract(Addr::unchecked(ADMIN), engine.clone(), &msg, &[]).unwrap();
+            let data: SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+            let breakdown: RewardBreakdownResponse = app.wrap().query_wasm_smart(&engine, &QueryMsg::GetRewardBreakdown { car_id: 1 }).unwrap();
+            (data.steps_taken.unwrap()[0].finished, breakdown.breakdown.unwrap().new_best)
+        };
+
+        // The first run only sets the record, a longer one beats it, a shorter one doesn't
+        assert_eq!(race(&mut app, 1), (false, 0));
+        assert_eq!(race(&mut app, 2), (false, 15));
+        assert_eq!(race(&mut app, 1), (false, 0));
+        // Straight up to the finish
+        assert!(race(&mut app, 4).0);
+    }
+}
+
+#[test]
+fn test_car_seeds_decorrelate_exploration() {
+    use crate::contract::{car_seed, simulate_tick};
+
+    // Car 0 used to get seed 0 on every tick
+    let seeds = |car_id: u128| (0..8).map(|tick| car_seed(tick, car_id)).collect::<Vec<_>>();
+    let (car_0, car_2) = (seeds(0), seeds(2));
+    assert!(car_0.iter().any(|seed| *seed != car_0[0]), "{:?}", car_0);
+    assert!(car_0.iter().zip(&car_2).all(|(a, b)| a != b));
+
+    // Purely random driving from the middle of the track, one car at a time
+    let layout = create_test_track().layout;
+    let path = |car_id: u128| {
+        let mut deps = setup_test_app();
+        let mut car = test_car_state(car_id);
+        car.x = 2;
+        car.y = 2;
+        let mut race_state = racing::race_engine::RaceState {
+            cars: vec![car],
+            track_layout: layout.clone(),
+            tick: 0,
+            play_by_play: std::collections::HashMap::new(),
+            claimed_checkpoints: vec![],
+            state_encoding: StateEncoding::Hashed,
+            finish_tiles: vec![],
+            q_track: None,
+            distinguish_edges: false,
+            shuffle_order: false,
+        };
+        let training_config = TrainingConfig {
+            training_mode: true,
+            ..Default::default()
+        };
+        let mut positions = vec![];
+        for tick in 0..8 {
+            simulate_tick(&mut deps.storage, &mut race_state, training_config.clone(), tick, 10).unwrap();
+            positions.push((race_state.cars[0].x, race_state.cars[0].y));
+            if race_state.cars[0].finished {
+                break;
+            }
+        }
+        positions
+    };
+    assert_ne!(path(0), path(2));
+}
+
+#[test]
+fn test_get_race_result_by_id_without_track() {
+    let mut deps = setup_test_app();
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), simulate_msg(vec![1u128, 2u128])).unwrap();
+    let data: racing::race_engine::SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetRaceResultById { race_id: data.race_id.clone() }).unwrap();
+    let by_id: racing::race_engine::RaceResultResponse = from_json(response).unwrap();
+    assert_eq!(by_id.result.race_id, data.race_id);
+    assert_eq!(by_id.result.track_id, cosmwasm_std::Uint128::from(1u128));
+    assert_eq!(by_id.result.winner_ids, data.winner_ids);
+
+    // Same race as the track-scoped lookup
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetRaceResult { track_id: 1, race_id: data.race_id }).unwrap();
+    let by_track: racing::race_engine::RaceResultResponse = from_json(response).unwrap();
+    assert_eq!(by_id, by_track);
+
+    let err = query(deps.as_ref(), mock_env(), QueryMsg::GetRaceResultById { race_id: "race_1_0".to_string() }).unwrap_err();
+    assert!(err.to_string().contains("Race not found: race_1_0"), "{}", err);
+}
+
+#[test]
+fn test_recent_races_store_play_by_play_once() {
+    use crate::state::{CAR_RECENT_RACES, RECENT_RACE_RESULTS, TRACK_RECENT_RACES};
+
+    let mut deps = setup_test_app();
+    let mut race_ids = vec![];
+    for block in 0..2 {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(block);
+        let res = execute(deps.as_mut(), env, mock_info(ADMIN, &[]), simulate_msg(vec![1u128, 2u128])).unwrap();
+        let data: racing::race_engine::SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+        race_ids.push(data.race_id);
+    }
+
+    // One stored result per race, the track and car lists only hold keys to it
+    let stored: Vec<_> = RECENT_RACE_RESULTS
+        .range(&deps.storage, None, None, cosmwasm_std::Order::Ascending)
+        .map(|item| item.unwrap())
+        .collect();
+    assert_eq!(stored.len(), 2);
+    assert!(stored.iter().all(|(_, race)| race.refs == 3 && !race.result.play_by_play.is_empty()));
+    assert_eq!(TRACK_RECENT_RACES.load(&deps.storage, 1).unwrap(), vec![0, 1]);
+    assert_eq!(CAR_RECENT_RACES.load(&deps.storage, 1).unwrap(), vec![0, 1]);
+    assert_eq!(CAR_RECENT_RACES.load(&deps.storage, 2).unwrap(), vec![0, 1]);
+
+    // Every query resolves the keys back into the full result
+    let recent_races = |car_id: Option<u128>, track_id: Option<u128>| {
+        let query_msg = QueryMsg::ListRecentRaces { car_id, track_id, start_after: None, limit: None };
+        let races: racing::race_engine::RecentRacesResponse = from_json(query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
+        races.races
+    };
+    let results: Vec<_> = stored.into_iter().map(|(_, race)| race.result).collect();
+    assert_eq!(recent_races(None, Some(1)), results);
+    assert_eq!(recent_races(Some(1), None), results);
+    assert_eq!(recent_races(Some(2), None), results);
+
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetRaceResult { track_id: 1, race_id: race_ids[1].clone() }).unwrap();
+    let by_track: racing::race_engine::RaceResultResponse = from_json(response).unwrap();
+    assert_eq!(by_track.result, results[1]);
+}
+
+#[test]
+fn test_race_standings_join_rankings_and_steps() {
+    let mut deps = setup_test_app();
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), simulate_msg(vec![3u128, 1u128, 2u128])).unwrap();
+    let data: racing::race_engine::SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetRaceStandings { race_id: data.race_id.clone(), track_id: 1 }).unwrap();
+    let standings: Vec<racing::race_engine::Standing> = from_json(response).unwrap();
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetRaceResult { track_id: 1, race_id: data.race_id }).unwrap();
+    let result: racing::race_engine::RaceResultResponse = from_json(response).unwrap();
+
+    assert_eq!(standings.len(), 3);
+    assert!(standings.windows(2).all(|pair| pair[0].rank < pair[1].rank));
+    for standing in &standings {
+        let step = result.result.steps_taken.iter().find(|step| step.car_id == standing.car_id).unwrap();
+        assert_eq!((standing.steps, standing.finished), (step.steps_taken, step.finished));
+        assert!(result.result.rankings.contains(&racing::race_engine::Rank { car_id: standing.car_id, rank: standing.rank }));
+    }
+}
+
+#[test]
+fn test_last_race_summary_matches_race_result() {
+    let mut deps = setup_test_app();
+    let summary = |deps: cosmwasm_std::Deps, car_id: u128| -> Option<racing::race_engine::LastRaceSummary> {
+        from_json(query(deps, mock_env(), QueryMsg::GetLastRaceSummary { car_id }).unwrap()).unwrap()
+    };
+    assert_eq!(summary(deps.as_ref(), 1), None);
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), simulate_msg(vec![1u128, 2u128])).unwrap();
+    let data: racing::race_engine::SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetRaceResultById { race_id: data.race_id.clone() }).unwrap();
+    let result: racing::race_engine::RaceResultResponse = from_json(response).unwrap();
+
+    for car_id in [1u128, 2] {
+        let last = summary(deps.as_ref(), car_id).unwrap();
+        let rank = result.result.rankings.iter().find(|rank| rank.car_id == car_id).unwrap();
+        let step = result.result.steps_taken.iter().find(|step| step.car_id == car_id).unwrap();
+        assert_eq!(last, racing::race_engine::LastRaceSummary {
+            race_id: data.race_id.clone(),
+            track_id: 1,
+            rank: rank.rank,
+            steps: step.steps_taken,
+            finished: step.finished,
+        });
+    }
+}
+
+#[test]
+fn test_non_finish_reason_classifies_play_by_play() {
+    use crate::state::add_recent_race;
+    use racing::race_engine::{Action, NonFinishReason, PlayByPlay, Position, RaceResult, Step};
+
+    let mut deps = setup_test_app();
+    // (car, positions from the start tile on, finished)
+    let runs: Vec<(u128, Vec<(u32, u32)>, bool)> = vec![
+        // Back and forth between (1, 3) and (1, 2)
+        (1, vec![(0, 4), (0, 3), (1, 3), (1, 2), (1, 3), (1, 2), (1, 3), (1, 2)], false),
+        // Laps of a 2x2 square
+        (2, vec![(2, 3), (3, 3), (3, 2), (2, 2), (2, 3), (3, 3), (3, 2), (2, 2)], false),
+        // Up column 4, waiting a tick here and there
+        (3, vec![(4, 4), (4, 3), (4, 3), (4, 2), (4, 2), (4, 1)], false),
+        // Stunned on (0, 3) for the rest of the race
+        (4, vec![(0, 4), (0, 3), (0, 3), (0, 3), (0, 3), (0, 3)], false),
+        (5, vec![(2, 4), (2, 3), (2, 2), (2, 1), (2, 0)], true),
+        (6, vec![(3, 4), (3, 3), (3, 2)], false),
+    ];
+    let race_result = RaceResult {
+        race_id: "race_1_0".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: runs.iter().map(|(car_id, _, _)| *car_id).collect(),
+        winner_ids: vec![5],
+        rankings: vec![],
+        play_by_play: runs.iter()
+            .map(|(car_id, positions, _)| {
+                let position = |(x, y): (u32, u32)| Position { car_id: *car_id, x, y };
+                (*car_id, PlayByPlay {
+                    starting_position: position(positions[0]),
+                    actions: positions[1..].iter()
+                        .map(|p| Action { action: "0".to_string(), resulting_position: position(*p) })
+                        .collect(),
+                })
+            })
+            .collect(),
+        steps_taken: runs.iter()
+            .map(|(car_id, positions, finished)| Step { car_id: *car_id, steps_taken: positions.len() as u32 - 1, finished: *finished })
+            .collect(),
+        eliminations: Some(vec![6]),
+        teams: None,
+        team_results: None,
+    };
+    add_recent_race(&mut deps.storage, 0, &race_result, None, None).unwrap();
+
+    let reason = |car_id: u128| -> Option<NonFinishReason> {
+        let query_msg = QueryMsg::GetNonFinishReason { race_id: "race_1_0".to_string(), car_id };
+        from_json(query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap()
+    };
+    assert_eq!(reason(1), Some(NonFinishReason::Oscillated));
+    assert_eq!(reason(2), Some(NonFinishReason::BoxedIn));
+    assert_eq!(reason(3), Some(NonFinishReason::RanOutOfTicks));
+    assert_eq!(reason(4), Some(NonFinishReason::StuckOnTile));
+    assert_eq!(reason(5), None);
+    assert_eq!(reason(6), Some(NonFinishReason::Eliminated));
+
+    let err = query(deps.as_ref(), mock_env(), QueryMsg::GetNonFinishReason { race_id: "race_1_0".to_string(), car_id: 7 }).unwrap_err();
+    assert!(err.to_string().contains("Car not found: 7"), "{}", err);
+}
+
+#[test]
+fn test_multi_car_stats_fills_defaults_for_fresh_cars() {
+    let mut deps = setup_test_app();
+    let env = mock_env();
+    let info = mock_info(ADMIN, &[]);
+
+    execute(deps.as_mut(), env.clone(), info, simulate_msg(vec![1, 2])).unwrap();
+
+    let response = query(deps.as_ref(), env, QueryMsg::GetMultiCarStats {
+        car_ids: vec![1, 2, 3],
+        track_id: 1,
+    }).unwrap();
+    let stats: Vec<GetTrackTrainingStatsResponse> = from_json(response).unwrap();
+
+    assert_eq!(stats.iter().map(|s| s.car_id).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert!(stats.iter().all(|s| s.track_id == 1));
+    assert_eq!(stats[0].stats.pvp.tally, 1);
+    assert_eq!(stats[1].stats.pvp.tally, 1);
+    // Car 3 never raced, so it gets the same defaults as the single-car query
+    assert_eq!(stats[2].stats.pvp.tally, 0);
+    assert_eq!(stats[2].stats.solo.tally, 0);
+    assert_eq!(stats[2].stats.pvp.fastest, u32::MAX);
+}
+
+#[test]
+fn test_comeback_bonus_for_car_last_at_halfway() {
+    use crate::contract::{calculate_reward_breakdown, generate_state_hash, simulate_race};
+    use crate::state::set_q_values;
+
+    // Progress rises toward the finish. Car 1 drives up column 0 into a mud pit one tile short,
+    // car 2 crawls up column 4 over slow tiles but never stops for long
+    let mut layout = create_test_track().layout;
+    layout[1][0].properties = TileProperties::mud(8);
+    for y in 1..=3 {
+        layout[y][4].properties = TileProperties::slow(2);
+    }
+
+    let mut deps = setup_test_app();
+    let mut drive_up = |car_id: u128, x: i32, others: &[(i32, i32)]| {
+        for y in 1..=4 {
+            let state_hash = generate_state_hash(&layout, x, y, 1, others, &[], &StateEncoding::Hashed, false);
+            set_q_values(&mut deps.storage, car_id, None, &state_hash, &StateEncoding::Hashed, [10, 0, 0, 0]).unwrap();
+        }
+    };
+    drive_up(1, 0, &[(4, 2)]);
+    // Car 2 is over the line by the time car 1 leaves the mud
+    drive_up(1, 0, &[]);
+    drive_up(2, 4, &[(0, 2)]);
+
+    let start = |car_id: u128, x: i32| {
+        let mut car = test_car_state(car_id);
+        car.x = x;
+        car.y = 4;
+        car.tile = layout[4][x as usize].clone();
+        car
+    };
+    let mut race_state = racing::race_engine::RaceState {
+        cars: vec![start(1, 0), start(2, 4)],
+        track_layout: layout.clone(),
+        tick: 0,
+        play_by_play: std::collections::HashMap::new(),
+        claimed_checkpoints: vec![],
+        state_encoding: StateEncoding::Hashed,
+        finish_tiles: vec![],
+        q_track: None,
+        distinguish_edges: false,
+        shuffle_order: false,
+    };
+    let training_config = TrainingConfig {
+        ..Default::default()
+    };
+    let race_result = simulate_race(&mut deps.storage, &mut race_state, training_config, 20, None, false, &RankingMode::FinishedFirst, None).unwrap();
+
+    // Car 2 was last at the halfway tick and wins
+    assert_eq!(race_state.tick, 12);
+    let (car_1, car_2) = (&race_state.cars[0], &race_state.cars[1]);
+    assert_eq!((car_1.steps_taken, car_2.steps_taken), (12, 10));
+    assert_eq!((car_1.midpoint_rank, car_2.midpoint_rank), (Some(0), Some(1)));
+    assert_eq!(race_result.winner_ids[0], 2);
+
+    let reward_config = RewardNumbers {
+        distance: 0,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0, 0, 0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: Some(30),
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+    let comeback = |car: &racing::race_engine::CarState, action_index: usize| {
+        let total_actions = car.action_history.len();
+        let tile = car.action_history[action_index].2.clone();
+        calculate_reward_breakdown(car, &race_result, 0, tile.clone(), tile, action_index, total_actions, reward_config.clone(), 10, None)
+            .unwrap()
+            .comeback
+    };
+    // Credited once, on the final action, per position gained
+    assert_eq!(comeback(car_2, car_2.action_history.len() - 1), 30);
+    assert_eq!(comeback(car_2, 0), 0);
+    // Car 1 led at halfway, no comeback to reward
+    assert_eq!(comeback(car_1, car_1.action_history.len() - 1), 0);
+}
+
+#[test]
+fn test_duplicate_car_ids_rejected() {
+    let mut deps = setup_test_app();
+    let info = mock_info(ADMIN, &[]);
+
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), simulate_msg(vec![1u128, 2u128, 1u128])).unwrap_err();
+    assert_eq!(err, ContractError::DuplicateCar { car_id: 1 });
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::SimulateEliminationRace { track_id: cosmwasm_std::Uint128::from(1u128), car_ids: vec![3u128, 3u128], elimination_interval: 5 },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::DuplicateCar { car_id: 3 });
+
+    // Distinct ids race as usual
+    let res = execute(deps.as_mut(), mock_env(), info, simulate_msg(vec![1u128, 2u128, 3u128])).unwrap();
+    let data: racing::race_engine::SimulateRaceResponse = from_json(res.data.unwrap()).unwrap();
+    assert_eq!(data.rankings.len(), 3);
+}
+
+#[test]
+fn test_path_efficiency_bonus_favours_direct_path() {
+    use crate::contract::{calculate_reward_breakdown, generate_state_hash, simulate_race};
+    use crate::state::set_q_values;
+
+    // Progress rises one per row toward the finish. Both cars finish in three ticks off boost tiles,
+    // car 1 straight up column 0, car 2 sidesteps into column 1 first
+    let mut layout = create_test_track().layout;
+    layout[3][0].properties = TileProperties::boost(2);
+    layout[4][1].properties = TileProperties::boost(2);
+    layout[2][1].properties = TileProperties::boost(2);
+
+    let mut deps = setup_test_app();
+    // (car, x, y, speed, action)
+    for (car_id, x, y, speed, action) in [
+        (1u128, 0, 4, 1, 0usize), (1, 0, 3, 2, 0), (1, 0, 1, 1, 0),
+        (2, 2, 4, 1, 2), (2, 1, 4, 2, 0), (2, 1, 2, 2, 0),
+    ] {
+        let state_hash = generate_state_hash(&layout, x, y, speed, &[], &[], &StateEncoding::Hashed, false);
+        let mut q_values = [0; 4];
+        q_values[action] = 10;
+        set_q_values(&mut deps.storage, car_id, None, &state_hash, &StateEncoding::Hashed, q_values).unwrap();
+    }
+
+    let training_config = TrainingConfig {
+        ..Default::default()
+    };
+    let reward_config = RewardNumbers {
+        distance: 0,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: Some(100),
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+
+    // Solo races, so the two runs can't interfere
+    let mut race = |car_id: u128, x: i32| {
+        let mut car = test_car_state(car_id);
+        car.x = x;
+        car.y = 4;
+        car.tile = layout[4][x as usize].clone();
+        let mut race_state = racing::race_engine::RaceState {
+            cars: vec![car],
+            track_layout: layout.clone(),
+            tick: 0,
+            play_by_play: std::collections::HashMap::new(),
+            claimed_checkpoints: vec![],
+            state_encoding: StateEncoding::Hashed,
+            finish_tiles: vec![],
+            q_track: None,
+            distinguish_edges: false,
+            shuffle_order: false,
+        };
+        let race_result = simulate_race(&mut deps.storage, &mut race_state, training_config.clone(), 10, None, false, &RankingMode::FinishedFirst, None).unwrap();
+        let car = race_state.cars.remove(0);
+        assert!(car.finished);
+        let total_actions = car.action_history.len();
+        let breakdowns: Vec<_> = (0..total_actions)
+            .map(|i| {
+                let tile = car.action_history[i].2.clone();
+                calculate_reward_breakdown(&car, &race_result, 0, tile.clone(), tile, i, total_actions, reward_config.clone(), 10, None).unwrap()
+            })
+            .collect();
+        (car, breakdowns)
+    };
+    let (direct, direct_rewards) = race(1, 0);
+    let (meandering, meandering_rewards) = race(2, 2);
+
+    assert_eq!((direct.steps_taken, meandering.steps_taken), (3, 3));
+    assert_eq!((direct.cells_traveled, meandering.cells_traveled), (4, 5));
+
+    // Credited once, on the final action
+    assert!(direct_rewards[..2].iter().chain(&meandering_rewards[..2]).all(|b| b.path_efficiency == 0));
+    assert_eq!(direct_rewards[2].path_efficiency, 100);
+    assert_eq!(meandering_rewards[2].path_efficiency, 80);
+    let total = |rewards: &[racing::types::RewardBreakdown]| rewards.iter().map(|b| b.total()).sum::<i32>();
+    assert!(total(&direct_rewards) > total(&meandering_rewards));
+}
+
+#[test]
+fn test_tile_variety_bonus_rewards_trying_track_features() {
+    use crate::contract::{calculate_reward_breakdown, generate_state_hash, simulate_race};
+    use crate::state::set_q_values;
+    use racing::types::TileKind;
+
+    // Car 1 drives up onto sticky, right onto a boost, two up onto a normal tile and then the finish
+    // Car 2 drives straight up column 4 on normal tiles
+    let mut layout = create_test_track().layout;
+    layout[3][0].properties = TileProperties::sticky();
+    layout[3][1].properties = TileProperties::boost(2);
+
+    let mut deps = setup_test_app();
+    // (car, x, y, speed, action)
+    for (car_id, x, y, speed, action) in [
+        (1u128, 0, 4, 1, 0usize), (1, 0, 3, 1, 3), (1, 1, 3, 2, 0), (1, 1, 1, 1, 0),
+        (2, 4, 4, 1, 0), (2, 4, 3, 1, 0), (2, 4, 2, 1, 0), (2, 4, 1, 1, 0),
+    ] {
+        let state_hash = generate_state_hash(&layout, x, y, speed, &[], &[], &StateEncoding::Hashed, false);
+        let mut q_values = [0; 4];
+        q_values[action] = 10;
+        set_q_values(&mut deps.storage, car_id, None, &state_hash, &StateEncoding::Hashed, q_values).unwrap();
+    }
+
+    let training_config = TrainingConfig {
+        ..Default::default()
+    };
+    let reward_config = RewardNumbers {
+        distance: 0,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0],
+            other: 0,
+            margin_bonus: None,
+        },
+        // No speed reward, car 1 takes longer with its stun
+        max_speed_reward: Some(0),
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: Some(10),
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+
+    // Solo races, so the two runs can't interfere
+    let mut race = |car_id: u128, x: i32| {
+        let mut car = test_car_state(car_id);
+        car.x = x;
+        car.y = 4;
+        car.tile = layout[4][x as usize].clone();
+        let mut race_state = racing::race_engine::RaceState {
+            cars: vec![car],
+            track_layout: layout.clone(),
+            tick: 0,
+            play_by_play: std::collections::HashMap::new(),
+            claimed_checkpoints: vec![],
+            state_encoding: StateEncoding::Hashed,
+            finish_tiles: vec![],
+            q_track: None,
+            distinguish_edges: false,
+            shuffle_order: false,
+        };
+        let race_result = simulate_race(&mut deps.storage, &mut race_state, training_config.clone(), 10, None, false, &RankingMode::FinishedFirst, None).unwrap();
+        let car = race_state.cars.remove(0);
+        assert!(car.finished);
+        let total_actions = car.action_history.len();
+        let breakdowns: Vec<_> = (0..total_actions)
+            .map(|i| {
+                let tile = car.action_history[i].2.clone();
+                calculate_reward_breakdown(&car, &race_result, 0, tile.clone(), tile, i, total_actions, reward_config.clone(), 10, None).unwrap()
+            })
+            .collect();
+        (car, breakdowns)
+    };
+    let (varied, varied_rewards) = race(1, 0);
+    let (plain, plain_rewards) = race(2, 4);
+
+    assert_eq!(varied.tile_kinds, vec![TileKind::Sticky, TileKind::Boost, TileKind::Normal]);
+    assert_eq!(plain.tile_kinds, vec![TileKind::Normal]);
+
+    // Credited once, on the final action
+    assert_eq!(varied_rewards.last().unwrap().tile_variety, 30);
+    assert_eq!(plain_rewards.last().unwrap().tile_variety, 10);
+    let total = |rewards: &[racing::types::RewardBreakdown]| rewards.iter().map(|b| b.total()).sum::<i32>();
+    assert_eq!(total(&varied_rewards), 30);
+    assert_eq!(total(&plain_rewards), 10);
+}
+
+#[test]
+fn test_simulate_action_selection_under_each_strategy() {
+    use crate::contract::{car_seed, pseudo_random};
+    use crate::state::{get_config, set_q_values};
+    use racing::race_engine::ActionSelectionResponse;
+    use racing::types::ActionSelectionStrategy;
+
+    let mut deps = setup_test_app();
+    let state_hash = [7u8; 32];
+    let encoding = get_config(&deps.storage).unwrap().state_encoding;
+    set_q_values(&mut deps.storage, 1, None, &state_hash, &encoding, [5, 40, 10, -3]).unwrap();
+
+    let select = |strategy: ActionSelectionStrategy, seed: u32| {
+        let response = query(deps.as_ref(), mock_env(), QueryMsg::SimulateActionSelection {
+            car_id: 1,
+            track_id: None,
+            state_hash,
+            strategy,
+            seed,
+        }).unwrap();
+        from_json::<ActionSelectionResponse>(response).unwrap()
+    };
+
+    let best = select(ActionSelectionStrategy::Best, 0);
+    assert_eq!(best.action, 1);
+    assert_eq!(best.action_values, [5, 40, 10, -3]);
+
+    // Random ignores the values and draws from the car's seed for the tick
+    for seed in 0..8 {
+        let expected = pseudo_random(car_seed(seed, 1), 4) as usize;
+        assert_eq!(select(ActionSelectionStrategy::Random, seed).action, expected);
+    }
+
+    // Never exploring is Best, always exploring draws on the next seed
+    for seed in 0..8 {
+        assert_eq!(select(ActionSelectionStrategy::EpsilonGreedy(0.0), seed).action, 1);
+        let expected = pseudo_random(car_seed(seed, 1).wrapping_add(1), 4) as usize;
+        assert_eq!(select(ActionSelectionStrategy::EpsilonGreedy(1.0), seed).action, expected);
+    }
+
+    // An unvisited state selects over zeros, ties go to the last action like in a race
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::SimulateActionSelection {
+        car_id: 2,
+        track_id: None,
+        state_hash,
+        strategy: ActionSelectionStrategy::Best,
+        seed: 0,
+    }).unwrap();
+    let unvisited: ActionSelectionResponse = from_json(response).unwrap();
+    assert_eq!((unvisited.action, unvisited.action_values), (3, [0; 4]));
+}
+
+#[test]
+fn test_deterministic_softmax_repeats_selections() {
+    use crate::contract::{select_action, simulate_race};
+    use racing::types::ActionSelectionStrategy;
+
+    // T = 10: actions 0 and 3 tie, 1 is ~22% as likely and 2 is ~0.25% as likely
+    let q_values = [20, 5, -40, 20];
+    let strategy = ActionSelectionStrategy::DeterministicSoftmax(10_000);
+    let run = || (0..200u32)
+        .map(|seed| select_action(&q_values, &[0, 1, 2, 3], strategy.clone(), seed))
+        .collect::<Vec<_>>();
+    let selections = run();
+    assert_eq!(selections, run());
+    // Pinned, integer math must pick exactly these on every target
+    let counts = (0..4).map(|action| selections.iter().filter(|&&a| a == action).count()).collect::<Vec<_>>();
+    assert_eq!(counts, vec![93, 18, 2, 87]);
+
+    // Whole races replay the same under the flag
+    let race = || {
+        let mut deps = setup_test_app();
+        let layout = create_test_track().layout;
+        let start = |car_id: u128, x: i32| {
+            let mut car = test_car_state(car_id);
+            car.x = x;
+            car.y = 4;
+            car.tile = layout[4][x as usize].clone();
+            car
+        };
+        let mut race_state = racing::race_engine::RaceState {
+            cars: vec![start(1, 0), start(2, 4)],
+            track_layout: layout.clone(),
+            tick: 0,
+            play_by_play: std::collections::HashMap::new(),
+            claimed_checkpoints: vec![],
+            state_encoding: StateEncoding::Hashed,
+            finish_tiles: vec![],
+            q_track: None,
+            distinguish_edges: false,
+            shuffle_order: false,
+        };
+        let training_config = TrainingConfig {
+            training_mode: true,
+            temperature: 2.5,
+            deterministic_softmax: true,
+            ..Default::default()
+        };
+        simulate_race(&mut deps.storage, &mut race_state, training_config, 30, None, false, &RankingMode::FinishedFirst, None).unwrap()
+    };
+    let first = race();
+    assert_eq!(first.play_by_play, race().play_by_play);
+    assert_eq!(first.rankings, race().rankings);
+}
+
+#[test]
+fn test_online_q_updates_shift_behaviour_mid_race() {
+    use crate::contract::{generate_state_hash, simulate_race, OnlineUpdates};
+    use crate::state::{get_config, get_q_values, set_q_values};
+    use cosmwasm_std::QuerierWrapper;
+
+    // The car starts in the corner believing driving into the right edge is best
+    // - A move that bounces off the edge is recorded as RIGHT, so that's the value the wall penalty lowers
+    let layout = create_test_track().layout;
+    let corner = generate_state_hash(&layout, 4, 4, 1, &[], &[], &StateEncoding::Hashed, false);
+    let reward_config = RewardNumbers {
+        distance: 0,
+        stuck: 0,
+        wall: -50,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: None,
+        overtime_penalty_per_tick: None,
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+
+    let race = |online_batch_ticks: Option<u32>| {
+        let mut deps = setup_test_app();
+        set_q_values(&mut deps.storage, 1, None, &corner, &StateEncoding::Hashed, [0, 0, 0, 10]).unwrap();
+        let config = get_config(&deps.storage).unwrap();
+
+        let mut car = test_car_state(1);
+        car.x = 4;
+        car.y = 4;
+        car.tile = layout[4][4].clone();
+        let mut race_state = racing::race_engine::RaceState {
+            cars: vec![car],
+            track_layout: layout.clone(),
+            tick: 0,
+            play_by_play: std::collections::HashMap::new(),
+            claimed_checkpoints: vec![],
+            state_encoding: StateEncoding::Hashed,
+            finish_tiles: vec![],
+            q_track: None,
+            distinguish_edges: false,
+            shuffle_order: false,
+        };
+        // Greedy, so only the Q-values decide where the car goes
+        let training_config = TrainingConfig {
+            online_batch_ticks: online_batch_ticks,
+            ..Default::default()
+        };
+        let online = OnlineUpdates {
+            reward_config: &reward_config,
+            config: &config,
+            querier: QuerierWrapper::new(&deps.querier),
+            fastest_track_tick_time: 10,
+        };
+        let race_result = simulate_race(&mut deps.storage, &mut race_state, training_config, 6, None, false, &RankingMode::FinishedFirst, Some(online)).unwrap();
+        let positions: Vec<(u32, u32)> = race_result.play_by_play[&1].actions.iter()
+            .map(|action| (action.resulting_position.x, action.resulting_position.y))
+            .collect();
+        (positions, get_q_values(&deps.storage, 1, None, &corner, &StateEncoding::Hashed).unwrap(), race_state.cars[0].q_updates_applied)
+    };
+
+    // End-of-race updates: nothing is learned until the race is over, the car hits the edge every tick
+    let (positions, q_values, applied) = race(None);
+    assert_eq!(positions, vec![(4, 4); 6]);
+    assert_eq!(q_values, [0, 0, 0, 10]);
+    assert_eq!(applied, 0);
+
+    // Every 2 ticks: the first flush takes RIGHT down to 5, the second to -5, below the other actions,
+    // so on the fifth tick the car drives out of the corner
+    let (positions, q_values, applied) = race(Some(2));
+    assert_eq!(positions[..4], vec![(4, 4); 4]);
+    assert_eq!(positions[4], (3, 4));
+    assert!(q_values[3] < 0, "{:?}", q_values);
+    // The last action is left for the end-of-race update
+    assert_eq!(applied, 5);
+}
+
+#[test]
+fn test_overtime_penalty_only_for_slow_finisher() {
+    use crate::contract::calculate_reward_breakdown;
+
+    let race_result = racing::race_engine::RaceResult {
+        race_id: "race_1".to_string(),
+        track_id: cosmwasm_std::Uint128::from(1u128),
+        car_ids: vec![1u128, 2u128],
+        winner_ids: vec![1u128],
+        rankings: vec![
+            racing::race_engine::Rank { car_id: 1, rank: 0 },
+            racing::race_engine::Rank { car_id: 2, rank: 1 },
+        ],
+        play_by_play: std::collections::HashMap::new(),
+        steps_taken: vec![],
+        eliminations: None,
+        teams: None,
+        team_results: None,
+    };
+    let reward_config = RewardNumbers {
+        distance: 0,
+        stuck: 0,
+        wall: 0,
+        no_move: 0,
+        explore: 0,
+        rank: racing::types::RankReward {
+            ranks: vec![0, 0],
+            other: 0,
+            margin_bonus: None,
+        },
+        max_speed_reward: None,
+        draft_bonus: None,
+        checkpoint_bonus: None,
+        backward_penalty_multiplier: None,
+        collision_penalty: None,
+        reward_min: None,
+        reward_max: None,
+        comeback_bonus: None,
+        path_efficiency_bonus: None,
+        target_ticks: Some(10),
+        overtime_penalty_per_tick: Some(-5),
+        evasion_bonus: None,
+        tile_variety_bonus: None,
+        frustration_penalty: None,
+        new_best_bonus: None,
+    };
+    let finisher = |car_id: u128, steps_taken: u32| {
+        let mut car = test_car_state(car_id);
+        car.finished = true;
+        car.steps_taken = steps_taken;
+        car
+    };
+    let (fast, slow) = (finisher(1, 8), finisher(2, 14));
+    let overtime = |car: &racing::race_engine::CarState, action_index: usize| {
+        let total_actions = car.steps_taken as usize;
+        calculate_reward_breakdown(car, &race_result, 0, car.tile.clone(), car.tile.clone(), action_index, total_actions, reward_config.clone(), 10, None)
+            .unwrap()
+            .overtime
+    };
+
+    // Four steps over the target, charged once on the final action
+    assert_eq!(overtime(&slow, 13), -20);
+    assert_eq!(overtime(&slow, 12), 0);
+    assert_eq!(overtime(&fast, 7), 0);
+
+    // On target is not over it
+    assert_eq!(overtime(&finisher(3, 10), 9), 0);
+}
+
+#[test]
+fn test_per_track_q_tables_learn_independently() {
+    use crate::state::{get_config, set_config};
+
+    let mut deps = setup_test_app();
+    let mut config = get_config(&deps.storage).unwrap();
+    config.per_track_q = true;
+    set_config(&mut deps.storage, config).unwrap();
+
+    // Every track id serves the same layout, so both tracks produce the same state hashes
+    let race_on = |track_id: u128| {
+        let mut msg = simulate_msg(vec![1u128]);
+        if let ExecuteMsg::SimulateRace { track_id: id, .. } = &mut msg {
+            *id = cosmwasm_std::Uint128::from(track_id);
+        }
+        msg
+    };
+    let q_table = |deps: cosmwasm_std::Deps, track_id: Option<u128>| {
+        let response = query(deps, mock_env(), QueryMsg::GetQ { car_id: 1u128, track_id, state_hash: None, start_after: None, limit: None }).unwrap();
+        from_json::<racing::race_engine::GetQResponse>(response).unwrap().q_values
+    };
+
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), race_on(1)).unwrap();
+    let track_1 = q_table(deps.as_ref(), Some(1));
+    assert!(!track_1.is_empty());
+    assert!(q_table(deps.as_ref(), Some(2)).is_empty());
+    assert!(q_table(deps.as_ref(), None).is_empty());
+
+    // Training on track 2 fills its own table and leaves track 1's alone
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), race_on(2)).unwrap();
+    let track_2 = q_table(deps.as_ref(), Some(2));
+    assert!(!track_2.is_empty());
+    assert_eq!(q_table(deps.as_ref(), Some(1)), track_1);
+
+    // Single-state reads use the same table
+    let state_hash = track_1[0].state_hash;
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetQ { car_id: 1u128, track_id: Some(1), state_hash: Some(state_hash), start_after: None, limit: None }).unwrap();
+    let single: racing::race_engine::GetQResponse = from_json(response).unwrap();
+    assert_eq!(single.q_values, vec![track_1[0].clone()]);
+
+    // So do the other Q-table tools
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetQTableSize { car_id: 1u128, track_id: Some(1) }).unwrap();
+    let size: racing::race_engine::QTableSizeResponse = from_json(response).unwrap();
+    assert_eq!(size.entries, track_1.len() as u64);
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::GetBestAction { car_id: 1u128, track_id: Some(1), state_hash }).unwrap();
+    let best: racing::race_engine::BestActionResponse = from_json(response).unwrap();
+    assert_eq!(best.action_values, track_1[0].action_values);
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::SimulateActionSelection {
+        car_id: 1u128,
+        track_id: Some(1),
+        state_hash,
+        strategy: racing::types::ActionSelectionStrategy::Best,
+        seed: 0,
+    }).unwrap();
+    let selection: racing::race_engine::ActionSelectionResponse = from_json(response).unwrap();
+    assert_eq!(selection.action_values, track_1[0].action_values);
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::ExportPolicy { car_id: 1u128, track_id: Some(1), start_after: None, limit: None }).unwrap();
+    let policy: Vec<racing::race_engine::PolicyEntry> = from_json(response).unwrap();
+    assert_eq!(policy.len(), track_1.len());
+
+    // A track's export imports into another track's table, and compacting it leaves the source alone
+    let response = query(deps.as_ref(), mock_env(), QueryMsg::ExportQTable { car_id: 1u128, track_id: Some(1) }).unwrap();
+    let export: racing::race_engine::QTableExport = from_json(response).unwrap();
+    assert_eq!(export.entries, track_1);
+    let import = ExecuteMsg::ImportQTable { car_id: cosmwasm_std::Uint128::from(1u128), track_id: Some(3), export };
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), import).unwrap();
+    assert_eq!(q_table(deps.as_ref(), Some(3)), track_1);
+    assert!(q_table(deps.as_ref(), None).is_empty());
+    let compact = ExecuteMsg::CompactQTable { car_id: cosmwasm_std::Uint128::from(1u128), track_id: Some(3), threshold: u32::MAX };
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), compact).unwrap();
+    assert!(q_table(deps.as_ref(), Some(3)).is_empty());
+    assert_eq!(q_table(deps.as_ref(), Some(1)), track_1);
+
+    // ResetQ clears every table the car has
+    execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), ExecuteMsg::ResetQ { car_id: cosmwasm_std::Uint128::from(1u128) }).unwrap();
+    assert!(q_table(deps.as_ref(), Some(1)).is_empty());
+    assert!(q_table(deps.as_ref(), Some(2)).is_empty());
+}
@@ -8,9 +8,9 @@ use cw_storage_plus::Bound;
 use racing::race_engine::DEFAULT_SPEED;
 
 use crate::error::TrackManagerError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::state::{get_track, set_track, ADMIN, TRACKS, TRACK_ID_COUNTER};
-use racing::types::{Track, TrackTile, TileProperties};
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::state::{get_track, set_track, ADMIN, RACE_ENGINE, TRACKS, TRACK_ID_COUNTER};
+use racing::types::{Track, TrackTile, TileKind, TileProperties};
 
 const MAX_LIMIT: u32 = 32;
 
@@ -23,12 +23,36 @@ pub fn instantiate(
 ) -> Result<Response, TrackManagerError> {
     let admin = deps.api.addr_validate(&msg.admin)?;
     ADMIN.save(deps.storage, &admin)?;
+    // Track ids start at 0
+    TRACK_ID_COUNTER.save(deps.storage, &Uint128::zero())?;
 
     Ok(Response::new()
         .add_attribute("method", "instantiate")
         .add_attribute("admin", admin))
 }
 
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, TrackManagerError> {
+    // Recomputed from the tile properties, so running it again changes nothing
+    // - fastest_tick_time is kept, the race engine may have lowered it since
+    let tracks = TRACKS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(u128, Track)>>>()?;
+    let count = tracks.len();
+    for (track_id, mut track) in tracks {
+        let properties: Vec<Vec<TileProperties>> = track.layout.iter()
+            .map(|row| row.iter().map(|tile| tile.properties.clone()).collect())
+            .collect();
+        let (layout, _) = calculate_progress_towards_finish(&properties, track.width, track.height);
+        track.layout = layout;
+        set_track(deps.storage, &track_id, track)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("tracks", count.to_string()))
+}
+
 #[entry_point]
 pub fn execute(
     deps: DepsMut,
@@ -43,9 +67,48 @@ pub fn execute(
             height,
             layout,
         } => execute_add_track(deps, _info, name, width, height, layout),
+        ExecuteMsg::SetRaceEngine { race_engine } => {
+            if _info.sender != ADMIN.load(deps.storage)? {
+                return Err(TrackManagerError::Unauthorized {});
+            }
+            let race_engine = deps.api.addr_validate(&race_engine)?;
+            RACE_ENGINE.save(deps.storage, &race_engine)?;
+            Ok(Response::new()
+                .add_attribute("method", "set_race_engine")
+                .add_attribute("race_engine", race_engine))
+        },
+        ExecuteMsg::UpdateFastestTime { track_id, fastest_tick_time } => execute_update_fastest_time(deps, _info, track_id, fastest_tick_time),
     }
 }
 
+/// Record a faster finish reported by the race engine
+pub fn execute_update_fastest_time(
+    deps: DepsMut,
+    info: MessageInfo,
+    track_id: Uint128,
+    fastest_tick_time: u64,
+) -> Result<Response, TrackManagerError> {
+    let race_engine = RACE_ENGINE.may_load(deps.storage)?;
+    if info.sender != ADMIN.load(deps.storage)? && Some(&info.sender) != race_engine.as_ref() {
+        return Err(TrackManagerError::Unauthorized {});
+    }
+
+    let mut track = get_track(deps.storage, &track_id.u128())
+        .map_err(|_| TrackManagerError::TrackNotFound { track_id: track_id.to_string() })?;
+    // Two races in the same block can both report, keep the faster one
+    let updated = fastest_tick_time < track.fastest_tick_time;
+    if updated {
+        track.fastest_tick_time = fastest_tick_time;
+        set_track(deps.storage, &track_id.u128(), track)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "update_fastest_time")
+        .add_attribute("track_id", track_id)
+        .add_attribute("fastest_tick_time", fastest_tick_time.to_string())
+        .add_attribute("updated", updated.to_string()))
+}
+
 pub fn execute_add_track(
     deps: DepsMut,
     _info: MessageInfo,
@@ -262,6 +325,9 @@ fn calculate_distances_and_validate(
 }
 
 /// Calculate progress towards finish for each tile using combined validation and distance calculation
+/// - Progress is the BFS distance flipped so higher is closer to the finish, which is how the race engine reads it:
+///   the tile farthest from the finish gets 0, finish tiles get that farthest distance
+/// - Walls and tiles that can't reach the finish get 0
 fn calculate_progress_towards_finish(
     layout: &Vec<Vec<TileProperties>>,
     width: u8,
@@ -271,8 +337,15 @@ fn calculate_progress_towards_finish(
     let distances = calculate_distances_and_validate(layout, width, height)
         .expect("Track validation should have passed");
     
-    //Save starting tiles 
-    let mut starting_tiles = vec![];
+    let farthest = distances.iter()
+        .flatten()
+        .copied()
+        .filter(|distance| *distance != u16::MAX)
+        .max()
+        .unwrap_or(0);
+
+    //Fastest path from any starting tile
+    let mut fastest = u64::MAX;
 
     // Convert to TrackTile format
     let mut track_layout = vec![];
@@ -281,31 +354,22 @@ fn calculate_progress_towards_finish(
         for x in 0..width {
             let properties = layout[y as usize][x as usize].clone();
             let distance = distances[y as usize][x as usize];
+            let progress = if distance == u16::MAX { 0 } else { farthest - distance };
+
+            if properties.is_start && (distance as u64) < fastest {
+                fastest = distance as u64;
+            }
 
-            let tile = TrackTile {
-                properties: properties.clone(),
-                progress_towards_finish: distance,
+            row.push(TrackTile {
+                properties,
+                progress_towards_finish: progress,
                 x,
                 y,
-            };
-
-            if properties.is_start {
-                starting_tiles.push(tile.clone());
-            }
-            
-            row.push(tile);
+            });
         }
         track_layout.push(row);
     }
 
-    let mut fastest = u64::MAX;
-    //Find the fastest path from any starting tile
-    for start_tile in starting_tiles {
-        if (start_tile.progress_towards_finish as u64) < fastest {
-            fastest = start_tile.progress_towards_finish as u64;
-        }
-    }
-    
     (track_layout, fastest)
 }
 
@@ -317,6 +381,8 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             start_after,
             limit,
         } => to_json_binary(&query_list_tracks(deps, start_after, limit).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::GetTrackTileSummary { track_id } => to_json_binary(&query_track_tile_summary(deps, track_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
+        QueryMsg::IsCircuit { track_id } => to_json_binary(&query_is_circuit(deps, track_id).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?),
     }
 }
 
@@ -339,3 +405,128 @@ pub fn query_list_tracks(deps: Deps, start_after: Option<u128>, limit: Option<u3
     }
     Ok(crate::msg::ListTracksResponse { tracks })
 }
+
+/// Count the tiles of each type in a track's layout
+pub fn query_track_tile_summary(deps: Deps, track_id: Uint128) -> Result<crate::msg::TrackTileSummaryResponse, TrackManagerError> {
+    let track = get_track(deps.storage, &track_id.into())?;
+
+    let mut summary = crate::msg::TrackTileSummaryResponse {
+        track_id: track.id,
+        wall: 0,
+        start: 0,
+        finish: 0,
+        checkpoint: 0,
+        mud: 0,
+        sticky: 0,
+        slow: 0,
+        boost: 0,
+        teleporter: 0,
+        one_way: 0,
+        damage: 0,
+        normal: 0,
+    };
+
+    for row in &track.layout {
+        for tile in row {
+            let properties = &tile.properties;
+            if properties.blocks_movement {
+                summary.wall += 1;
+            } else if properties.is_finish {
+                summary.finish += 1;
+            } else if properties.is_start {
+                summary.start += 1;
+            } else if properties.is_checkpoint {
+                summary.checkpoint += 1;
+            } else {
+                match properties.kind() {
+                    TileKind::Sticky if properties.stun_ticks > 0 => summary.mud += 1,
+                    TileKind::Sticky => summary.sticky += 1,
+                    TileKind::Slow => summary.slow += 1,
+                    TileKind::Boost => summary.boost += 1,
+                    TileKind::Teleporter => summary.teleporter += 1,
+                    TileKind::OneWay => summary.one_way += 1,
+                    TileKind::Damage => summary.damage += 1,
+                    TileKind::Normal => summary.normal += 1,
+                }
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Check whether a track forms a closed circuit
+pub fn query_is_circuit(deps: Deps, track_id: Uint128) -> Result<crate::msg::IsCircuitResponse, TrackManagerError> {
+    let track = get_track(deps.storage, &track_id.into())?;
+
+    Ok(crate::msg::IsCircuitResponse {
+        track_id: track.id,
+        is_circuit: is_circuit(&track.layout),
+    })
+}
+
+/// A track is a circuit if a car can enter the finish line from the start side, drive straight
+/// across it and reach a start tile again from the far side without touching the finish
+fn is_circuit(layout: &[Vec<TrackTile>]) -> bool {
+    use std::collections::VecDeque;
+
+    let height = layout.len() as i32;
+    let width = layout.first().map_or(0, |row| row.len()) as i32;
+    let tile_at = |x: i32, y: i32| {
+        if x < 0 || y < 0 || x >= width || y >= height {
+            return None;
+        }
+        Some(&layout[y as usize][x as usize])
+    };
+    let drivable = |x: i32, y: i32| tile_at(x, y)
+        .map_or(false, |tile| !tile.properties.blocks_movement && !tile.properties.is_finish);
+
+    // Everything reachable from the start tiles without crossing the finish
+    let mut reachable = vec![vec![false; width as usize]; height as usize];
+    let mut queue = VecDeque::new();
+    for y in 0..height {
+        for x in 0..width {
+            if drivable(x, y) && layout[y as usize][x as usize].properties.is_start {
+                reachable[y as usize][x as usize] = true;
+                queue.push_back((x, y));
+            }
+        }
+    }
+
+    let directions = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+    while let Some((x, y)) = queue.pop_front() {
+        for (dx, dy) in directions {
+            let (nx, ny) = (x + dx, y + dy);
+            if drivable(nx, ny) && !reachable[ny as usize][nx as usize] {
+                reachable[ny as usize][nx as usize] = true;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+
+    // Drive straight over the finish line from every reachable edge of it
+    for y in 0..height {
+        for x in 0..width {
+            if !layout[y as usize][x as usize].properties.is_finish {
+                continue;
+            }
+            for (dx, dy) in directions {
+                let (entry_x, entry_y) = (x - dx, y - dy);
+                if !drivable(entry_x, entry_y) || !reachable[entry_y as usize][entry_x as usize] {
+                    continue;
+                }
+
+                let (mut exit_x, mut exit_y) = (x, y);
+                while tile_at(exit_x, exit_y).map_or(false, |tile| tile.properties.is_finish) {
+                    exit_x += dx;
+                    exit_y += dy;
+                }
+                if drivable(exit_x, exit_y) && reachable[exit_y as usize][exit_x as usize] {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
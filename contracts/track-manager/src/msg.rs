@@ -1,7 +1,10 @@
 // Re-export types from the shared package
 pub use racing::track_manager::{
     InstantiateMsg,
+    MigrateMsg,
     ExecuteMsg,
     QueryMsg,
     ListTracksResponse,
+    TrackTileSummaryResponse,
+    IsCircuitResponse,
 }; 
\ No newline at end of file
@@ -5,6 +5,8 @@ use racing::types::{Track, TrackTile};
 
 pub const ADMIN: Item<Addr> = Item::new("admin");
 pub const TRACK_ID_COUNTER: Item<Uint128> = Item::new("track_id_counter");
+/// Race engine allowed to report track records
+pub const RACE_ENGINE: Item<Addr> = Item::new("race_engine");
 
 // Track storage: track_id -> Track
 pub const TRACKS: Map<u128, Track> = Map::new("tracks");
@@ -280,6 +280,173 @@ fn test_add_track_with_large_dimensions() {
     assert_eq!(track_response.layout[0].len(), width);
 }
 
+#[test]
+fn test_get_track_tile_summary() {
+    use racing::race_engine::DEFAULT_BOOST_SPEED;
+    use racing::types::{TileProperties, Track, TrackTile, DIRECTION_UP};
+
+    let mut deps = mock_dependencies();
+
+    // 3x5 mixed track: 2 finish, 2 walls, 1 boost, 1 sticky, 1 start, 2 normal,
+    // 1 checkpoint, 1 mud, 1 slow, 1 teleporter, 1 one-way and 1 damage
+    let properties = vec![
+        vec![TileProperties::finish(), TileProperties::finish(), TileProperties::wall()],
+        vec![TileProperties::boost(DEFAULT_BOOST_SPEED as u32), TileProperties::sticky(), TileProperties::normal()],
+        vec![TileProperties::checkpoint(), TileProperties::mud(2), TileProperties::slow(2)],
+        vec![TileProperties::teleporter(0, 0), TileProperties::one_way(DIRECTION_UP), TileProperties::damage(1)],
+        vec![TileProperties::start(), TileProperties::normal(), TileProperties::wall()],
+    ];
+    let layout = properties
+        .into_iter()
+        .enumerate()
+        .map(|(y, row)| {
+            row.into_iter()
+                .enumerate()
+                .map(|(x, properties)| TrackTile {
+                    properties,
+                    progress_towards_finish: 0,
+                    x: x as u8,
+                    y: y as u8,
+                })
+                .collect()
+        })
+        .collect();
+
+    crate::state::set_track(deps.as_mut().storage, &1u128, Track {
+        creator: "creator".to_string(),
+        id: 1,
+        name: "Mixed Track".to_string(),
+        width: 3,
+        height: 5,
+        layout,
+        fastest_tick_time: 2,
+    }).unwrap();
+
+    let query_msg = QueryMsg::GetTrackTileSummary { track_id: cosmwasm_std::Uint128::new(1) };
+    let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+    let summary: crate::msg::TrackTileSummaryResponse = from_json(&res).unwrap();
+
+    assert_eq!(summary.track_id, 1);
+    assert_eq!(summary.wall, 2);
+    assert_eq!(summary.finish, 2);
+    assert_eq!(summary.start, 1);
+    assert_eq!(summary.boost, 1);
+    assert_eq!(summary.sticky, 1);
+    assert_eq!(summary.checkpoint, 1);
+    assert_eq!(summary.mud, 1);
+    assert_eq!(summary.slow, 1);
+    assert_eq!(summary.teleporter, 1);
+    assert_eq!(summary.one_way, 1);
+    assert_eq!(summary.damage, 1);
+    assert_eq!(summary.normal, 2);
+}
+
+#[test]
+fn test_is_circuit() {
+    use racing::types::{TileProperties, Track, TrackTile};
+
+    fn save_track(deps: cosmwasm_std::DepsMut, id: u128, properties: Vec<Vec<TileProperties>>) {
+        let height = properties.len() as u8;
+        let width = properties[0].len() as u8;
+        let layout = properties
+            .into_iter()
+            .enumerate()
+            .map(|(y, row)| {
+                row.into_iter()
+                    .enumerate()
+                    .map(|(x, properties)| TrackTile {
+                        properties,
+                        progress_towards_finish: 0,
+                        x: x as u8,
+                        y: y as u8,
+                    })
+                    .collect()
+            })
+            .collect();
+        crate::state::set_track(deps.storage, &id, Track {
+            creator: "creator".to_string(),
+            id,
+            name: format!("Track {}", id),
+            width,
+            height,
+            layout,
+            fastest_tick_time: 2,
+        }).unwrap();
+    }
+
+    let (n, w, s, f) = (TileProperties::normal(), TileProperties::wall(), TileProperties::start(), TileProperties::finish());
+    let mut deps = mock_dependencies();
+
+    // Straight sprint, the finish is a dead end
+    save_track(deps.as_mut(), 1, vec![
+        vec![f.clone(), f.clone(), f.clone()],
+        vec![n.clone(), n.clone(), n.clone()],
+        vec![s.clone(), s.clone(), s.clone()],
+    ]);
+    // Ring around a walled infield, the finish sits on the top straight
+    save_track(deps.as_mut(), 2, vec![
+        vec![n.clone(), n.clone(), f.clone(), n.clone(), n.clone()],
+        vec![n.clone(), w.clone(), w.clone(), w.clone(), n.clone()],
+        vec![n.clone(), w.clone(), w.clone(), w.clone(), n.clone()],
+        vec![n.clone(), w.clone(), w.clone(), w.clone(), n.clone()],
+        vec![n.clone(), n.clone(), s.clone(), n.clone(), n.clone()],
+    ]);
+
+    let is_circuit = |track_id: u128| -> bool {
+        let query_msg = QueryMsg::IsCircuit { track_id: cosmwasm_std::Uint128::new(track_id) };
+        let res = query(deps.as_ref(), mock_env(), query_msg).unwrap();
+        let response: crate::msg::IsCircuitResponse = from_json(&res).unwrap();
+        assert_eq!(response.track_id, track_id);
+        response.is_circuit
+    };
+    assert!(!is_circuit(1));
+    assert!(is_circuit(2));
+}
+
+#[test]
+fn test_migrate_recomputes_progress_towards_the_finish() {
+    use crate::contract::migrate;
+    use crate::msg::MigrateMsg;
+    use racing::types::{TileProperties, Track, TrackTile};
+
+    let mut deps = mock_dependencies();
+
+    // Stored before progress became higher-is-closer: the finish row has 0, the start row 2
+    let layout = [TileProperties::finish(), TileProperties::normal(), TileProperties::start()]
+        .into_iter()
+        .enumerate()
+        .map(|(y, properties)| {
+            (0..3u8)
+                .map(|x| TrackTile {
+                    properties: properties.clone(),
+                    progress_towards_finish: y as u16,
+                    x,
+                    y: y as u8,
+                })
+                .collect()
+        })
+        .collect();
+    crate::state::set_track(deps.as_mut().storage, &1u128, Track {
+        creator: "creator".to_string(),
+        id: 1,
+        name: "Old Track".to_string(),
+        width: 3,
+        height: 3,
+        layout,
+        fastest_tick_time: 1,
+    }).unwrap();
+
+    // Running it twice gives the same layout
+    for _ in 0..2 {
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        let track = crate::state::get_track(deps.as_ref().storage, &1u128).unwrap();
+        let progress: Vec<u16> = track.layout.iter().map(|row| row[0].progress_towards_finish).collect();
+        assert_eq!(progress, vec![2, 1, 0]);
+        // The record the race engine reported stays
+        assert_eq!(track.fastest_tick_time, 1);
+    }
+}
+
 // Integration tests using cw-multi-test
 #[cfg(test)]
 mod integration_tests {
@@ -31,12 +31,23 @@ pub enum ExecuteMsg {
     UpdateConfig {
         payment_options: Option<Vec<Coin>>,
         new_owner: Option<String>,
+        /// Race engine allowed to record race outcomes
+        race_engine: Option<String>,
     },
     /// Owner-only: update the custom decal SVG for a token
     UpdateCustomDecal {
         token_id: String,
         svg: String,
     },
+    /// Owner-only: permanently retire a car and clear its Q-table entries
+    Burn {
+        car_id: String,
+    },
+    /// Race-engine-only: add a finished race to the car's cached record
+    RecordRaceOutcome {
+        car_id: String,
+        won: bool,
+    },
 }
 
 #[cw_serde]
@@ -44,6 +55,32 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     #[returns(cosmwasm_std::Binary)]
     Base(cw721_base::QueryMsg<cosmwasm_std::Empty>),
+    /// Token ids owned by `owner`, ordered by car id
+    #[returns(Vec<String>)]
+    TokensByOwner {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Owner and cached race record for a car
+    #[returns(GetCarInfoResponse)]
+    GetCarInfo { car_id: String },
+}
+
+#[cw_serde]
+pub struct GetCarInfoResponse {
+    pub car_id: String,
+    pub owner: String,
+    pub total_races: u32,
+    pub wins: u32,
+}
+
+/// Lightweight race summary reported by the race engine
+#[cw_serde]
+#[derive(Default)]
+pub struct RaceRecord {
+    pub total_races: u32,
+    pub wins: u32,
 }
 
 
@@ -52,4 +89,7 @@ pub enum QueryMsg {
 pub struct Config {
     pub owner: Addr,
     pub payment_options: Vec<Coin>,
+    /// Race engine allowed to call RecordRaceOutcome
+    #[serde(default)]
+    pub race_engine: Option<Addr>,
 }
@@ -3,16 +3,38 @@ use std::collections::HashMap;
 use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::Uint128;
 
-use crate::types::{QTableEntry, RewardNumbers, Track, TrackTile, TrackTrainingStats};
+use crate::types::{ActionSelectionStrategy, QTableEntry, RewardBreakdown, RewardNumbers, TileKind, Track, TrackTile, TrackTrainingStats};
 
 pub const DEFAULT_SPEED: u8 = 1;
 pub const DEFAULT_BOOST_SPEED: u8 = 3;
+/// Format version of QTableExport, bump when its layout changes
+pub const Q_TABLE_EXPORT_VERSION: u32 = 1;
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub admin: String,
     pub track_contract: String,
     pub car_contract: String,
+    /// Q-table key format, defaults to Hashed
+    pub state_encoding: Option<StateEncoding>,
+    /// Q-learning updates applied per storage write after a training race, defaults to 32
+    pub q_update_batch_size: Option<u32>,
+    /// Give each car a separate Q-table per track, defaults to false
+    pub per_track_q: Option<bool>,
+    /// Add the car's distance to the track edges to its state, defaults to false
+    pub distinguish_edges: Option<bool>,
+}
+
+/// How a car's local state is turned into its Q-table key.
+/// Fixed at instantiation, Q-tables written with one encoding can't be read with the other.
+#[cw_serde]
+#[derive(Default)]
+pub enum StateEncoding {
+    /// 32-byte Blake2b digest of the packed state
+    #[default]
+    Hashed,
+    /// The packed state itself, stored as a 4-byte key
+    Packed,
 }
 
 #[cw_serde]
@@ -23,12 +45,137 @@ pub enum ExecuteMsg {
         train: bool,
         training_config: Option<TrainingConfig>,
         reward_config: Option<RewardNumbers>,
+        /// Tick limit for this race, defaults to config.max_ticks
+        /// Cars still racing when it runs out are ranked by progress
+        max_ticks: Option<u32>,
+        /// How much of the race result to attach to the response data, defaults to Summary
+        response_detail: Option<ResponseDetail>,
+        /// Group the cars into teams, every car in exactly one team
+        /// - Teams are ranked by their best finisher and rank rewards use the team's rank
+        teams: Option<Vec<Vec<u128>>>,
+        /// Stop the race on the tick the first car finishes, the rest are ranked by progress
+        /// Defaults to false, racing until every car finishes or max_ticks runs out
+        end_on_first_finish: Option<bool>,
+        /// Keep the race inputs so VerifyReplay can re-run it, defaults to false
+        /// - Stores a snapshot of every Q-value the cars read, so leave it off for training
+        record_replay: Option<bool>,
+        /// How cars are ordered in the rankings, defaults to FinishedFirst
+        ranking_mode: Option<RankingMode>,
+        /// Send the track manager an UpdateFastestTime when a car beats the track's fastest_tick_time
+        /// Defaults to false
+        report_records: Option<bool>,
+        /// A ranked race between cars rather than a practice run, needs at least 2 cars unless training
+        /// Defaults to false
+        competitive: Option<bool>,
+        /// Process the cars in a different order each tick, shuffled from the tick index, defaults to false
+        /// - Off, ties for a contested cell always go to the lower car_id, on they go to whichever car the tick's order puts first
+        shuffle_order: Option<bool>,
     },
-    /// Reset the Q-table for a car
-    /// Must be called by the owner of the car in the car contract
+    /// Reset the Q-table for a car, and its per-track tables
+    /// Must be called by the config admin
     ResetQ {
         car_id: Uint128,
     },
+    /// Clear a car's training stats on one track, or on every track if track_id is None
+    /// Must be called by the config admin
+    ResetTrainingStats {
+        car_id: Uint128,
+        track_id: Option<u128>,
+    },
+    /// Replace the list of addresses allowed to call SimulateRace
+    /// Must be called by the config admin (the admin is always allowed)
+    UpdateAuthorizedCallers {
+        authorized_callers: Vec<String>,
+    },
+    /// Remove a car's Q-table entries whose largest absolute action value is <= threshold
+    /// Must be called by the config admin
+    CompactQTable {
+        car_id: Uint128,
+        /// Compact the car's table for this track instead of its shared one
+        track_id: Option<u128>,
+        threshold: u32,
+    },
+    /// Drop the cached copy of a track so the next race re-fetches it from the track manager
+    /// Must be called by the config admin
+    InvalidateTrackCache {
+        track_id: Uint128,
+    },
+    /// Race without training where every `elimination_interval` ticks the trailing car
+    /// (least progress_towards_finish) is knocked out, until one car remains
+    SimulateEliminationRace {
+        track_id: Uint128,
+        car_ids: Vec<u128>,
+        elimination_interval: u32,
+    },
+    /// Time trial without training against the car's fastest finished run on the track
+    /// - The ghost doesn't block or show up in the live car's state, its run is returned for side-by-side playback
+    SimulateGhostRace {
+        track_id: Uint128,
+        car_id: u128,
+    },
+    /// Write the entries of a QTableExport into a car's Q-table, existing states are overwritten
+    /// Must be called by the config admin
+    ImportQTable {
+        car_id: Uint128,
+        /// Write into the car's table for this track instead of its shared one
+        track_id: Option<u128>,
+        export: QTableExport,
+    },
+}
+
+#[cw_serde]
+pub enum ResponseDetail {
+    /// Winners and rankings only
+    Summary,
+    /// Also include the play-by-play and steps taken
+    Full,
+}
+
+/// How a race's rankings are ordered
+#[cw_serde]
+#[derive(Default)]
+pub enum RankingMode {
+    /// Finished cars by steps taken, then unfinished cars by progress_towards_finish
+    #[default]
+    FinishedFirst,
+    /// One score for every car: progress_towards_finish if unfinished, the track's best progress short
+    /// of the finish line plus the ticks to spare if finished. Equal scores go to fewer steps taken, so
+    /// a car that stopped one tile short can rank above a finisher that needed every tick
+    ProgressUnified,
+}
+
+/// Response data for SimulateRace
+#[cw_serde]
+pub struct SimulateRaceResponse {
+    pub race_id: String,
+    pub winner_ids: Vec<u128>,
+    pub rankings: Vec<Rank>,
+    /// Only set for ResponseDetail::Full
+    pub play_by_play: Option<HashMap<u128, PlayByPlay>>,
+    /// Only set for ResponseDetail::Full
+    pub steps_taken: Option<Vec<Step>>,
+}
+
+/// A car's fastest finished run on a track
+#[cw_serde]
+pub struct Ghost {
+    pub race_id: String,
+    pub steps: u32,
+    pub play_by_play: PlayByPlay,
+}
+
+/// Response data for SimulateGhostRace
+#[cw_serde]
+pub struct GhostRaceResponse {
+    pub race_id: String,
+    pub car_id: u128,
+    /// None if the live car didn't finish
+    pub steps: Option<u32>,
+    pub ghost_steps: u32,
+    /// Finished in fewer steps than the ghost, the run becomes the new ghost
+    pub beat_ghost: bool,
+    pub play_by_play: PlayByPlay,
+    pub ghost: PlayByPlay,
 }
 
 #[cw_serde]
@@ -39,6 +186,15 @@ pub enum QueryMsg {
         track_id: u128,
         race_id: String,
      },
+    /// Look a race up by race_id alone, searching every track's and car's recent races
+    #[returns(RaceResultResponse)]
+    GetRaceResultById { race_id: String },
+    /// A race's rankings joined with each car's steps and finish flag, best rank first
+    #[returns(Vec<Standing>)]
+    GetRaceStandings {
+        race_id: String,
+        track_id: u128,
+    },
     #[returns(RecentRacesResponse)]
     ListRecentRaces {
         ///Must provide one of the following////
@@ -54,8 +210,16 @@ pub enum QueryMsg {
     },
     #[returns(ConfigResponse)]
     GetConfig {},
+    /// Q-values for one state, or a page of the car's whole table ordered by state hash
     #[returns(GetQResponse)]
-    GetQ { car_id: u128, state_hash: Option< [u8; 32]> },
+    GetQ {
+        car_id: u128,
+        /// Read the car's table for this track instead of its shared one
+        track_id: Option<u128>,
+        state_hash: Option< [u8; 32]>,
+        start_after: Option<[u8; 32]>,
+        limit: Option<u32>,
+    },
     #[returns(Vec<GetTrackTrainingStatsResponse>)]
     GetTrackTrainingStats { 
         car_id: u128, 
@@ -63,12 +227,220 @@ pub enum QueryMsg {
         start_after: Option<u128>,
         limit: Option<u32>,
     },
+    /// One track's training stats for several cars, in car_ids order
+    /// - Only the first 32 car_ids are looked up, cars without stats get the zeroed defaults
+    #[returns(Vec<GetTrackTrainingStatsResponse>)]
+    GetMultiCarStats {
+        car_ids: Vec<u128>,
+        track_id: u128,
+    },
+    /// Tick limit races on this track run with when SimulateRace doesn't override it.
+    /// Unfinished runs don't touch `fastest`, it stays u32::MAX until a car finishes within the limit.
+    #[returns(TimeoutValueResponse)]
+    GetTimeoutValue { track_id: u128 },
+    /// Number of stored Q-table states for a car and their estimated storage size
+    #[returns(QTableSizeResponse)]
+    GetQTableSize {
+        car_id: u128,
+        /// Size up the car's table for this track instead of its shared one
+        track_id: Option<u128>,
+    },
+    /// Races the car won among its recent races, most recent first
+    /// - Optionally filtered to one track
+    #[returns(CarWinsResponse)]
+    GetCarWins {
+        car_id: u128,
+        track_id: Option<u128>,
+        limit: Option<u32>,
+    },
+    /// Action the car would pick at this state when racing without exploration
+    /// - `best_action` is None and the values are zero if the car never visited the state
+    #[returns(BestActionResponse)]
+    GetBestAction {
+        car_id: u128,
+        /// Read the car's table for this track instead of its shared one
+        track_id: Option<u128>,
+        state_hash: [u8; 32],
+    },
+    /// What-if: the action the car would pick at this state under `strategy`, whatever it actually trains with
+    /// - `seed` is the race tick, mixed with car_id the same way a race does
+    /// - Unvisited states select over zeroed values, and no actions are masked
+    #[returns(ActionSelectionResponse)]
+    SimulateActionSelection {
+        car_id: u128,
+        /// Read the car's table for this track instead of its shared one
+        track_id: Option<u128>,
+        state_hash: [u8; 32],
+        strategy: ActionSelectionStrategy,
+        seed: u32,
+    },
+    /// Reward components summed over the car's last training race
+    #[returns(RewardBreakdownResponse)]
+    GetRewardBreakdown { car_id: u128 },
+    /// Training stats for a car rolled up across every track it raced on
+    #[returns(CarStatsSummaryResponse)]
+    GetCarStatsSummary { car_id: u128 },
+    /// Ids of every track the car has training stats on, ascending
+    #[returns(Vec<u128>)]
+    GetCarTracks { car_id: u128 },
+    /// A car's whole Q-table in a versioned envelope, for off-chain analysis or ImportQTable
+    #[returns(QTableExport)]
+    ExportQTable {
+        car_id: u128,
+        /// Export the car's table for this track instead of its shared one
+        track_id: Option<u128>,
+    },
+    /// A page of the car's greedy policy, the best action per state ordered by state hash
+    #[returns(Vec<PolicyEntry>)]
+    ExportPolicy {
+        car_id: u128,
+        /// Read the car's table for this track instead of its shared one
+        track_id: Option<u128>,
+        start_after: Option<[u8; 32]>,
+        limit: Option<u32>,
+    },
+    /// Re-run a race recorded with record_replay and compare its play-by-play hash
+    #[returns(VerifyReplayResponse)]
+    VerifyReplay { race_id: String, expected_hash: [u8; 32] },
+    /// How the car did in its last SimulateRace, None if it hasn't raced
+    #[returns(Option<LastRaceSummary>)]
+    GetLastRaceSummary { car_id: u128 },
+    /// Best guess from a recent race's play-by-play at why a car didn't finish, None if it finished
+    #[returns(Option<NonFinishReason>)]
+    GetNonFinishReason { race_id: String, car_id: u128 },
+    /// Totals across every SimulateRace, for monitoring
+    #[returns(EngineStatsResponse)]
+    GetEngineStats {},
+    /// What a recent SimulateRace was run with, to reproduce it for a bug report or dispute
+    /// - Errors with RaceNotFound once the race has dropped out of the recent races, or for other kinds of race
+    #[returns(RaceInputs)]
+    GetRaceInputs { race_id: String },
+}
+
+/// A SimulateRace's inputs with the defaults it ran with filled in
+/// - Sent again as a SimulateRace against the same Q-tables and failed_races, it gives the same RaceResult
+/// - There's no seed to send, randomness comes from the tick index and car ids
+#[cw_serde]
+pub struct RaceInputs {
+    pub track_id: Uint128,
+    pub car_ids: Vec<u128>,
+    pub train: bool,
+    pub training_config: TrainingConfig,
+    pub reward_config: RewardNumbers,
+    pub max_ticks: u32,
+    pub teams: Option<Vec<Vec<u128>>>,
+    pub end_on_first_finish: bool,
+    pub ranking_mode: RankingMode,
+    pub shuffle_order: bool,
+    /// Each car's failed_races on the track when it ran, which raise its epsilon over training_config's
+    #[serde(default)]
+    pub failed_races: Vec<(u128, u32)>,
+}
+
+#[cw_serde]
+pub struct VerifyReplayResponse {
+    pub race_id: String,
+    /// Whether the replayed play-by-play hashes to expected_hash
+    pub valid: bool,
+    /// Blake2b-256 of the replayed play-by-play, ordered by car id
+    pub replay_hash: [u8; 32],
+}
+
+#[cw_serde]
+pub struct CarStatsSummaryResponse {
+    pub car_id: u128,
+    pub solo_races: u32,
+    pub pvp_races: u32,
+    /// Win rate over all solo and PvP races as a percentage (0-100)
+    pub win_rate: u32,
+    /// Fastest completion time in ticks on any track, None if the car never raced
+    pub fastest: Option<u32>,
+}
+
+#[cw_serde]
+pub struct RewardBreakdownResponse {
+    pub car_id: u128,
+    /// None until the car has trained
+    pub breakdown: Option<RewardBreakdown>,
+}
+
+#[cw_serde]
+pub struct BestActionResponse {
+    pub car_id: u128,
+    pub state_hash: [u8; 32],
+    pub best_action: Option<usize>,
+    pub action_values: [i32; 4],
+}
+
+/// One row of ExportPolicy
+#[cw_serde]
+pub struct PolicyEntry {
+    pub state_hash: [u8; 32],
+    pub best_action: usize,
+}
+
+#[cw_serde]
+pub struct ActionSelectionResponse {
+    pub car_id: u128,
+    pub state_hash: [u8; 32],
+    pub action: usize,
+    pub action_values: [i32; 4],
+}
+
+#[cw_serde]
+pub struct CarWin {
+    pub race_id: String,
+    pub track_id: Uint128,
+    pub steps_taken: u32,
+}
+
+#[cw_serde]
+pub struct CarWinsResponse {
+    pub car_id: u128,
+    pub wins: Vec<CarWin>,
+}
+
+#[cw_serde]
+pub struct QTableSizeResponse {
+    pub car_id: u128,
+    pub entries: u64,
+    /// Sum over entries of (state key bytes + 16 bytes of action values)
+    pub estimated_bytes: u64,
+}
+
+#[cw_serde]
+pub struct EngineStatsResponse {
+    pub total_races: u64,
+    /// Distinct cars that have raced
+    pub total_cars: u64,
+    /// Entries in every car's shared and per-track Q-tables
+    pub total_q_entries: u64,
+    pub total_ticks: u64,
+    /// total_ticks / total_races rounded down, 0 before the first race
+    pub average_ticks: u64,
+}
+
+#[cw_serde]
+pub struct TimeoutValueResponse {
+    pub track_id: u128,
+    pub max_ticks: u32,
 }
 
 #[cw_serde]
 pub struct RaceResultResponse {
     pub result: RaceResult,
 }
+/// Portable snapshot of a Q-table
+#[cw_serde]
+pub struct QTableExport {
+    pub car_id: u128,
+    /// Q_TABLE_EXPORT_VERSION at export time
+    pub version: u32,
+    /// Encoding the state hashes were generated with, imports must match it
+    pub state_encoding: StateEncoding,
+    pub entries: Vec<QTableEntry>,
+}
+
 #[cw_serde]
 pub struct GetQResponse {
     pub car_id: u128,
@@ -122,6 +494,44 @@ pub struct PlayByPlay {
 pub struct Step {
     pub car_id: u128,
     pub steps_taken: u32,
+    /// False if the car ran out of ticks (or got stuck) instead of finishing
+    /// - Races recorded before racing 0.2.0 read as false
+    #[serde(default)]
+    pub finished: bool,
+}
+
+/// One row of GetRaceStandings
+#[cw_serde]
+pub struct Standing {
+    pub car_id: u128,
+    pub rank: u32,
+    pub steps: u32,
+    pub finished: bool,
+}
+
+/// Why a car didn't finish a race, checked in this order
+#[cw_serde]
+pub enum NonFinishReason {
+    /// Knocked out of an elimination race
+    Eliminated,
+    /// Ended the race going back and forth between two tiles
+    Oscillated,
+    /// Sat still for at least half its ticks, on sticky tiles or against walls
+    StuckOnTile,
+    /// Kept moving but never left a 2x2 patch of track
+    BoxedIn,
+    /// Made steady progress, just not enough to finish in time
+    RanOutOfTicks,
+}
+
+/// A car's result in its last race, kept apart from the full RaceResult
+#[cw_serde]
+pub struct LastRaceSummary {
+    pub race_id: String,
+    pub track_id: u128,
+    pub rank: u32,
+    pub steps: u32,
+    pub finished: bool,
 }
 
 #[cw_serde]
@@ -133,6 +543,13 @@ pub struct RaceResult {
     pub rankings: Vec<Rank>,
     pub play_by_play: HashMap<u128, PlayByPlay>,
     pub steps_taken: Vec<Step>,
+    /// Elimination races only: car ids in the order they were knocked out
+    pub eliminations: Option<Vec<u128>>,
+    /// Team races only: the teams as given, a team's id is its index
+    pub teams: Option<Vec<Vec<u128>>>,
+    /// Team races only: (team_id, score) best team first
+    /// - The score is the best individual rank in the team, lower is better
+    pub team_results: Option<Vec<(u32, u32)>>,
 }
 
 
@@ -144,6 +561,36 @@ pub struct CarState {
     pub x: i32,
     pub y: i32,
     pub stuck: bool,
+    // Turns left to sit out on a sticky tile, stuck clears when it reaches zero
+    #[serde(default)]
+    pub stuck_remaining: u8,
+    // Ticks left to wait before moving off a slow tile
+    #[serde(default)]
+    pub move_cooldown: u8,
+    // Standing at the race's halfway tick, set once the race is over
+    #[serde(default)]
+    pub midpoint_rank: Option<u32>,
+    // progress_towards_finish of the tile the car started the race on
+    #[serde(default)]
+    pub start_progress: u16,
+    // Cells driven this race, teleport hops excluded
+    #[serde(default)]
+    pub cells_traveled: u32,
+    // Actions in action_history already applied by online Q-updates
+    #[serde(default)]
+    pub q_updates_applied: usize,
+    // Kinds of tile the car drove onto this race, each listed once
+    #[serde(default)]
+    pub tile_kinds: Vec<TileKind>,
+    // Training races run on this track before this one without ever winning, 0 once the car has won
+    #[serde(default)]
+    pub failed_races: u32,
+    // Best progress on this track from earlier training races, None if it has no record yet
+    #[serde(default)]
+    pub best_progress: Option<u16>,
+    // Knocked out of an elimination race, the car stays put and other cars drive through it
+    #[serde(default)]
+    pub eliminated: bool,
     pub finished: bool,
     pub steps_taken: u32,
     pub last_action: usize,
@@ -151,18 +598,60 @@ pub struct CarState {
     pub action_history: Vec<( [u8; 32], usize, TrackTile)>, // (state_hash, action, tile)
     // **NEW**: Track wall collisions for reward calculation
     pub hit_wall: bool,
+    // Total wall collisions this race, used to break finishing ties
+    pub wall_hits: u32,
+    // Whether the car was drafting at the end of each action in action_history
+    pub drafting: Vec<bool>,
+    // Whether each action in action_history was the first arrival on a checkpoint
+    pub first_to_checkpoint: Vec<bool>,
+    // Whether each action in action_history steered around a car moving into the cell ahead
+    #[serde(default)]
+    pub evaded: Vec<bool>,
+    // Whether each action in action_history first beat best_progress
+    #[serde(default)]
+    pub new_best: Vec<bool>,
+    // What each action in action_history did, for the no-progress penalties
+    pub outcomes: Vec<ActionOutcome>,
     // **NEW**: Track speed modifiers
     pub current_speed: u32,
     // **NEW**: Store used Q-table for this car
     pub q_table:  Vec<QTableEntry>, 
 }
 
+/// What a single action did. The no-progress cases are mutually exclusive.
+#[cw_serde]
+pub enum ActionOutcome {
+    Moved,
+    /// Ran into a wall or the track edge
+    HitWall,
+    /// Stuck on a sticky tile, couldn't act
+    Stuck,
+    /// Stayed put without hitting a wall or being blocked
+    NoMove,
+    /// Another car held the cell it tried to move into
+    BlockedByCar,
+}
+
 #[cw_serde]
 pub struct RaceState {
     pub cars: Vec<CarState>,
     pub track_layout: Vec<Vec<TrackTile>>,
     pub tick: u32,
     pub play_by_play: std::collections::HashMap<u128, PlayByPlay>,
+    // Checkpoint tiles (x, y) a car has already reached this race
+    pub claimed_checkpoints: Vec<(i32, i32)>,
+    pub state_encoding: StateEncoding,
+    // Finish tile (x, y) positions, used for the finish direction in state hashes
+    pub finish_tiles: Vec<(i32, i32)>,
+    // Track whose Q-tables the cars use with Config.per_track_q, None for their shared tables
+    #[serde(default)]
+    pub q_track: Option<u128>,
+    // Config.distinguish_edges for the state hashes of this race
+    #[serde(default)]
+    pub distinguish_edges: bool,
+    // Shuffle the order cars are processed in each tick, see SimulateRace.shuffle_order
+    #[serde(default)]
+    pub shuffle_order: bool,
 }
 
 
@@ -173,12 +662,58 @@ pub struct Config {
     pub car_contract: String,
     pub max_ticks: u32,
     pub max_recent_races: u32,
+    /// Addresses allowed to call SimulateRace in addition to the admin (e.g. the trainer contract)
+    pub authorized_callers: Vec<String>,
+    pub state_encoding: StateEncoding,
+    /// Q-learning updates applied per storage write, None uses the default of 32
+    pub q_update_batch_size: Option<u32>,
+    /// Races read and write a Q-table per (car, track) instead of one per car
+    /// - The Q-table messages take an optional track_id to reach a per-track table, ResetQ clears them all
+    #[serde(default)]
+    pub per_track_q: bool,
+    /// State keys also hold the car's distance to the track edges, so a map edge doesn't look like an interior wall
+    /// - Off keeps the original key layout, turning it on leaves Q-tables learned without it unmatched
+    #[serde(default)]
+    pub distinguish_edges: bool,
 } 
 
 #[cw_serde]
+#[derive(Default)]
 pub struct TrainingConfig {
     pub training_mode: bool,
     pub epsilon: f32,
     pub temperature: f32,
     pub enable_epsilon_decay: bool,
+    /// Eligibility trace decay for TD(λ) updates (0-1), defaults to 0 (1-step Q-learning)
+    pub lambda: Option<f32>,
+    /// How Q-values are primed for states a car hasn't seen yet, defaults to Random
+    pub init_strategy: Option<QInitStrategy>,
+    /// Leave moves that would hit a wall out of action selection, unless every move would
+    #[serde(default)]
+    pub enable_action_masking: bool,
+    /// Sample softmax exploration with integer math instead of f32::exp, so every target picks the same actions
+    #[serde(default)]
+    pub deterministic_softmax: bool,
+    /// Apply Q-updates every this many ticks during the race instead of all at the end, so later ticks act on them
+    /// - Training races only, and rank and speed rewards still only reach the transitions left at the end
+    pub online_batch_ticks: Option<u32>,
+    /// Scale each step's reward by this to the power of its tick (0-1], so early decisions weigh more
+    /// - Separate from the Q-learning discount, defaults to no decay
+    pub reward_time_decay: Option<f32>,
+    /// Raise a car's epsilon by this for each race it has run on the track without ever winning there, up to 1
+    /// - Defaults to 0, every car explores at epsilon
+    pub failure_epsilon_step: Option<f32>,
+}
+
+/// Starting Q-values for a state that isn't in the car's Q-table
+#[cw_serde]
+#[derive(Default)]
+pub enum QInitStrategy {
+    /// Small random values
+    #[default]
+    Random,
+    /// Small random values plus a head start for moving onto the neighbouring tile closest to the finish
+    DistanceBiased,
+    /// All zeros, so what a car learns doesn't depend on its id or the tick, for reproducible tests
+    Zero,
 }
\ No newline at end of file
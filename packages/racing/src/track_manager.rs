@@ -8,6 +8,12 @@ pub struct InstantiateMsg {
     pub admin: String,
 }
 
+/// Recomputes every stored track's progress_towards_finish, tracks added before progress became higher-is-closer
+/// have it the other way round
+/// - The race engine keeps its own copies, invalidate its track cache for each track afterwards
+#[cw_serde]
+pub struct MigrateMsg {}
+
 #[cw_serde]
 pub enum ExecuteMsg {
     AddTrack {
@@ -16,6 +22,17 @@ pub enum ExecuteMsg {
         height: u8,
         layout: Vec<Vec<TileProperties>>,
     },
+    /// Set the race engine allowed to report track records
+    /// Must be called by the admin
+    SetRaceEngine {
+        race_engine: String,
+    },
+    /// Lower a track's fastest_tick_time to a finish the race engine saw
+    /// Must be called by the admin or the race engine, slower times are ignored
+    UpdateFastestTime {
+        track_id: Uint128,
+        fastest_tick_time: u64,
+    },
 }
 
 #[cw_serde]
@@ -28,6 +45,13 @@ pub enum QueryMsg {
         start_after: Option<u128>,
         limit: Option<u32>,
     },
+    /// Count how many tiles of each type make up a track's layout
+    #[returns(TrackTileSummaryResponse)]
+    GetTrackTileSummary { track_id: Uint128 },
+    /// Whether a car driving across the finish line can get back to a start tile
+    /// without crossing the finish again, i.e. the track can be raced for multiple laps
+    #[returns(IsCircuitResponse)]
+    IsCircuit { track_id: Uint128 },
 }
 
 // #[cw_serde]
@@ -38,4 +62,31 @@ pub enum QueryMsg {
 #[cw_serde]
 pub struct ListTracksResponse {
     pub tracks: Vec<Track>,
+}
+
+/// Per-type tile counts for a track.
+/// Each tile is counted once, in priority order: wall, finish, start, checkpoint, then mud, sticky, slow, boost,
+/// teleporter, one_way and damage by TileProperties::kind, and normal for the rest.
+#[cw_serde]
+pub struct TrackTileSummaryResponse {
+    pub track_id: u128,
+    pub wall: u32,
+    pub start: u32,
+    pub finish: u32,
+    pub checkpoint: u32,
+    /// Sticky tiles that also stun for stun_ticks
+    pub mud: u32,
+    pub sticky: u32,
+    pub slow: u32,
+    pub boost: u32,
+    pub teleporter: u32,
+    pub one_way: u32,
+    pub damage: u32,
+    pub normal: u32,
+}
+
+#[cw_serde]
+pub struct IsCircuitResponse {
+    pub track_id: u128,
+    pub is_circuit: bool,
 } 
\ No newline at end of file
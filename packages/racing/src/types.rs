@@ -12,6 +12,12 @@ pub struct CarMetadata {
     pub attributes: Option<Vec<CarAttribute>>,
     /// Optional on-chain car id (stringified), auto-populated by the contract
     pub car_id: Option<String>,
+    /// Address that minted the car, auto-populated by the contract
+    #[serde(default)]
+    pub creator: String,
+    /// Optional marketplace royalty in basis points (max 10000)
+    #[serde(default)]
+    pub royalty_bps: Option<u16>,
 }
 
 #[cw_serde]
@@ -61,14 +67,106 @@ pub struct RewardNumbers {
     pub explore: i32,
     /// Rank-based reward (0=1st place, 1=2nd place, etc.)
     pub rank: RankReward,
+    /// Cap on the finish speed reward, defaults to 100
+    pub max_speed_reward: Option<i32>,
+    /// Bonus for ending a tick moving forward right next to an opponent that's further ahead, defaults to 0
+    pub draft_bonus: Option<i32>,
+    /// Bonus for being the first car onto a checkpoint tile in a PvP race, defaults to 0
+    pub checkpoint_bonus: Option<i32>,
+    /// Multiplier on the distance penalty for moving away from the finish, defaults to 1
+    pub backward_penalty_multiplier: Option<i32>,
+    /// Penalty for a move blocked by another car (negative reward), defaults to no_move
+    pub collision_penalty: Option<i32>,
+    /// Floor on an action's summed reward before it reaches the Q update, defaults to unbounded
+    /// - The reward breakdown still reports the unclamped components
+    pub reward_min: Option<i32>,
+    /// Ceiling on an action's summed reward before it reaches the Q update, defaults to unbounded
+    pub reward_max: Option<i32>,
+    /// PvP only: reward per position gained by a car that was last at the halfway tick, on its final action, defaults to 0
+    pub comeback_bonus: Option<i32>,
+    /// Bonus for finishing along a direct path, defaults to 0
+    /// - Scaled by the start to finish progress span over the cells actually driven, so a straight run earns all of it
+    pub path_efficiency_bonus: Option<i32>,
+    /// Steps a car is expected to finish within, overtime_penalty_per_tick applies past it
+    pub target_ticks: Option<u32>,
+    /// Penalty per step taken past target_ticks (negative reward), on the final action, defaults to 0
+    pub overtime_penalty_per_tick: Option<i32>,
+    /// PvP only: bonus for turning off the car's heading when another car was moving into the cell ahead, and still moving, defaults to 0
+    pub evasion_bonus: Option<i32>,
+    /// Bonus per distinct TileKind a car drove onto during the race, credited at race end, defaults to 0
+    pub tile_variety_bonus: Option<i32>,
+    /// Penalty (negative) on the final action of a car that didn't finish a track it has never won, defaults to 0
+    pub frustration_penalty: Option<i32>,
+    /// Training only: bonus on the action that first takes a car past its best progress on the track in earlier races, defaults to 0
+    pub new_best_bonus: Option<i32>,
+}
+
+/// Reward split into the components calculate_action_reward adds up
+#[cw_serde]
+#[derive(Default)]
+pub struct RewardBreakdown {
+    pub rank: i32,
+    pub speed: i32,
+    pub wall: i32,
+    pub stuck: i32,
+    pub no_move: i32,
+    pub distance: i32,
+    pub draft: i32,
+    pub checkpoint: i32,
+    #[serde(default)]
+    pub collision: i32,
+    #[serde(default)]
+    pub comeback: i32,
+    #[serde(default)]
+    pub path_efficiency: i32,
+    #[serde(default)]
+    pub overtime: i32,
+    #[serde(default)]
+    pub evasion: i32,
+    #[serde(default)]
+    pub tile_variety: i32,
+    #[serde(default)]
+    pub frustration: i32,
+    #[serde(default)]
+    pub new_best: i32,
+}
+
+impl RewardBreakdown {
+    pub fn total(&self) -> i32 {
+        self.rank + self.speed + self.wall + self.stuck + self.no_move
+            + self.distance + self.draft + self.checkpoint + self.collision
+            + self.comeback + self.path_efficiency + self.overtime + self.evasion + self.tile_variety + self.frustration
+            + self.new_best
+    }
+
+    pub fn add(&mut self, other: &RewardBreakdown) {
+        self.rank += other.rank;
+        self.speed += other.speed;
+        self.wall += other.wall;
+        self.stuck += other.stuck;
+        self.no_move += other.no_move;
+        self.distance += other.distance;
+        self.draft += other.draft;
+        self.checkpoint += other.checkpoint;
+        self.collision += other.collision;
+        self.comeback += other.comeback;
+        self.path_efficiency += other.path_efficiency;
+        self.overtime += other.overtime;
+        self.evasion += other.evasion;
+        self.tile_variety += other.tile_variety;
+        self.frustration += other.frustration;
+        self.new_best += other.new_best;
+    }
 }
 
 #[cw_serde]
 pub struct RankReward {
-    pub first: i32,
-    pub second: i32,
-    pub third: i32,
+    /// Reward per finishing position, ranks[0] for 1st place, ranks[1] for 2nd, ...
+    pub ranks: Vec<i32>,
+    /// Reward for positions past the end of ranks
     pub other: i32,
+    /// PvP only: extra reward for the winner per step it finished ahead of the runner-up, defaults to 0
+    pub margin_bonus: Option<i32>,
 }
 
 #[cw_serde]
@@ -83,9 +181,12 @@ pub struct TrackTrainingStats {
 pub struct TrainingStats {
     /// Total number of training runs
     pub tally: u32,
-    /// Win rate as a percentage (0-100)
+    /// Number of runs won
+    #[serde(default)]
+    pub wins: u32,
+    /// Win rate as a percentage (0-100), wins * 100 / tally
     pub win_rate: u32,
-    /// Fastest completion time in ticks
+    /// Fastest finished run in ticks, u32::MAX until the car finishes
     pub fastest: u32,
 }
 
@@ -117,8 +218,29 @@ pub struct TileProperties {
     pub is_finish: bool,
     /// Whether this tile is a start line
     pub is_start: bool,
+    /// Whether this tile is a checkpoint (mid-race objective)
+    #[serde(default)]
+    pub is_checkpoint: bool,
+    /// Turns a car sits out after entering this tile, 0 with `skip_next_turn` means one
+    #[serde(default)]
+    pub stun_ticks: u8,
+    /// Partner tile a car is moved to on landing here, ignored if it's a wall or off the track
+    #[serde(default)]
+    pub teleport_target: Option<(u8, u8)>,
+    /// Directions a car may leave this tile in, one bit per DIRECTION_* flag, 0 allows all
+    #[serde(default)]
+    pub allowed_directions: u8,
+    /// Ticks a car waits after entering this tile before it moves again, 1 halves its speed
+    #[serde(default)]
+    pub slow_ticks: u8,
 }
 
+/// Bit flags for `TileProperties::allowed_directions`
+pub const DIRECTION_UP: u8 = 1 << 0;
+pub const DIRECTION_DOWN: u8 = 1 << 1;
+pub const DIRECTION_LEFT: u8 = 1 << 2;
+pub const DIRECTION_RIGHT: u8 = 1 << 3;
+
 impl Default for TileProperties {
     fn default() -> Self {
         Self {
@@ -128,6 +250,11 @@ impl Default for TileProperties {
             damage: 0,
             is_finish: false,
             is_start: false,
+            is_checkpoint: false,
+            stun_ticks: 0,
+            teleport_target: None,
+            allowed_directions: 0,
+            slow_ticks: 0,
         }
     }
 }
@@ -148,14 +275,14 @@ impl TileProperties {
         }
     }
 
-    //No more slow tiles bc normal speed is 1
-    /// Create a slow tile
-    // pub fn slow(speed_modifier: u32) -> Self {
-    //     Self {
-    //         speed_modifier,
-    //         ..Default::default()
-    //     }
-    // }
+    /// Create a slow tile, cars move once every slow_ticks + 1 ticks while on it
+    /// - Normal speed is already 1, so slowing down is a wait between moves instead of a lower speed_modifier
+    pub fn slow(slow_ticks: u8) -> Self {
+        Self {
+            slow_ticks,
+            ..Default::default()
+        }
+    }
 
     /// Create a sticky tile
     pub fn sticky() -> Self {
@@ -165,6 +292,31 @@ impl TileProperties {
         }
     }
 
+    /// Create a sticky tile that stuns for several turns (e.g., a mud pit)
+    pub fn mud(stun_ticks: u8) -> Self {
+        Self {
+            skip_next_turn: true,
+            stun_ticks,
+            ..Default::default()
+        }
+    }
+
+    /// Create a teleporter that sends cars to (x, y)
+    pub fn teleporter(x: u8, y: u8) -> Self {
+        Self {
+            teleport_target: Some((x, y)),
+            ..Default::default()
+        }
+    }
+
+    /// Create a one-way tile that can only be left in the given DIRECTION_* flags
+    pub fn one_way(allowed_directions: u8) -> Self {
+        Self {
+            allowed_directions,
+            ..Default::default()
+        }
+    }
+
     /// Create a wall tile
     pub fn wall() -> Self {
         Self {
@@ -189,6 +341,14 @@ impl TileProperties {
         }
     }
 
+    /// Create a checkpoint tile
+    pub fn checkpoint() -> Self {
+        Self {
+            is_checkpoint: true,
+            ..Default::default()
+        }
+    }
+
     /// Create a damage tile (e.g., spikes)
     pub fn damage(damage_amount: i32) -> Self {
         Self {
@@ -204,13 +364,46 @@ impl TileProperties {
             ..Default::default()
         }
     }
+
+    /// The feature a car driving onto this tile experiences, the first match in TileKind order
+    /// - Start, finish and checkpoint tiles drive like normal ones
+    pub fn kind(&self) -> TileKind {
+        if self.skip_next_turn || self.stun_ticks > 0 {
+            TileKind::Sticky
+        } else if self.slow_ticks > 0 {
+            TileKind::Slow
+        } else if self.speed_modifier > 1 {
+            TileKind::Boost
+        } else if self.teleport_target.is_some() {
+            TileKind::Teleporter
+        } else if self.allowed_directions != 0 {
+            TileKind::OneWay
+        } else if self.damage != 0 {
+            TileKind::Damage
+        } else {
+            TileKind::Normal
+        }
+    }
+}
+
+/// Kinds of drivable tile, see TileProperties::kind
+#[cw_serde]
+pub enum TileKind {
+    Sticky,
+    Slow,
+    Boost,
+    Teleporter,
+    OneWay,
+    Damage,
+    Normal,
 }
 
 #[cw_serde]
 pub struct TrackTile {
     /// Properties of the track tile
     pub properties: TileProperties,
-    /// Progress towards the finish line in positions
+    /// Progress towards the finish line in positions, higher is closer to the finish
+    /// - Rankings, collision priority and the progress-based rewards all rely on this direction
     pub progress_towards_finish: u16,
     /// x position of the tile
     pub x: u8,
@@ -306,6 +499,7 @@ pub enum ActionSelectionStrategy {
     Random,                     // Pure exploration
     EpsilonGreedy(f32),         // Exploration with ε chance
     Softmax(f32),               // Probabilistic based on Q-values
+    DeterministicSoftmax(u32),  // Softmax in integer math, temperature in thousandths
     EpsilonDecay {              // Epsilon that decays over training progress
         initial_epsilon: f32,   // Starting epsilon value
         final_epsilon: f32,     // Final epsilon value